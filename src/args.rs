@@ -1,90 +1,459 @@
+use std::ops::Range;
 use std::path::Path;
+use crate::compression::Compression;
+use crate::digest::DigestAlgorithm;
+use crate::encryptor::Verification;
+use crate::crypto_utils::{COMPRESSION_DEFAULT_LEVEL, PARITY_DEFAULT_PERCENT, VOLUME_MIN_SIZE};
+use crate::key_derivation::Kdf;
+use crate::metadata;
+use crate::padding::{self, Padding};
+use crate::parity::Parity;
+use crate::passgen::{self, PassStyle};
+use crate::password_policy::{PasswordPolicy, PolicyMode};
+use crate::password_source::PasswordSource;
+use crate::secret::SecretString;
+use crate::volume;
 
 #[derive(Debug, PartialEq)]
-pub enum Op { Enc, Dec }
+pub enum Op { Enc, Dec, Verify, Info, Upgrade, GenPass, Seal, Open, SopsEncrypt, SopsDecrypt, ExecEnv, GitFilter, GitInit, Edit, Cat, Repair }
+
+impl Op {
+    /// 可以一次处理多个输入文件、且不另行写出文件的操作
+    fn is_batch(&self) -> bool {
+        matches!(self, Op::Verify | Op::Info | Op::Upgrade)
+    }
+
+    /// 写出单个输出文件（可用 `-o` 指定）的操作
+    fn writes_output(&self) -> bool {
+        matches!(self, Op::Enc | Op::Dec | Op::SopsEncrypt | Op::SopsDecrypt | Op::Repair)
+    }
+
+    /// 以新密码写出密文、接受 KDF 与密码策略等选项的操作
+    fn encrypts(&self) -> bool {
+        matches!(self, Op::Enc | Op::Upgrade | Op::Seal | Op::SopsEncrypt)
+    }
+
+    /// 第一个参数是输入文件的操作（`open` 的第一个参数是令牌，`git-filter` 的是 clean 或 smudge）
+    fn reads_file(&self) -> bool {
+        !matches!(self, Op::GenPass | Op::Seal | Op::Open | Op::GitFilter | Op::GitInit)
+    }
+}
 
 #[derive(Debug)]
 pub struct Args {
     pub op: Op,
     pub input_path: String,
     pub output_path: String,
-    pub password: Option<String>,
+    pub password: Option<PasswordSource>,
     pub quiet: bool,
+    pub kdf: Option<Kdf>,
+    /// `--compress[=LEVEL]`
+    pub compression: Option<Compression>,
+    /// `--pad SCHEME`
+    pub padding: Option<Padding>,
+    /// 全部输入文件（`verify`、`info`、`upgrade` 可批量处理），第一个即 `input_path`
+    pub input_paths: Vec<String>,
+    pub json: bool,
+    /// `upgrade` 时设置新密码
+    pub change_password: bool,
+    /// `upgrade` 时即使已是当前格式也重新加密
+    pub force: bool,
+    /// 加密时（含 `upgrade --change-password`）的密码策略
+    pub policy: PasswordPolicy,
+    /// 生成随机密码（`genpass` 总是生成）
+    pub generate_password: bool,
+    pub pass_style: PassStyle,
+    /// 生成的密码另存到此文件（0600）
+    pub password_out: Option<String>,
+    /// 加密时保存原文件名、权限与修改时间（`--no-metadata` 关闭）
+    pub metadata: bool,
+    /// 元数据中一并保存扩展属性
+    pub xattrs: bool,
+    /// 加密输出使用随机文件名
+    pub random_name: bool,
+    /// 解密时以文件中记录的原文件名保存
+    pub restore_name: bool,
+    /// 加密输出 ASCII 封装
+    pub armor: bool,
+    /// `seal` 或 `-e --text` 的明文；未指定时从标准输入读取
+    pub text: Option<SecretString>,
+    /// `exec-env` 在 `--` 之后的命令及其参数
+    pub command: Vec<String>,
+    /// `git-init` 要加密的路径模式（写入 `.gitattributes`）
+    pub patterns: Vec<String>,
+    /// `cat --range START:END` 只输出明文中的这部分字节
+    pub range: Option<Range<u64>>,
+    /// 加密并校验成功后删除源文件
+    pub remove_source: bool,
+    /// 删除源文件前先以随机数据覆盖
+    pub shred: bool,
+    /// `--verify[=mac|content]` 加密后重新读取输出自检
+    pub verify: Option<Verification>,
+    /// `--digest[=sha256|blake3]` 在加密文件中保存明文摘要
+    pub digest: Option<DigestAlgorithm>,
+    /// `--parity[=PERCENT]` 加密后附加 Reed-Solomon 纠错数据
+    pub parity: Option<Parity>,
+    /// `--split SIZE` 把输出拆成每个不超过 SIZE 的分卷
+    pub split: Option<u64>,
 }
-pub fn parse_args(args: &Vec<String>) -> Result<Args, String> {
-    // 标准：~ -e file （两个往上）
-    if args.len() < 2 {
+pub fn parse_args(args: &[String]) -> Result<Args, String> {
+    if args.is_empty() {
         return Err("arg too short".to_string());
     }
 
     // 参数分类
-    let op = {
+    let mut op = {
         match args[0].as_str() {
+            "genpass" => { Op::GenPass }
             "-e" | "--encrypt" => { Op::Enc }
             "-d" | "--decrypt" => { Op::Dec }
+            "verify" => { Op::Verify }
+            "info" => { Op::Info }
+            "upgrade" => { Op::Upgrade }
+            "seal" => { Op::Seal }
+            "open" => { Op::Open }
+            "sops-encrypt" => { Op::SopsEncrypt }
+            "sops-decrypt" => { Op::SopsDecrypt }
+            "exec-env" => { Op::ExecEnv }
+            "git-filter" => { Op::GitFilter }
+            "git-init" => { Op::GitInit }
+            "edit" => { Op::Edit }
+            "cat" => { Op::Cat }
+            "repair" => { Op::Repair }
             _ => {
                 return Err("unknown operation".to_string())
             }
         }
     };
 
-    // 获取 输出文件路径
-    let input_path = args[1].clone();
+    // `-e --text SECRET` 加密短字符串，等同于 `seal --text SECRET`
+    if op == Op::Enc && args.get(1).is_some_and(|a| a == "--text") {
+        op = Op::Seal;
+    }
+
+    // 标准：~ -e file （两个往上）；genpass、seal 没有输入文件，open 的参数是令牌（`-` 为标准输入）
+    let first_option = if matches!(op, Op::GenPass | Op::Seal | Op::GitInit) { 1 } else { 2 };
+    if args.len() < first_option {
+        return Err("arg too short".to_string());
+    }
+
+    // 获取 输入文件路径
+    let input_path = if first_option == 1 { String::new() } else { args[1].clone() };
 
     // 检查 输入文件 是否存在
-    if !Path::new(&input_path).exists() {
+    if op.reads_file() && !Path::new(&input_path).exists() {
         return Err("no such file".to_string())
     }
+    if op == Op::GitFilter && input_path != "clean" && input_path != "smudge" {
+        return Err("git-filter needs clean or smudge".to_string());
+    }
 
     let mut quiet = false;
     let mut output_path: Option<String> = None;
-    let mut password: Option<String> = None;
-
-    if args.len() > 2 {
-        let mut skip = false;
-        let mut i: usize = 2;
-        for v in &args[2..] {
-            i += 1;
-            if skip { skip = false; continue; }
-            match v.as_str() {
-                "-q" | "--quiet" => { quiet = true; }
-
-                "-p" | "--password" => {
-                    if password == None {
-                        password = Some(args[i].clone());
-                        skip = true;
-                    } else {
-                        return Err("one password option only".to_string());
+    let mut password: Option<PasswordSource> = None;
+    let mut kdf: Option<Kdf> = None;
+    let mut compression: Option<Compression> = None;
+    let mut padding: Option<Padding> = None;
+    let mut input_paths = vec![input_path.clone()];
+    let mut json = false;
+    let mut change_password = false;
+    let mut force = false;
+    let mut policy = PasswordPolicy::default();
+    let mut generate_password = op == Op::GenPass;
+    let mut pass_style: Option<PassStyle> = None;
+    let mut password_out: Option<String> = None;
+    let mut metadata = true;
+    let mut xattrs = false;
+    let mut random_name = false;
+    let mut restore_name = false;
+    let mut armor = false;
+    let mut text: Option<SecretString> = None;
+    let mut command: Vec<String> = Vec::new();
+    let mut patterns: Vec<String> = Vec::new();
+    let mut range: Option<Range<u64>> = None;
+    let mut remove_source = false;
+    let mut shred = false;
+    let mut verify: Option<Verification> = None;
+    let mut digest: Option<DigestAlgorithm> = None;
+    let mut parity: Option<Parity> = None;
+    let mut split: Option<u64> = None;
+
+    let mut i: usize = first_option;
+    while i < args.len() {
+        let v = args[i].as_str();
+        i += 1;
+
+        match v {
+            "-q" | "--quiet" => { quiet = true; }
+
+            "-p" | "--password" | "--password-env" | "--password-file" | "--password-fd" | "--password-command" => {
+                if password.is_some() {
+                    return Err("one password option only".to_string());
+                }
+                let value = take_value(args, &mut i, v)?;
+                password = Some(match v {
+                    "--password-env" => PasswordSource::Env(value),
+                    "--password-file" => PasswordSource::File(value),
+                    "--password-fd" => PasswordSource::Fd(value.parse().map_err(|_| format!("bad file descriptor: {}", value))?),
+                    "--password-command" => PasswordSource::Command(value),
+                    _ => PasswordSource::Literal(SecretString::from(value)),
+                });
+            }
+
+            "-o" | "--output" => {
+                if !op.writes_output() {
+                    return Err("no output for this operation".to_string());
+                }
+                if output_path.is_none() {
+                    output_path = Some(take_value(args, &mut i, v)?);
+                } else {
+                    return Err("one output option only".to_string());
+                }
+            }
+
+            "--kdf" => {
+                if !op.encrypts() && op != Op::GitInit {
+                    return Err("--kdf is for encryption only".to_string());
+                }
+                if kdf.is_none() {
+                    kdf = Some(Kdf::parse(&take_value(args, &mut i, v)?)?);
+                } else {
+                    return Err("one kdf option only".to_string());
+                }
+            }
+
+            _ if v == "--compress" || v.starts_with("--compress=") => {
+                content_option(&op, "--compress")?;
+                let level = match v.strip_prefix("--compress=") {
+                    Some(level) => Compression::parse_level(level)?,
+                    None => COMPRESSION_DEFAULT_LEVEL,
+                };
+                compression = Some(Compression::new(level));
+            }
+
+            "--range" if op == Op::Cat => {
+                if range.is_none() {
+                    range = Some(parse_range(&take_value(args, &mut i, v)?)?);
+                } else {
+                    return Err("one range option only".to_string());
+                }
+            }
+
+            _ if v == "--digest" || v.starts_with("--digest=") => {
+                content_option(&op, "--digest")?;
+                digest = Some(match v.strip_prefix("--digest=") {
+                    Some(name) => DigestAlgorithm::parse(name)?,
+                    None => DigestAlgorithm::Sha256,
+                });
+            }
+
+            "--pad" => {
+                content_option(&op, "--pad")?;
+                if padding.is_none() {
+                    padding = Some(Padding::parse(&take_value(args, &mut i, v)?)?);
+                } else {
+                    return Err("one padding option only".to_string());
+                }
+            }
+
+            "--json" if op == Op::Info => { json = true; }
+
+            "--min-score" | "--min-length" | "--forbidden-words" | "--password-policy" => {
+                if !op.encrypts() {
+                    return Err(format!("{} is for encryption only", v));
+                }
+                let value = take_value(args, &mut i, v)?;
+                match v {
+                    "--min-score" => {
+                        policy.min_score = value.parse().ok().filter(|s| *s <= 4)
+                            .ok_or_else(|| format!("bad minimum score: {}", value))?;
+                    }
+                    "--min-length" => {
+                        policy.min_length = value.parse().map_err(|_| format!("bad minimum length: {}", value))?;
                     }
+                    "--forbidden-words" => {
+                        policy.forbidden_words.extend(PasswordPolicy::load_forbidden_words(&value)?);
+                    }
+                    _ => policy.mode = PolicyMode::parse(&value)?,
                 }
+            }
 
-                "-o" | "--output" => {
-                    if output_path == None {
-                        output_path = Some(args[i].clone());
-                        skip = true;
-                    } else {
-                        return Err("one output option only".to_string());
+            "--generate-password" if op == Op::Enc => { generate_password = true; }
+
+            "--words" | "--chars" if op == Op::Enc || op == Op::GenPass => {
+                if pass_style.is_some() {
+                    return Err("one of --words or --chars only".to_string());
+                }
+                let value = take_value(args, &mut i, v)?;
+                let count: usize = value.parse().map_err(|_| format!("bad {} value: {}", v, value))?;
+                pass_style = Some(if v == "--words" {
+                    if !(passgen::MIN_WORDS..=passgen::MAX_WORDS).contains(&count) {
+                        return Err(format!("--words must be {}-{}", passgen::MIN_WORDS, passgen::MAX_WORDS));
                     }
+                    PassStyle::Words(count)
+                } else {
+                    if !(passgen::MIN_CHARS..=passgen::MAX_CHARS).contains(&count) {
+                        return Err(format!("--chars must be {}-{}", passgen::MIN_CHARS, passgen::MAX_CHARS));
+                    }
+                    PassStyle::Chars(count)
+                });
+            }
+
+            "--password-out" if op == Op::Enc || op == Op::GenPass => {
+                if password_out.is_none() {
+                    password_out = Some(take_value(args, &mut i, v)?);
+                } else {
+                    return Err("one password output only".to_string());
+                }
+            }
+
+            "--no-metadata" if op == Op::Enc => { metadata = false; }
+
+            "--xattrs" if op == Op::Enc => { xattrs = true; }
+
+            "--random-name" if op == Op::Enc => { random_name = true; }
+
+            "--restore-name" if op == Op::Dec => { restore_name = true; }
+
+            "--armor" if op == Op::Enc => { armor = true; }
+
+            "--remove-source" if op == Op::Enc => { remove_source = true; }
+
+            _ if op == Op::Enc && (v == "--verify" || v.starts_with("--verify=")) => {
+                verify = Some(match v.strip_prefix("--verify=") {
+                    Some(mode) => Verification::parse(mode)?,
+                    None => Verification::Mac,
+                });
+            }
+
+            "--shred" if op == Op::Enc => { shred = true; }
+
+            "--split" if op == Op::Enc => {
+                let size = padding::parse_size(&take_value(args, &mut i, v)?)?;
+                if size < VOLUME_MIN_SIZE {
+                    return Err("--split must be at least 4K".to_string());
                 }
+                split = Some(size);
+            }
+
+            _ if op == Op::Enc && (v == "--parity" || v.starts_with("--parity=")) => {
+                parity = Some(match v.strip_prefix("--parity=") {
+                    Some(percent) => Parity::parse(percent)?,
+                    None => Parity::with_percent(PARITY_DEFAULT_PERCENT)?,
+                });
+            }
 
-                _ => {
-                    return Err("unknown option".to_string());
+            "--text" if op == Op::Seal => {
+                if text.is_none() {
+                    text = Some(SecretString::from(take_value(args, &mut i, v)?));
+                } else {
+                    return Err("one --text option only".to_string());
                 }
             }
+
+            // 之后的参数都属于要执行的命令
+            "--" if op == Op::ExecEnv => {
+                command = args[i..].to_vec();
+                i = args.len();
+            }
+
+            "--change-password" if op == Op::Upgrade => { change_password = true; }
+
+            "--force" if op == Op::Upgrade => { force = true; }
+
+            _ if op == Op::GitInit && !v.starts_with('-') => { patterns.push(v.to_string()); }
+
+            // verify、info、upgrade 可以接受多个输入文件
+            _ if op.is_batch() && !v.starts_with('-') => {
+                if !Path::new(v).exists() {
+                    return Err("no such file".to_string());
+                }
+                input_paths.push(v.to_string());
+            }
+
+            // sops-encrypt 只加密各个值，作用于整个文件的选项没有意义
+            _ if op == Op::SopsEncrypt && v.starts_with("--") => {
+                return Err(format!("{} is not supported by sops-encrypt", v.split('=').next().unwrap_or(v)));
+            }
+
+            _ => {
+                return Err("unknown option".to_string());
+            }
         }
     }
 
+    // 生成密码相关的选项需要 --generate-password，且不能与其他密码来源同时使用
+    if !generate_password && (pass_style.is_some() || password_out.is_some()) {
+        return Err("--words, --chars and --password-out need --generate-password".to_string());
+    }
+    if generate_password && password.is_some() {
+        return Err("one password option only".to_string());
+    }
+
+    // 过滤器命令之后由 git 调用，密码来源必须能重新读取
+    if op == Op::GitInit {
+        if patterns.is_empty() {
+            return Err("git-init needs at least one path pattern".to_string());
+        }
+        if password.as_ref().is_none_or(|p| p.to_option().is_none()) {
+            return Err("git-init needs --password-env, --password-file or --password-command".to_string());
+        }
+    }
+
+    if op == Op::ExecEnv && command.is_empty() {
+        return Err("exec-env needs a command after --".to_string());
+    }
+
+    if shred && !remove_source {
+        return Err("--shred needs --remove-source".to_string());
+    }
+    if armor && parity.is_some() {
+        return Err("--parity cannot be used with --armor".to_string());
+    }
+    if armor && split.is_some() {
+        return Err("--split cannot be used with --armor".to_string());
+    }
+
+    // 输出文件名由 --random-name / --restore-name 决定时，不能再指定 -o
+    if (random_name || restore_name) && output_path.is_some() {
+        return Err("-o cannot be used with --random-name or --restore-name".to_string());
+    }
+    if !metadata && (xattrs || random_name) {
+        return Err("--xattrs and --random-name need the metadata block".to_string());
+    }
+
     // 当未指定 输出文件路径 时
-    if output_path == None {
+    if output_path.is_none() {
         match op {
-            Op::Enc => output_path = Some(format!("{}.decx", input_path)),
+            Op::Enc => {
+                let path = if random_name {
+                    let dir = Path::new(&input_path).parent().unwrap_or(Path::new(""));
+                    dir.join(metadata::random_file_name()).to_string_lossy().into_owned()
+                } else {
+                    format!("{}.decx", input_path)
+                };
+                // ASCII 封装的输出再追加 .asc
+                output_path = Some(if armor { format!("{}.asc", path) } else { path });
+            }
+            // 输出路径在解密并验证后才能确定
+            Op::Dec if restore_name => output_path = Some(String::new()),
             Op::Dec => {
-                if input_path.ends_with(".decx") {
-                    output_path = Some(input_path[..input_path.len() - 5].to_string());
+                // 分卷按整组命名：file.decx.001 → file
+                let input_path = match volume::split_volume_name(&input_path) {
+                    Some((base, _)) if base.ends_with(".decx") => base,
+                    _ => input_path.as_str(),
+                };
+                if let Some(stem) = input_path.strip_suffix(".decx.asc") {
+                    output_path = Some(stem.to_string());
+                } else if let Some(stem) = input_path.strip_suffix(".decx") {
+                    output_path = Some(stem.to_string());
                 } else {
                     output_path = Some(format!("{}.out", input_path));
                 }
             }
+            // 结构化加密、解密未指定 -o 时写到标准输出
+            Op::Verify | Op::Info | Op::Upgrade | Op::GenPass | Op::Seal | Op::Open
+                | Op::SopsEncrypt | Op::SopsDecrypt | Op::ExecEnv | Op::GitFilter | Op::GitInit | Op::Edit | Op::Cat | Op::Repair => output_path = Some(String::new()),
         }
     }
 
@@ -93,7 +462,37 @@ pub fn parse_args(args: &Vec<String>) -> Result<Args, String> {
         _ => unreachable!()
     };
 
-    Ok(Args { op, input_path, output_path: output, password, quiet })
+    Ok(Args { op, input_path, output_path: output, password, quiet, kdf, compression, padding, input_paths, json, change_password, force, policy,
+        generate_password, pass_style: pass_style.unwrap_or_default(), password_out, metadata, xattrs, random_name, restore_name, armor, text, command, patterns, range, remove_source, shred, verify, digest, parity, split })
+}
+
+/// 压缩、填充与明文摘要作用于整个明文，只对写出完整密文的操作有效
+fn content_option(op: &Op, option: &str) -> Result<(), String> {
+    if !op.encrypts() {
+        return Err(format!("{} is for encryption only", option));
+    }
+    if *op == Op::SopsEncrypt {
+        return Err(format!("{} is not supported by sops-encrypt", option));
+    }
+    Ok(())
+}
+
+/// 解析 `START:END` 字节范围（不含 END），两端可带 K/M/G/T 后缀，省略时分别为开头与末尾
+fn parse_range(value: &str) -> Result<Range<u64>, String> {
+    let (start, end) = value.split_once(':').ok_or_else(|| format!("bad range: {}", value))?;
+    let start = if start.is_empty() { 0 } else { padding::parse_size(start)? };
+    let end = if end.is_empty() { u64::MAX } else { padding::parse_size(end)? };
+    if start > end {
+        return Err(format!("bad range: {}", value));
+    }
+    Ok(start..end)
+}
+
+/// 取出选项的值，并前移下标
+fn take_value(args: &[String], i: &mut usize, option: &str) -> Result<String, String> {
+    let value = args.get(*i).cloned().ok_or_else(|| format!("missing value for {}", option))?;
+    *i += 1;
+    Ok(value)
 }
 
 #[cfg(test)]
@@ -115,7 +514,7 @@ mod tests {
         let parsed_args = result.unwrap();
         assert_eq!(parsed_args.op, Op::Enc);
         assert_eq!(parsed_args.output_path, format!("{}.decx", test_file.path().to_str().unwrap()));
-        assert_eq!(parsed_args.quiet, false);
+        assert!(!parsed_args.quiet);
     }
 
     #[test]
@@ -138,8 +537,438 @@ mod tests {
         let parsed_args = result.unwrap();
         assert_eq!(parsed_args.op, Op::Dec);
         assert_eq!(parsed_args.output_path, "custom_output.txt");
-        assert_eq!(parsed_args.password, Some("testpassword".to_string()));
-        assert_eq!(parsed_args.quiet, true);
+        assert!(matches!(&parsed_args.password, Some(PasswordSource::Literal(p)) if p.expose() == "testpassword"));
+        assert!(parsed_args.quiet);
+    }
+
+    #[test]
+    fn test_parse_args_kdf() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+
+        let args = vec!["-e".to_string(), path.clone(), "--kdf".to_string(), "scrypt:n=14".to_string()];
+        let parsed_args = parse_args(&args).unwrap();
+        assert_eq!(parsed_args.kdf, Some(Kdf::Scrypt { log_n: 14, r: 8, p: 1 }));
+
+        // 解密时 KDF 由文件头决定
+        let args = vec!["-d".to_string(), path.clone(), "--kdf".to_string(), "scrypt".to_string()];
+        assert!(parse_args(&args).is_err());
+
+        // 缺少选项值
+        let args = vec!["-e".to_string(), path, "--kdf".to_string()];
+        assert_eq!(parse_args(&args).unwrap_err(), "missing value for --kdf");
+    }
+
+    #[test]
+    fn test_parse_args_compress_and_pad() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+
+        let parsed_args = parse_args(&["-e".to_string(), path.clone()]).unwrap();
+        assert_eq!(parsed_args.compression, None);
+
+        let parsed_args = parse_args(&["-e".to_string(), path.clone(), "--compress".to_string()]).unwrap();
+        assert_eq!(parsed_args.compression, Some(Compression::new(COMPRESSION_DEFAULT_LEVEL)));
+
+        let parsed_args = parse_args(&["-e".to_string(), path.clone(), "--compress=19".to_string()]).unwrap();
+        assert_eq!(parsed_args.compression, Some(Compression::new(19)));
+
+        assert!(parse_args(&["-e".to_string(), path.clone(), "--compress=0".to_string()]).is_err());
+        assert!(parse_args(&["-d".to_string(), path.clone(), "--compress".to_string()]).is_err());
+
+        let parsed_args = parse_args(&["-e".to_string(), path.clone(), "--pad".to_string(), "bucket:4K".to_string()]).unwrap();
+        assert_eq!(parsed_args.padding, Some(Padding::Bucket(4096)));
+        assert_eq!(parse_args(&["-e".to_string(), path, "--pad".to_string(), "x".to_string()]).unwrap_err(), "unknown padding: x");
+    }
+
+    #[test]
+    fn test_parse_args_metadata() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let parsed_args = parse_args(&to_args(&["-e", &path])).unwrap();
+        assert!(parsed_args.metadata);
+        assert!(!parsed_args.xattrs);
+
+        let parsed_args = parse_args(&to_args(&["-e", &path, "--no-metadata"])).unwrap();
+        assert!(!parsed_args.metadata);
+
+        // 随机文件名与输入文件在同一目录
+        let parsed_args = parse_args(&to_args(&["-e", &path, "--random-name", "--xattrs"])).unwrap();
+        assert!(parsed_args.random_name && parsed_args.xattrs);
+        let output = Path::new(&parsed_args.output_path);
+        assert_eq!(output.parent(), test_file.path().parent());
+        assert!(output.extension().is_some_and(|e| e == "decx"));
+
+        let parsed_args = parse_args(&to_args(&["-d", &path, "--restore-name"])).unwrap();
+        assert!(parsed_args.restore_name);
+        assert_eq!(parsed_args.output_path, "");
+
+        assert!(parse_args(&to_args(&["-d", &path, "--restore-name", "-o", "x"])).is_err());
+        assert!(parse_args(&to_args(&["-e", &path, "--random-name", "--no-metadata"])).is_err());
+        assert!(parse_args(&to_args(&["-d", &path, "--random-name"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_remove_source() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let parsed_args = parse_args(&to_args(&["-e", &path])).unwrap();
+        assert!(!parsed_args.remove_source && !parsed_args.shred);
+
+        let parsed_args = parse_args(&to_args(&["-e", &path, "--remove-source", "--shred"])).unwrap();
+        assert!(parsed_args.remove_source && parsed_args.shred);
+
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--shred"])).unwrap_err(), "--shred needs --remove-source");
+        assert!(parse_args(&to_args(&["-d", &path, "--remove-source"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_verify_encryption() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        assert_eq!(parse_args(&to_args(&["-e", &path])).unwrap().verify, None);
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--verify"])).unwrap().verify, Some(Verification::Mac));
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--verify=content"])).unwrap().verify, Some(Verification::Content));
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--verify=all"])).unwrap_err(), "unknown verification: all");
+        assert!(parse_args(&to_args(&["-d", &path, "--verify"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_digest() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        assert_eq!(parse_args(&to_args(&["-e", &path])).unwrap().digest, None);
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--digest"])).unwrap().digest, Some(DigestAlgorithm::Sha256));
+        assert_eq!(parse_args(&to_args(&["upgrade", &path, "--digest=blake3"])).unwrap().digest, Some(DigestAlgorithm::Blake3));
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--digest=md5"])).unwrap_err(), "unknown digest: md5");
+        assert!(parse_args(&to_args(&["-d", &path, "--digest"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_armor() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let parsed_args = parse_args(&to_args(&["-e", &path, "--armor"])).unwrap();
+        assert!(parsed_args.armor);
+        assert_eq!(parsed_args.output_path, format!("{}.decx.asc", path));
+
+        // 解密时自动识别，不需要 --armor
+        assert!(parse_args(&to_args(&["-d", &path, "--armor"])).is_err());
+
+        let armored = tempfile::Builder::new().suffix(".decx.asc").tempfile().unwrap();
+        let armored_path = armored.path().to_str().unwrap().to_string();
+        let parsed_args = parse_args(&to_args(&["-d", &armored_path])).unwrap();
+        assert_eq!(parsed_args.output_path, armored_path.strip_suffix(".decx.asc").unwrap());
+    }
+
+    #[test]
+    fn test_parse_args_seal_open() {
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        // `-e --text` 不需要输入文件
+        let parsed_args = parse_args(&to_args(&["-e", "--text", "s3cret", "--kdf", "pbkdf2"])).unwrap();
+        assert_eq!(parsed_args.op, Op::Seal);
+        assert_eq!(parsed_args.text.as_ref().map(|t| t.expose()), Some("s3cret"));
+        assert_eq!(parsed_args.kdf, Some(Kdf::Pbkdf2Sha256 { iterations: 600_000 }));
+
+        // 未指定 --text 时从标准输入读取
+        let parsed_args = parse_args(&to_args(&["seal", "--min-score", "3"])).unwrap();
+        assert_eq!(parsed_args.op, Op::Seal);
+        assert!(parsed_args.text.is_none());
+        assert_eq!(parsed_args.policy.min_score, 3);
+
+        assert_eq!(parse_args(&to_args(&["-e", "--text"])).unwrap_err(), "missing value for --text");
+        assert!(parse_args(&to_args(&["seal", "-o", "out"])).is_err());
+
+        let parsed_args = parse_args(&to_args(&["open", "dec:AAAA", "-p", "pw"])).unwrap();
+        assert_eq!(parsed_args.op, Op::Open);
+        assert_eq!(parsed_args.input_path, "dec:AAAA");
+        assert!(parse_args(&to_args(&["open", "-"])).is_ok());
+        assert_eq!(parse_args(&to_args(&["open", "dec:AAAA", "--kdf", "pbkdf2"])).unwrap_err(), "--kdf is for encryption only");
+        assert_eq!(parse_args(&to_args(&["open"])).unwrap_err(), "arg too short");
+    }
+
+    #[test]
+    fn test_parse_args_structured() {
+        let config = tempfile::Builder::new().suffix(".yaml").tempfile().unwrap();
+        let path = config.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let parsed_args = parse_args(&to_args(&["sops-encrypt", &path, "--kdf", "scrypt", "--min-length", "12"])).unwrap();
+        assert_eq!(parsed_args.op, Op::SopsEncrypt);
+        assert_eq!(parsed_args.output_path, "");
+        assert_eq!(parsed_args.policy.min_length, 12);
+
+        let parsed_args = parse_args(&to_args(&["sops-decrypt", &path, "-o", "plain.yaml"])).unwrap();
+        assert_eq!(parsed_args.op, Op::SopsDecrypt);
+        assert_eq!(parsed_args.output_path, "plain.yaml");
+        assert!(parse_args(&to_args(&["sops-decrypt", &path, "--kdf", "scrypt"])).is_err());
+
+        // `--` 之后的参数原样交给命令
+        let parsed_args = parse_args(&to_args(&["exec-env", &path, "--password-env", "PW", "--", "env", "-q", "--"])).unwrap();
+        assert_eq!(parsed_args.op, Op::ExecEnv);
+        assert_eq!(parsed_args.command, ["env", "-q", "--"]);
+        assert!(parsed_args.password.is_some());
+
+        assert_eq!(parse_args(&to_args(&["exec-env", &path])).unwrap_err(), "exec-env needs a command after --");
+        assert_eq!(parse_args(&to_args(&["exec-env", &path, "--"])).unwrap_err(), "exec-env needs a command after --");
+        assert!(parse_args(&to_args(&["exec-env", &path, "-o", "x", "--", "env"])).is_err());
+        assert_eq!(parse_args(&to_args(&["sops-encrypt", "missing.yaml"])).unwrap_err(), "no such file");
+        // 作用于整个文件的选项被拒绝，而不是被忽略
+        for option in ["--compress", "--compress=9", "--pad", "--digest=blake3", "--armor", "--parity", "--split", "--verify"] {
+            let args = to_args(&["sops-encrypt", &path, option, "padme"]);
+            let name = option.split('=').next().unwrap();
+            assert_eq!(parse_args(&args).unwrap_err(), format!("{} is not supported by sops-encrypt", name));
+        }
+    }
+
+    #[test]
+    fn test_parse_args_git() {
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let parsed_args = parse_args(&to_args(&["git-filter", "clean", "--password-env", "PW"])).unwrap();
+        assert_eq!(parsed_args.op, Op::GitFilter);
+        assert_eq!(parsed_args.input_path, "clean");
+        assert_eq!(parse_args(&to_args(&["git-filter", "diff"])).unwrap_err(), "git-filter needs clean or smudge");
+
+        let parsed_args = parse_args(&to_args(&["git-init", "secrets/**", "*.key", "--password-file", "/k", "--kdf", "scrypt"])).unwrap();
+        assert_eq!(parsed_args.op, Op::GitInit);
+        assert_eq!(parsed_args.patterns, ["secrets/**", "*.key"]);
+        assert_eq!(parsed_args.kdf, Kdf::from_name("scrypt"));
+
+        // 密码来源会写进 git 配置，-p 与 --password-fd 不行
+        let error = "git-init needs --password-env, --password-file or --password-command";
+        assert_eq!(parse_args(&to_args(&["git-init", "*.key", "-p", "pw"])).unwrap_err(), error);
+        assert_eq!(parse_args(&to_args(&["git-init", "*.key"])).unwrap_err(), error);
+        assert_eq!(parse_args(&to_args(&["git-init", "--password-env", "PW"])).unwrap_err(), "git-init needs at least one path pattern");
+    }
+
+    #[test]
+    fn test_parse_args_cat() {
+        let file = create_test_file("log.decx");
+        let path = file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let parsed_args = parse_args(&to_args(&["cat", &path])).unwrap();
+        assert_eq!(parsed_args.op, Op::Cat);
+        assert_eq!(parsed_args.range, None);
+
+        let parsed_args = parse_args(&to_args(&["cat", &path, "--range", "1K:2K"])).unwrap();
+        assert_eq!(parsed_args.range, Some(1024..2048));
+        assert_eq!(parse_args(&to_args(&["cat", &path, "--range", "100:"])).unwrap().range, Some(100..u64::MAX));
+        assert_eq!(parse_args(&to_args(&["cat", &path, "--range", ":100"])).unwrap().range, Some(0..100));
+
+        assert_eq!(parse_args(&to_args(&["cat", &path, "--range", "5:1"])).unwrap_err(), "bad range: 5:1");
+        assert_eq!(parse_args(&to_args(&["cat", &path, "--range", "100"])).unwrap_err(), "bad range: 100");
+        assert!(parse_args(&to_args(&["-d", &path, "--range", "0:1"])).is_err());
+        assert!(parse_args(&to_args(&["cat", &path, "-o", "out"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_edit() {
+        let file = create_test_file("notes.decx");
+        let path = file.path().to_str().unwrap().to_string();
+
+        let parsed_args = parse_args(&["edit".to_string(), path.clone(), "-p".to_string(), "pw".to_string()]).unwrap();
+        assert_eq!(parsed_args.op, Op::Edit);
+        assert_eq!(parsed_args.input_path, path);
+
+        // 就地替换，不写出其他文件
+        assert!(parse_args(&["edit".to_string(), path, "-o".to_string(), "out".to_string()]).is_err());
+        assert!(parse_args(&["edit".to_string(), "/no/such/file".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_parity() {
+        let file = create_test_file("photos.tar");
+        let path = file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        assert_eq!(parse_args(&to_args(&["-e", &path])).unwrap().parity, None);
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--parity"])).unwrap().parity, Some(Parity::with_percent(10).unwrap()));
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--parity=25%"])).unwrap().parity.unwrap().parity_shards, 16);
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--parity=abc"])).unwrap_err(), "bad parity: abc");
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--parity", "--armor"])).unwrap_err(), "--parity cannot be used with --armor");
+        assert!(parse_args(&to_args(&["-d", &path, "--parity"])).is_err());
+
+        let parsed_args = parse_args(&to_args(&["repair", &path])).unwrap();
+        assert_eq!(parsed_args.op, Op::Repair);
+        assert_eq!(parsed_args.output_path, "");
+        assert_eq!(parse_args(&to_args(&["repair", &path, "-o", "healed.decx"])).unwrap().output_path, "healed.decx");
+    }
+
+    #[test]
+    fn test_parse_args_split() {
+        let file = create_test_file("backup.tar");
+        let path = file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let parsed_args = parse_args(&to_args(&["-e", &path, "--split", "4G"])).unwrap();
+        assert_eq!(parsed_args.split, Some(4 << 30));
+        assert_eq!(parsed_args.output_path, format!("{}.decx", path));
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--split", "1K"])).unwrap_err(), "--split must be at least 4K");
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--split", "x"])).unwrap_err(), "bad size: x");
+        assert_eq!(parse_args(&to_args(&["-e", &path, "--split", "1M", "--armor"])).unwrap_err(), "--split cannot be used with --armor");
+
+        // 解密分卷时输出按整组命名
+        let volume = tempfile::Builder::new().suffix(".decx.001").tempfile().unwrap();
+        let volume_path = volume.path().to_str().unwrap().to_string();
+        let parsed_args = parse_args(&to_args(&["-d", &volume_path])).unwrap();
+        assert_eq!(parsed_args.output_path, volume_path.strip_suffix(".decx.001").unwrap());
+        assert!(parse_args(&to_args(&["-d", &volume_path, "--split", "1M"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_verify_batch() {
+        let file1 = create_test_file("a.decx");
+        let file2 = create_test_file("b.decx");
+        let path1 = file1.path().to_str().unwrap().to_string();
+        let path2 = file2.path().to_str().unwrap().to_string();
+
+        let args = vec!["verify".to_string(), path1.clone(), path2.clone(), "-p".to_string(), "pw".to_string()];
+        let parsed_args = parse_args(&args).unwrap();
+        assert_eq!(parsed_args.op, Op::Verify);
+        assert_eq!(parsed_args.input_paths, vec![path1.clone(), path2]);
+        assert!(matches!(&parsed_args.password, Some(PasswordSource::Literal(p)) if p.expose() == "pw"));
+
+        // verify 不写出文件
+        let args = vec!["verify".to_string(), path1.clone(), "-o".to_string(), "out".to_string()];
+        assert!(parse_args(&args).is_err());
+
+        // info 支持 JSON 输出
+        let args = vec!["info".to_string(), path1.clone(), "--json".to_string()];
+        let parsed_args = parse_args(&args).unwrap();
+        assert_eq!(parsed_args.op, Op::Info);
+        assert!(parsed_args.json);
+
+        // 批量中的文件同样需要存在
+        let args = vec!["verify".to_string(), path1, "nonexistent.decx".to_string()];
+        assert_eq!(parse_args(&args).unwrap_err(), "no such file");
+    }
+
+    #[test]
+    fn test_parse_args_password_sources() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+        let parse = |extra: &[&str]| {
+            let mut args = vec!["-e".to_string(), path.clone()];
+            args.extend(extra.iter().map(|s| s.to_string()));
+            parse_args(&args)
+        };
+
+        let parsed_args = parse(&["--password-env", "DEC_PASSWORD"]).unwrap();
+        assert!(matches!(parsed_args.password, Some(PasswordSource::Env(ref name)) if name == "DEC_PASSWORD"));
+
+        let parsed_args = parse(&["--password-file", "/run/secrets/pw"]).unwrap();
+        assert!(matches!(parsed_args.password, Some(PasswordSource::File(ref p)) if p == "/run/secrets/pw"));
+
+        let parsed_args = parse(&["--password-fd", "3"]).unwrap();
+        assert!(matches!(parsed_args.password, Some(PasswordSource::Fd(3))));
+
+        let parsed_args = parse(&["--password-command", "pass show dec"]).unwrap();
+        assert!(matches!(parsed_args.password, Some(PasswordSource::Command(ref c)) if c == "pass show dec"));
+
+        assert_eq!(parse(&["--password-fd", "x"]).unwrap_err(), "bad file descriptor: x");
+        assert_eq!(parse(&["-p", "pw", "--password-env", "X"]).unwrap_err(), "one password option only");
+    }
+
+    #[test]
+    fn test_parse_args_password_policy() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+        let words = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(words.path(), "acme\n").unwrap();
+
+        let args = vec![
+            "-e".to_string(), path.clone(),
+            "--min-score".to_string(), "3".to_string(),
+            "--min-length".to_string(), "12".to_string(),
+            "--forbidden-words".to_string(), words.path().to_str().unwrap().to_string(),
+            "--password-policy".to_string(), "refuse".to_string(),
+        ];
+        let parsed_args = parse_args(&args).unwrap();
+        assert_eq!(parsed_args.policy, PasswordPolicy {
+            min_score: 3,
+            min_length: 12,
+            forbidden_words: vec!["acme".to_string()],
+            mode: PolicyMode::Refuse,
+        });
+
+        // 默认只警告
+        let parsed_args = parse_args(&["-e".to_string(), path.clone()]).unwrap();
+        assert_eq!(parsed_args.policy.mode, PolicyMode::Warn);
+
+        let args = vec!["-e".to_string(), path.clone(), "--min-score".to_string(), "5".to_string()];
+        assert_eq!(parse_args(&args).unwrap_err(), "bad minimum score: 5");
+
+        let args = vec!["-d".to_string(), path, "--min-length".to_string(), "5".to_string()];
+        assert_eq!(parse_args(&args).unwrap_err(), "--min-length is for encryption only");
+    }
+
+    #[test]
+    fn test_parse_args_generate_password() {
+        let test_file = create_test_file("test_input.txt");
+        let path = test_file.path().to_str().unwrap().to_string();
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        // genpass 不需要输入文件
+        let parsed_args = parse_args(&to_args(&["genpass"])).unwrap();
+        assert_eq!(parsed_args.op, Op::GenPass);
+        assert!(parsed_args.generate_password);
+        assert_eq!(parsed_args.pass_style, PassStyle::Words(passgen::DEFAULT_WORDS));
+
+        let parsed_args = parse_args(&to_args(&["genpass", "--chars", "32", "--password-out", "pw.txt"])).unwrap();
+        assert_eq!(parsed_args.pass_style, PassStyle::Chars(32));
+        assert_eq!(parsed_args.password_out, Some("pw.txt".to_string()));
+
+        let parsed_args = parse_args(&to_args(&["-e", &path, "--generate-password", "--words", "10"])).unwrap();
+        assert!(parsed_args.generate_password);
+        assert_eq!(parsed_args.pass_style, PassStyle::Words(10));
+
+        assert_eq!(parse_args(&to_args(&["genpass", "--words", "2"])).unwrap_err(), "--words must be 4-64");
+        assert!(parse_args(&to_args(&["-e", &path, "--words", "10"])).is_err());
+        assert_eq!(
+            parse_args(&to_args(&["-e", &path, "--generate-password", "-p", "pw"])).unwrap_err(),
+            "one password option only"
+        );
+        assert!(parse_args(&to_args(&["-d", &path, "--generate-password"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_upgrade() {
+        let file1 = create_test_file("a.decx");
+        let file2 = create_test_file("b.decx");
+        let path1 = file1.path().to_str().unwrap().to_string();
+        let path2 = file2.path().to_str().unwrap().to_string();
+
+        let args = vec![
+            "upgrade".to_string(), path1.clone(), path2.clone(),
+            "--kdf".to_string(), "pbkdf2".to_string(), "--change-password".to_string(), "--force".to_string()
+        ];
+        let parsed_args = parse_args(&args).unwrap();
+        assert_eq!(parsed_args.op, Op::Upgrade);
+        assert_eq!(parsed_args.input_paths, vec![path1.clone(), path2]);
+        assert_eq!(parsed_args.kdf, Some(Kdf::Pbkdf2Sha256 { iterations: 600_000 }));
+        assert!(parsed_args.change_password);
+        assert!(parsed_args.force);
+
+        // 就地替换，不接受输出路径
+        let args = vec!["upgrade".to_string(), path1.clone(), "-o".to_string(), "out".to_string()];
+        assert!(parse_args(&args).is_err());
+
+        // 仅 upgrade 可用
+        let args = vec!["-e".to_string(), path1, "--force".to_string()];
+        assert_eq!(parse_args(&args).unwrap_err(), "unknown option");
     }
 
     #[test]
@@ -0,0 +1,430 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use crate::crypto_utils::*;
+use crate::parity::{self, ParityReader};
+use crate::volume::{self, VolumeReader};
+
+/// 可读取并定位的二进制加密流
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// 打开加密文件：自动识别 ASCII 封装与纠错封装，返回二进制内容的读取器、长度，以及是否为 ASCII 封装
+///
+/// 带纠错封装的文件边读边修复，修复的段报告到标准错误。
+pub fn open(path: &Path) -> io::Result<(Box<dyn ReadSeek>, u64, bool)> {
+    open_with_report(path, true)
+}
+
+/// 同 [`open`]，但不报告修复（只读取文件头的场合，随后的完整读取会报告）
+pub fn open_quietly(path: &Path) -> io::Result<(Box<dyn ReadSeek>, u64, bool)> {
+    open_with_report(path, false)
+}
+
+fn open_with_report(path: &Path, report: bool) -> io::Result<(Box<dyn ReadSeek>, u64, bool)> {
+    let mut file = File::open(path)?;
+
+    // 分卷连成一个流后，其中仍可能是 ASCII 封装或纠错封装
+    if volume::is_volume(path, &mut file)? {
+        let mut reader = VolumeReader::open(path)?;
+        if parity::is_parity(&mut reader)? {
+            reader.tolerate_damage(report);
+        }
+        open_stream(reader, report)
+    } else {
+        open_stream(file, report)
+    }
+}
+
+fn open_stream<R: Read + Seek + 'static>(mut reader: R, report: bool) -> io::Result<(Box<dyn ReadSeek>, u64, bool)> {
+    if is_armored(&mut reader)? {
+        let reader = ArmorReader::new(BufReader::new(reader))?;
+        let length = reader.length;
+        Ok((Box::new(reader), length, true))
+    } else if parity::is_parity(&mut reader)? {
+        let reader = ParityReader::new(reader, report)?;
+        let length = reader.length();
+        Ok((Box::new(reader), length, false))
+    } else {
+        let length = reader.seek(SeekFrom::End(0))?;
+        reader.rewind()?;
+        Ok((Box::new(reader), length, false))
+    }
+}
+
+/// 流是否以 ASCII 封装的起始行开头（允许前导空白），读取后回到开头
+pub fn is_armored<R: Read + Seek>(reader: &mut R) -> io::Result<bool> {
+    let mut head = Vec::with_capacity(ARMOR_SCAN_LENGTH);
+    reader.by_ref().take(ARMOR_SCAN_LENGTH as u64).read_to_end(&mut head)?;
+    reader.rewind()?;
+
+    Ok(head.trim_ascii_start().starts_with(ARMOR_BEGIN.as_bytes()))
+}
+
+/// OpenPGP 的 CRC-24（RFC 4880 6.1），用于尽早发现传输中损坏的字符
+fn crc24_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= ARMOR_CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xff_ffff
+}
+
+fn armor_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("ASCII 封装损坏: {}", message))
+}
+
+/// ASCII 封装写入器：起始行 + 每行 64 个 base64 字符 + `=` 开头的 CRC-24 行 + 结束行
+pub struct ArmorWriter<W: Write> {
+    writer: W,
+    pending: Vec<u8>,
+    crc: u32,
+}
+
+impl<W: Write> ArmorWriter<W> {
+    /// 写出起始行
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{}", ARMOR_BEGIN)?;
+        Ok(Self { writer, pending: Vec::with_capacity(ARMOR_LINE_BYTES), crc: ARMOR_CRC24_INIT })
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        let mut line = STANDARD.encode(&self.pending);
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// 写出最后一行、校验和与结束行，返回内部写入器
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            self.write_line()?;
+        }
+        writeln!(self.writer, "={}", STANDARD.encode(&self.crc.to_be_bytes()[1..]))?;
+        writeln!(self.writer, "{}", ARMOR_END)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for ArmorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes = std::cmp::min(buf.len(), ARMOR_LINE_BYTES - self.pending.len());
+        self.pending.extend_from_slice(&buf[..bytes]);
+        self.crc = crc24_update(self.crc, &buf[..bytes]);
+
+        if self.pending.len() == ARMOR_LINE_BYTES {
+            self.write_line()?;
+        }
+        Ok(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// ASCII 封装读取器：按固定行宽直接定位到任意行解码，无需读入整个文件
+///
+/// 除最后一行外每行都是 64 个字符（48 字节），因此可以支持 `Seek`；
+/// 从头顺序读完时校验 CRC-24。
+pub struct ArmorReader<R: Read + Seek> {
+    reader: R,
+    /// 第一行 base64 在原文件中的偏移
+    data_start: u64,
+    /// 行尾长度（`\n` 或 `\r\n`）
+    line_ending: u64,
+    /// 满行（64 个字符）的行数，其后还有一行（可能也是满行）
+    full_lines: u64,
+    /// 最后一行的字符数
+    last_chars: usize,
+    /// 解码后的总长度
+    length: u64,
+    expected_crc: u32,
+    /// 解码后的读取位置
+    position: u64,
+    /// 底层读取器的位置，连续读取时避免重新定位
+    raw_position: u64,
+    /// 已解码的当前行
+    line: [u8; ARMOR_LINE_BYTES],
+    line_index: Option<u64>,
+    /// 从头累计的校验和，及其已覆盖的长度；读取跳过未覆盖的部分时先补算
+    crc: u32,
+    crc_position: u64,
+}
+
+impl<R: Read + Seek> ArmorReader<R> {
+    /// 解析起始行与文件末尾的校验和、结束行，计算解码后的长度
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let size = reader.seek(SeekFrom::End(0))?;
+
+        // 起始行（允许前导空白），并由其确定行尾
+        let mut head = Vec::with_capacity(ARMOR_SCAN_LENGTH);
+        reader.seek(SeekFrom::Start(0))?;
+        Read::by_ref(&mut reader).take(ARMOR_SCAN_LENGTH as u64).read_to_end(&mut head)?;
+        let begin = head.len() - head.trim_ascii_start().len();
+        let after_marker = begin + ARMOR_BEGIN.len();
+        if !head[begin..].starts_with(ARMOR_BEGIN.as_bytes()) {
+            return Err(armor_error("缺少起始行"));
+        }
+        let line_ending: u64 = match &head[after_marker..] {
+            [b'\r', b'\n', ..] => 2,
+            [b'\n', ..] => 1,
+            _ => return Err(armor_error("起始行格式错误")),
+        };
+        let data_start = (after_marker as u64) + line_ending;
+
+        // 读取末尾：结束行、校验和行、最后一行 base64
+        let tail_start = std::cmp::max(data_start, size.saturating_sub(ARMOR_SCAN_LENGTH as u64));
+        let mut tail = Vec::new();
+        reader.seek(SeekFrom::Start(tail_start))?;
+        reader.read_to_end(&mut tail)?;
+
+        let tail = tail.trim_ascii_end();
+        let before_end = tail.strip_suffix(ARMOR_END.as_bytes()).ok_or_else(|| armor_error("缺少结束行"))?;
+        let mut lines = before_end.split(|&b| b == b'\n').rev().map(|l| l.strip_suffix(b"\r").unwrap_or(l));
+        let _ = lines.next().filter(|l| l.is_empty()).ok_or_else(|| armor_error("结束行格式错误"))?;
+
+        let checksum_line = lines.next().filter(|l| l.starts_with(b"=")).ok_or_else(|| armor_error("缺少校验和"))?;
+        let checksum = STANDARD.decode(&checksum_line[1..]).ok().filter(|c| c.len() == 3).ok_or_else(|| armor_error("校验和格式错误"))?;
+        let expected_crc = u32::from_be_bytes([0, checksum[0], checksum[1], checksum[2]]);
+
+        // 校验和行之前若紧接起始行，则内容为空
+        let checksum_line_start = (tail_start + before_end.len() as u64)
+            .checked_sub(line_ending + checksum_line.len() as u64)
+            .ok_or_else(|| armor_error("内容过短"))?;
+        let (full_lines, last_chars, last_bytes) = if checksum_line_start == data_start {
+            (0, 0, 0)
+        } else {
+            let last = lines.next().ok_or_else(|| armor_error("内容过短"))?;
+            let last_start = checksum_line_start.saturating_sub(line_ending + last.len() as u64);
+            let stride = ARMOR_LINE_CHARS as u64 + line_ending;
+            if last.is_empty() || last.len() > ARMOR_LINE_CHARS || !last.len().is_multiple_of(4)
+                || last_start < data_start || !(last_start - data_start).is_multiple_of(stride) {
+                return Err(armor_error("行宽不一致"));
+            }
+            let padding = last.iter().rev().take_while(|&&b| b == b'=').count();
+            let full_lines = (last_start - data_start) / stride;
+            (full_lines, last.len(), last.len() / 4 * 3 - padding)
+        };
+
+        reader.seek(SeekFrom::Start(0))?;
+        Ok(Self {
+            reader,
+            data_start,
+            line_ending,
+            full_lines,
+            last_chars,
+            length: full_lines * ARMOR_LINE_BYTES as u64 + last_bytes as u64,
+            expected_crc,
+            position: 0,
+            raw_position: 0,
+            line: [0u8; ARMOR_LINE_BYTES],
+            line_index: None,
+            crc: ARMOR_CRC24_INIT,
+            crc_position: 0,
+        })
+    }
+
+    /// 读取并解码第 `index` 行
+    fn load_line(&mut self, index: u64) -> io::Result<()> {
+        let is_full = index < self.full_lines;
+        let chars = if is_full { ARMOR_LINE_CHARS } else { self.last_chars };
+        let offset = self.data_start + index * (ARMOR_LINE_CHARS as u64 + self.line_ending);
+
+        if self.raw_position != offset {
+            self.reader.seek(SeekFrom::Start(offset))?;
+        }
+
+        // 满行连同行尾一起读取并检查
+        let mut raw = [0u8; ARMOR_LINE_CHARS + 2];
+        let raw_length = chars + if is_full { self.line_ending as usize } else { 0 };
+        self.reader.read_exact(&mut raw[..raw_length])?;
+        self.raw_position = offset + raw_length as u64;
+
+        let line_end = &raw[chars..raw_length];
+        if is_full && line_end != &b"\r\n"[2 - self.line_ending as usize..] {
+            return Err(armor_error("行宽不一致"));
+        }
+
+        let decoded = STANDARD.decode_slice(&raw[..chars], &mut self.line)
+            .map_err(|_| armor_error("非法的 base64 字符"))?;
+        if is_full && decoded != ARMOR_LINE_BYTES {
+            return Err(armor_error("行宽不一致"));
+        }
+
+        self.line_index = Some(index);
+        Ok(())
+    }
+
+    /// 把校验和累计到 `position`（读取解码后的第 `index` 行时 `line` 中已是该行），覆盖全部内容时比对
+    fn update_crc(&mut self, index: u64, position: u64) -> io::Result<()> {
+        let line_start = index * ARMOR_LINE_BYTES as u64;
+        if self.crc_position < line_start || self.crc_position >= position {
+            return Ok(());
+        }
+        let from = (self.crc_position - line_start) as usize;
+        let to = (position - line_start) as usize;
+        self.crc = crc24_update(self.crc, &self.line[from..to]);
+        self.crc_position = position;
+        if self.crc_position == self.length && self.crc != self.expected_crc {
+            return Err(armor_error("校验和不匹配"));
+        }
+        Ok(())
+    }
+
+    /// 定位后跳过的内容同样计入校验和，否则读到结尾时无法比对
+    fn catch_up_crc(&mut self, position: u64) -> io::Result<()> {
+        while self.crc_position < position {
+            let index = self.crc_position / ARMOR_LINE_BYTES as u64;
+            self.load_line(index)?;
+            let line_end = std::cmp::min((index + 1) * ARMOR_LINE_BYTES as u64, self.length);
+            self.update_crc(index, std::cmp::min(line_end, position))?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for ArmorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.length || buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.catch_up_crc(self.position)?;
+
+        let index = self.position / ARMOR_LINE_BYTES as u64;
+        if self.line_index != Some(index) {
+            self.load_line(index)?;
+        }
+
+        let line_bytes = std::cmp::min(ARMOR_LINE_BYTES as u64, self.length - index * ARMOR_LINE_BYTES as u64) as usize;
+        let within = (self.position % ARMOR_LINE_BYTES as u64) as usize;
+        let bytes = std::cmp::min(buf.len(), line_bytes - within);
+        buf[..bytes].copy_from_slice(&self.line[within..within + bytes]);
+
+        self.update_crc(index, self.position + bytes as u64)?;
+        self.position += bytes as u64;
+        Ok(bytes)
+    }
+}
+
+impl<R: Read + Seek> Seek for ArmorReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek"))?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn armor(data: &[u8]) -> Vec<u8> {
+        let mut writer = ArmorWriter::new(Vec::new()).unwrap();
+        // 分多次写入，覆盖跨行
+        for chunk in data.chunks(37) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_crc24() {
+        // RFC 4880 的初始值与多项式下，空输入即初始值
+        assert_eq!(crc24_update(ARMOR_CRC24_INIT, b""), ARMOR_CRC24_INIT);
+        assert_eq!(crc24_update(ARMOR_CRC24_INIT, b"123456789"), 0x21cf02);
+    }
+
+    #[test]
+    fn test_armor_roundtrip() {
+        for length in [0usize, 1, 47, 48, 49, 96, 1000] {
+            let data: Vec<u8> = (0..length).map(|i| (i * 7) as u8).collect();
+            let armored = armor(&data);
+
+            let text = String::from_utf8(armored.clone()).unwrap();
+            assert!(text.starts_with(ARMOR_BEGIN));
+            assert!(text.lines().all(|l| l.len() <= ARMOR_LINE_CHARS));
+
+            let mut reader = ArmorReader::new(Cursor::new(&armored)).unwrap();
+            assert_eq!(reader.length, length as u64);
+            let mut decoded = Vec::new();
+            reader.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, data, "length {}", length);
+
+            // CRLF 行尾与前导空白
+            let crlf = format!("\n  {}", text.replace('\n', "\r\n"));
+            let mut decoded = Vec::new();
+            ArmorReader::new(Cursor::new(crlf.as_bytes())).unwrap().read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, data);
+        }
+    }
+
+    #[test]
+    fn test_armor_seek() {
+        let data: Vec<u8> = (0..500u32).map(|i| (i % 251) as u8).collect();
+        let armored = armor(&data);
+        let mut reader = ArmorReader::new(Cursor::new(&armored)).unwrap();
+
+        let mut tail = [0u8; 8];
+        reader.seek(SeekFrom::End(-8)).unwrap();
+        reader.read_exact(&mut tail).unwrap();
+        assert_eq!(&tail, &data[492..]);
+
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        let mut middle = [0u8; 50];
+        reader.read_exact(&mut middle).unwrap();
+        assert_eq!(&middle[..], &data[100..150]);
+    }
+
+    #[test]
+    fn test_armor_corruption() {
+        let data = vec![0x5au8; 200];
+        let armored = armor(&data);
+        let text = String::from_utf8(armored).unwrap();
+
+        // 改动一个字符：校验和不匹配
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let replacement = if lines[2].starts_with('A') { "B" } else { "A" };
+        lines[2].replace_range(0..1, replacement);
+        let corrupted = lines.join("\n") + "\n";
+        let mut reader = ArmorReader::new(Cursor::new(corrupted.as_bytes())).unwrap();
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+        // 先定位到末尾再读取：跳过的内容同样计入校验和
+        let mut reader = ArmorReader::new(Cursor::new(corrupted.as_bytes())).unwrap();
+        reader.seek(SeekFrom::End(-8)).unwrap();
+        assert!(reader.read_exact(&mut [0u8; 8]).is_err());
+
+        // 重读已读过的部分不影响校验
+        let mut reader = ArmorReader::new(Cursor::new(armor(&data))).unwrap();
+        reader.read_exact(&mut [0u8; 10]).unwrap();
+        reader.seek(SeekFrom::Start(5)).unwrap();
+        let mut decoded = Vec::new();
+        reader.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, data[5..]);
+
+        // 重新折行：行宽不一致
+        let rewrapped = text.replacen('\n', "", 2).replacen(ARMOR_BEGIN, &format!("{}\n", ARMOR_BEGIN), 1);
+        assert!(ArmorReader::new(Cursor::new(rewrapped.as_bytes())).and_then(|mut r| r.read_to_end(&mut Vec::new())).is_err());
+
+        // 缺少结束行
+        let truncated = text.replace(ARMOR_END, "");
+        assert!(ArmorReader::new(Cursor::new(truncated.as_bytes())).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use std::io::{self, Read, Write};
+use rayon::prelude::*;
+use crate::crypto_utils::*;
+use crate::secret::SecretBytes;
+
+/// 压缩参数，记录在文件头中
+///
+/// 明文按 `block_size` 切块，每块独立压缩为一个 zstd 帧，前置 u32 小端长度：
+/// 各块可以并行压缩/解压，也可以只读取长度前缀跳到任意块。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    pub level: i32,
+    pub block_size: u32,
+}
+
+impl Compression {
+    pub fn new(level: i32) -> Self {
+        Self { level, block_size: COMPRESSION_BLOCK_SIZE }
+    }
+
+    /// 解析压缩级别
+    pub fn parse_level(value: &str) -> Result<i32, String> {
+        value.parse().ok()
+            .filter(|level| (COMPRESSION_MIN_LEVEL..=COMPRESSION_MAX_LEVEL).contains(level))
+            .ok_or_else(|| format!("compression level must be {}-{}", COMPRESSION_MIN_LEVEL, COMPRESSION_MAX_LEVEL))
+    }
+
+    /// 编码为文件头字段：算法标识 + 级别（i8）+ 块大小（u32 小端）
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![COMPRESSION_ID_ZSTD, self.level as i8 as u8];
+        out.extend_from_slice(&self.block_size.to_le_bytes());
+        out
+    }
+
+    /// 从文件头字段解码
+    pub fn decode(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        match bytes {
+            [COMPRESSION_ID_ZSTD, level, block_size @ ..] if block_size.len() == 4 => {
+                let block_size = u32::from_le_bytes(block_size.try_into()?);
+                if block_size == 0 || block_size > COMPRESSION_MAX_BLOCK_SIZE {
+                    return Err("压缩块大小异常".into());
+                }
+                Ok(Self { level: *level as i8 as i32, block_size })
+            }
+            [id, ..] => Err(format!("不支持的压缩算法: {}", id).into()),
+            [] => Err("压缩参数为空".into()),
+        }
+    }
+
+    /// 一个压缩块可能的最大长度
+    fn max_frame_length(&self) -> usize {
+        zstd::zstd_safe::compress_bound(self.block_size as usize)
+    }
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "zstd:level={},block={}", self.level, self.block_size)
+    }
+}
+
+/// 一批明文块与对应的压缩块
+struct Batch {
+    plain: Vec<SecretBytes>,
+    plain_lengths: Vec<usize>,
+    frames: Vec<SecretBytes>,
+    frame_lengths: Vec<usize>,
+}
+
+impl Batch {
+    /// 批大小取核心数，但总内存不超过 `COMPRESSION_BATCH_MEMORY`
+    fn new(compression: &Compression) -> Self {
+        let block_memory = compression.block_size as usize + compression.max_frame_length();
+        let parts = get_parts().min(COMPRESSION_BATCH_MEMORY / block_memory).max(1);
+        Self {
+            plain: (0..parts).map(|_| SecretBytes::zeroed(compression.block_size as usize)).collect(),
+            plain_lengths: vec![0; parts],
+            frames: (0..parts).map(|_| SecretBytes::zeroed(compression.max_frame_length())).collect(),
+            frame_lengths: vec![0; parts],
+        }
+    }
+}
+
+/// 压缩器：攒满一批块后并行压缩，按顺序写出“长度 + 帧”
+pub struct Compressor {
+    compression: Compression,
+    batch: Batch,
+    /// 当前正在填充的块
+    current: usize,
+}
+
+impl Compressor {
+    pub fn new(compression: Compression) -> Self {
+        Self { batch: Batch::new(&compression), compression, current: 0 }
+    }
+
+    /// 写入明文，满批时压缩并输出
+    pub fn write(&mut self, mut buf: &[u8], out: &mut dyn Write) -> io::Result<()> {
+        let block_size = self.compression.block_size as usize;
+
+        while !buf.is_empty() {
+            let filled = self.batch.plain_lengths[self.current];
+            let bytes = std::cmp::min(buf.len(), block_size - filled);
+            self.batch.plain[self.current].expose_mut()[filled..filled + bytes].copy_from_slice(&buf[..bytes]);
+            self.batch.plain_lengths[self.current] += bytes;
+            buf = &buf[bytes..];
+
+            if self.batch.plain_lengths[self.current] == block_size {
+                self.current += 1;
+                if self.current == self.batch.plain.len() {
+                    self.flush_batch(out)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 压缩并写出剩余的块
+    pub fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.flush_batch(out)
+    }
+
+    fn flush_batch(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let level = self.compression.level;
+        let Batch { plain, plain_lengths, frames, frame_lengths } = &mut self.batch;
+
+        frames.par_iter_mut()
+            .zip(frame_lengths.par_iter_mut())
+            .zip(plain.par_iter().zip(plain_lengths.par_iter()))
+            .filter(|(_, (_, length))| **length > 0)
+            .try_for_each(|((frame, frame_length), (block, length))| {
+                *frame_length = zstd::bulk::compress_to_buffer(&block.expose()[..*length], frame.expose_mut(), level)?;
+                Ok::<(), io::Error>(())
+            })?;
+
+        for i in 0..plain.len() {
+            if plain_lengths[i] == 0 { continue; }
+            out.write_all(&(frame_lengths[i] as u32).to_le_bytes())?;
+            out.write_all(&frames[i].expose()[..frame_lengths[i]])?;
+            plain_lengths[i] = 0;
+        }
+
+        self.current = 0;
+        Ok(())
+    }
+}
+
+/// 解压主体：逐批读取“长度 + 帧”，并行解压后按顺序写出，返回明文字节数
+pub fn decompress_body(compression: &Compression, reader: &mut dyn Read, writer: &mut dyn Write) -> io::Result<u64> {
+    let mut batch = Batch::new(compression);
+    let max_frame_length = compression.max_frame_length();
+    let mut total = 0u64;
+
+    loop {
+        // 读取一批压缩块
+        let mut count = 0;
+        while count < batch.frames.len() {
+            let mut length = [0u8; 4];
+            if !read_exact_or_eof(reader, &mut length)? {
+                break;
+            }
+            let length = u32::from_le_bytes(length) as usize;
+            if length == 0 || length > max_frame_length {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "压缩块损坏"));
+            }
+            reader.read_exact(&mut batch.frames[count].expose_mut()[..length])?;
+            batch.frame_lengths[count] = length;
+            count += 1;
+        }
+
+        if count == 0 {
+            break;
+        }
+
+        let Batch { plain, plain_lengths, frames, frame_lengths } = &mut batch;
+        plain[..count].par_iter_mut()
+            .zip(plain_lengths[..count].par_iter_mut())
+            .zip(frames[..count].par_iter().zip(frame_lengths[..count].par_iter()))
+            .try_for_each(|((block, length), (frame, frame_length))| {
+                *length = zstd::bulk::decompress_to_buffer(&frame.expose()[..*frame_length], block.expose_mut())
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "压缩块损坏"))?;
+                Ok::<(), io::Error>(())
+            })?;
+
+        for i in 0..count {
+            writer.write_all(&plain[i].expose()[..plain_lengths[i]])?;
+            total += plain_lengths[i] as u64;
+        }
+    }
+
+    Ok(total)
+}
+
+/// 读满缓冲区；在开头即遇到结尾时返回 false
+fn read_exact_or_eof(reader: &mut dyn Read, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 if filled == 0 => return Ok(false),
+            0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "压缩块损坏")),
+            n => filled += n,
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_encode_decode() {
+        let compression = Compression::new(19);
+        assert_eq!(Compression::decode(&compression.encode()).unwrap(), compression);
+
+        assert!(Compression::decode(&[0x7f, 3, 0, 0, 16, 0]).is_err());
+        assert!(Compression::decode(&[COMPRESSION_ID_ZSTD, 3, 0, 0, 0, 0]).is_err());
+        // 编码器写不出的块大小只可能来自篡改，在分配缓冲区之前拒绝
+        let oversized = Compression { level: 3, block_size: COMPRESSION_BLOCK_SIZE + 1 };
+        assert!(Compression::decode(&oversized.encode()).is_err());
+        assert!(Batch::new(&Compression::new(3)).plain.len() * 2 * COMPRESSION_BLOCK_SIZE as usize <= COMPRESSION_BATCH_MEMORY);
+        assert_eq!(Compression::parse_level("3"), Ok(3));
+        assert!(Compression::parse_level("23").is_err());
+    }
+
+    #[test]
+    fn test_compress_roundtrip() {
+        // 小块，覆盖跨块、跨批与最后一个不满的块
+        let compression = Compression { level: 3, block_size: 1000 };
+        let data: Vec<u8> = (0..25_500u32).map(|i| (i / 7 % 13) as u8).collect();
+
+        let mut compressor = Compressor::new(compression);
+        let mut compressed = Vec::new();
+        for chunk in data.chunks(777) {
+            compressor.write(chunk, &mut compressed).unwrap();
+        }
+        compressor.finish(&mut compressed).unwrap();
+        assert!(compressed.len() < data.len() / 5);
+
+        let mut decompressed = Vec::new();
+        let total = decompress_body(&compression, &mut compressed.as_slice(), &mut decompressed).unwrap();
+        assert_eq!(total, data.len() as u64);
+        assert_eq!(decompressed, data);
+
+        // 截断的压缩块
+        compressed.truncate(compressed.len() - 1);
+        assert!(decompress_body(&compression, &mut compressed.as_slice(), &mut Vec::new()).is_err());
+    }
+}
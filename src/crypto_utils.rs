@@ -3,16 +3,137 @@ use ring::rand::SecureRandom;
 
 // 常量定义
 pub const MAGIC_NUMBER: &str = "DEC!";
-pub const VERSION_SIGN: u8 = 0x02;
+pub const VERSION_SIGN: u8 = 0x03;
+pub const LEGACY_VERSION_SIGN: u8 = 0x02;
 pub const SALT_LENGTH: usize = 16;
 pub const IV_LENGTH: usize = 16;
 pub const ARGON2_ITERATIONS: u32 = 3;
 pub const ARGON2_MEMORY_KIB: u32 = 65536;
 pub const ARGON2_PARALLELISM: u32 = 4;
+pub const SCRYPT_LOG_N: u8 = 15;
+pub const SCRYPT_R: u32 = 8;
+pub const SCRYPT_P: u32 = 1;
+pub const PBKDF2_ITERATIONS: u32 = 600_000;
 pub const MASTER_KEY_LENGTH: usize = 32;
 pub const ENCRYPTION_KEY_LENGTH: usize = 32;
 pub const HMAC_KEY_LENGTH: usize = 32;
+pub const KEY_CHECK_LENGTH: usize = 16;
 pub const BUFFER_SIZE: usize = 256 * 1024;
+pub const HMAC_LENGTH: usize = 32;
+
+// 文件头字段（v3 起：魔数 + 版本 + u32 头部长度 + TLV 字段）
+pub const HEADER_MAX_LENGTH: usize = 64 * 1024;
+pub const TAG_KDF: u8 = 0x01;
+pub const TAG_SALT: u8 = 0x02;
+pub const TAG_IV: u8 = 0x03;
+pub const TAG_KEY_CHECK: u8 = 0x04;
+pub const TAG_CREATED: u8 = 0x05;
+pub const TAG_CREATOR: u8 = 0x06;
+pub const TAG_PASSWORD_NORMALIZATION: u8 = 0x07;
+pub const TAG_COMPRESSION: u8 = 0x08;
+pub const TAG_PADDING: u8 = 0x09;
+pub const TAG_METADATA: u8 = 0x0A;
+pub const TAG_DIGEST: u8 = 0x0B;
+
+// 密码 Unicode 规范化方式标识（无此字段的文件为原样字节）
+pub const NORMALIZATION_NONE: u8 = 0x00;
+pub const NORMALIZATION_NFC: u8 = 0x01;
+
+// 压缩（每块独立压缩，便于并行与定位）
+pub const COMPRESSION_ID_ZSTD: u8 = 0x01;
+pub const COMPRESSION_DEFAULT_LEVEL: i32 = 3;
+pub const COMPRESSION_MIN_LEVEL: i32 = 1;
+pub const COMPRESSION_MAX_LEVEL: i32 = 22;
+pub const COMPRESSION_BLOCK_SIZE: u32 = 1024 * 1024;
+/// 解密时接受的最大块大小：编码器只写 `COMPRESSION_BLOCK_SIZE`，更大的值只可能来自被篡改的文件头
+pub const COMPRESSION_MAX_BLOCK_SIZE: u32 = COMPRESSION_BLOCK_SIZE;
+/// 一批压缩块（明文 + 帧）占用内存的上限，与核心数无关
+pub const COMPRESSION_BATCH_MEMORY: usize = 32 * 1024 * 1024;
+
+// 隐藏长度的填充（主体末尾为 u64 填充长度）
+pub const PADDING_ID_PADME: u8 = 0x01;
+pub const PADDING_ID_BUCKET: u8 = 0x02;
+pub const PADDING_ID_FIXED: u8 = 0x03;
+pub const PADDING_TRAILER_LENGTH: usize = 8;
+
+// 主体开头的元数据块（u32 长度 + 字段：u8 标签 + u32 长度 + 值）
+pub const METADATA_FIELD_NAME: u8 = 0x01;
+pub const METADATA_FIELD_MODE: u8 = 0x02;
+pub const METADATA_FIELD_MTIME: u8 = 0x03;
+pub const METADATA_FIELD_XATTR: u8 = 0x04;
+pub const METADATA_MAX_LENGTH: usize = 16 * 1024 * 1024;
+
+// 明文摘要（加密保存在主体中内容之后、填充之前）
+pub const DIGEST_ID_SHA256: u8 = 0x01;
+pub const DIGEST_ID_BLAKE3: u8 = 0x02;
+pub const DIGEST_LENGTH: usize = 32;
+
+// ASCII 封装（base64 + 起止行 + CRC-24 校验和）
+pub const ARMOR_BEGIN: &str = "-----BEGIN DEC ENCRYPTED FILE-----";
+pub const ARMOR_END: &str = "-----END DEC ENCRYPTED FILE-----";
+pub const ARMOR_LINE_CHARS: usize = 64;
+pub const ARMOR_LINE_BYTES: usize = 48;
+pub const ARMOR_SCAN_LENGTH: usize = 1024;
+pub const ARMOR_CRC24_INIT: u32 = 0xB7_04CE;
+pub const ARMOR_CRC24_POLY: u32 = 0x186_4CFB;
+
+// Reed-Solomon 纠错封装（加密之后的外层）：文件头 + 若干段 + 两份尾部
+// 每段 64 个数据块与按比例计算的校验块，每块附 8 字节校验和，用于定位损坏的块
+pub const PARITY_MAGIC: &[u8; 4] = b"DECR";
+pub const PARITY_VERSION: u8 = 0x01;
+pub const PARITY_DATA_SHARDS: u8 = 64;
+pub const PARITY_SHARD_SIZE: u32 = 4096;
+pub const PARITY_CHECKSUM_LENGTH: usize = 8;
+pub const PARITY_HEADER_LENGTH: usize = 19;
+pub const PARITY_TRAILER_LENGTH: usize = 27;
+pub const PARITY_DEFAULT_PERCENT: u32 = 10;
+pub const PARITY_MAX_PERCENT: u32 = 100;
+
+// 分卷（`--split`）：每卷开头为卷头（魔数 + 版本 + 标志 + 分卷组标识 + u32 卷号 + u64 内容长度
+// + 内容的 BLAKE3 + 卷头校验和），文件名依次追加 `.001`、`.002`……
+pub const VOLUME_MAGIC: &[u8; 4] = b"DECV";
+pub const VOLUME_VERSION: u8 = 0x01;
+pub const VOLUME_FLAG_LAST: u8 = 0x01;
+pub const VOLUME_SET_ID_LENGTH: usize = 16;
+pub const VOLUME_HASH_LENGTH: usize = 32;
+pub const VOLUME_CHECKSUM_LENGTH: usize = 8;
+pub const VOLUME_HEADER_LENGTH: usize = 74;
+pub const VOLUME_MIN_SIZE: u64 = 4096;
+
+// 单行令牌（`dec seal`）：前缀 + URL 安全、无填充的 base64
+pub const TOKEN_PREFIX: &str = "dec:";
+
+// 结构化配置文件（sops 风格）：逐值加密，顶层 `dec` 段保存 KDF、盐与整篇 MAC
+pub const STRUCTURED_VERSION: u64 = 1;
+pub const STRUCTURED_METADATA_KEY: &str = "dec";
+pub const STRUCTURED_DOTENV_PREFIX: &str = "dec_";
+pub const STRUCTURED_VALUE_PREFIX: &str = "ENC[AES256_CTR_HMAC_SHA256,";
+
+// git clean/smudge 过滤器：仓库参数文件（KDF 与盐，随仓库提交）与过滤器名
+pub const GIT_PARAMS_FILE: &str = ".decgit";
+pub const GIT_FILTER_NAME: &str = "dec";
+
+// `dec edit` 的私有临时目录前缀，后接进程号，用于清理崩溃后残留的目录
+pub const EDIT_TEMP_PREFIX: &str = "dec-edit-";
+
+// 密钥派生算法标识
+pub const KDF_ID_ARGON2ID: u8 = 0x01;
+pub const KDF_ID_SCRYPT: u8 = 0x02;
+pub const KDF_ID_PBKDF2_SHA256: u8 = 0x03;
+
+// 读取文件头时允许的 KDF 参数上限
+pub const ARGON2_MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+pub const ARGON2_MAX_ITERATIONS: u32 = 1024;
+pub const ARGON2_MAX_PARALLELISM: u32 = 64;
+pub const SCRYPT_MAX_LOG_N: u8 = 22;
+pub const SCRYPT_MAX_R: u32 = 32;
+pub const SCRYPT_MAX_P: u32 = 16;
+/// scrypt 占用 128·r·2^n 字节，与 Argon2 的内存上限相同
+pub const SCRYPT_MAX_MEMORY: u64 = ARGON2_MAX_MEMORY_KIB as u64 * 1024;
+/// p·r 决定混合的总次数，单独限制各参数时仍可能过大
+pub const SCRYPT_MAX_PR: u32 = 128;
+pub const PBKDF2_MIN_ITERATIONS: u32 = 1000;
+pub const PBKDF2_MAX_ITERATIONS: u32 = 100_000_000;
 
 /// 获取 CPU 线程数
 pub fn get_parts() -> usize {
@@ -71,7 +192,8 @@ mod tests {
     #[test]
     fn test_constants() {
         assert_eq!(MAGIC_NUMBER, "DEC!");
-        assert_eq!(VERSION_SIGN, 0x02);
+        assert_eq!(VERSION_SIGN, 0x03);
+        assert_eq!(LEGACY_VERSION_SIGN, 0x02);
         assert_eq!(SALT_LENGTH, 16);
         assert_eq!(IV_LENGTH, 16);
         assert_eq!(MASTER_KEY_LENGTH, 32);
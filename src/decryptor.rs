@@ -1,138 +1,549 @@
 use std::fs::File;
-use std::io::{Read, Write, BufReader, BufWriter, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{Cursor, Read, Write, Seek, SeekFrom, BufReader, BufWriter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use aes::Aes256;
 use ctr::Ctr128BE;
-use ctr::cipher::{KeyIvInit, StreamCipher};
+use ctr::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use crate::armor::{self, ReadSeek};
+use crate::volume::{self, VolumeReader};
+use crate::compression::decompress_body;
+use crate::digest::{self, DigestValue, DigestWriter};
 use crate::crypto_utils::*;
 use crate::progress_utils::*;
+use crate::header::Header;
 use crate::key_derivation;
 use crate::hmac_validator::HmacValidator;
+use crate::metadata::Metadata;
+use crate::secret::SecretBytes;
 
 type Aes256Ctr = Ctr128BE<Aes256>;
 
 pub fn check_version(input_file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
     let input_path = Path::new(input_file_path);
     
-    // 打开文件并读取文件头信息
-    let mut file = File::open(input_path)?;
-    let mut magic_number_bytes = vec![0u8; MAGIC_NUMBER.len()];
-    file.read_exact(&mut magic_number_bytes)?;
+    // 打开文件并读取文件头信息（验证魔数、版本与文件头字段）
+    let (mut reader, _, _) = armor::open_quietly(input_path)?;
+    Header::read(&mut reader).map_err(|e| locate_damaged_volume(input_path, e))?;
     
-    // 验证魔数
-    if magic_number_bytes != MAGIC_NUMBER.as_bytes() {
-        return Err("无效的加密文件格式".into());
+    Ok(())
+}
+
+/// 已读取文件头并完成密钥派生的加密文件
+struct EncryptedFile {
+    reader: BufReader<Box<dyn ReadSeek>>,
+    header: Header,
+    header_bytes: Vec<u8>,
+    encryption_key: SecretBytes,
+    hmac_key: SecretBytes,
+    data_length: u64,
+    /// 是否显示进度条
+    progress: bool,
+    /// 解密后与输出比对一致的明文摘要
+    digest: Option<DigestValue>,
+}
+
+impl EncryptedFile {
+    /// 打开加密文件，读取文件头，派生密钥并校验口令
+    fn open(input_file_path: &str, password: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let input_path = Path::new(input_file_path);
+
+        if !input_path.exists() || !input_path.is_file() {
+            return Err(format!("输入文件不存在: {}", input_file_path).into());
+        }
+
+        // ASCII 封装的文件边读边解码
+        let (reader, total_file_length, _) = armor::open(input_path)?;
+        Self::from_reader(reader, total_file_length, password, true)
     }
-    
-    // 读取版本字节
-    let mut version = [0u8; 1];
-    file.read_exact(&mut version)?;
-    if version[0] != VERSION_SIGN {
-        return Err(format!("不支持的文件版本: {}", version[0]).into());
+
+    /// 从任意可定位的二进制流读取文件头，派生密钥并校验口令
+    fn from_reader(reader: Box<dyn ReadSeek>, total_file_length: u64, password: &str, progress: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        // 读取文件头信息（使用缓冲读）
+        let mut reader = BufReader::with_capacity(BUFFER_SIZE, reader);
+
+        // 读取魔数、版本、KDF 参数、盐和IV
+        let (header, header_bytes) = Header::read(&mut reader)?;
+
+        // 使用文件头记录的 KDF 与密码规范化方式派生主密钥
+        let password = header.normalization.apply(password);
+        let master_key = key_derivation::derive_master_key(&header.kdf, password.expose().as_bytes(), &header.salt)?;
+
+        // 有口令校验值时，先确认密码，避免错误密码时写出整个无效文件
+        if let Some(key_check) = &header.key_check {
+            key_derivation::verify_key_check(&master_key, key_check)?;
+        }
+
+        // 使用HKDF派生加密密钥和HMAC密钥
+        let (encryption_key, hmac_key) = key_derivation::derive_encryption_and_hmac_keys(&master_key)?;
+
+        // 计算文件头长度和主体长度
+        let header_length = header_bytes.len() as u64;
+        let data_length = total_file_length.checked_sub(header_length + HMAC_LENGTH as u64)
+            .ok_or("加密文件已截断")?;
+
+        Ok(Self { reader, header, header_bytes, encryption_key, hmac_key, data_length, progress, digest: None })
+    }
+
+    /// 创建HMAC计算器（文件头是否受保护由格式决定）
+    fn hmac(&self) -> Result<HmacValidator, Box<dyn std::error::Error>> {
+        let mut hmac = HmacValidator::new(self.hmac_key.expose())?;
+        if self.header.format().authenticated_header {
+            hmac.update(&self.header_bytes);
+        }
+        Ok(hmac)
+    }
+
+    /// 流式解密：先读出元数据块，由 `make_writer` 据此创建写入器，
+    /// 再把内容交给对应版本的主体解码器，最后验证HMAC；带有明文摘要时，再与输出的明文比对
+    fn decode_into<W: Write>(
+        &mut self,
+        make_writer: impl FnOnce(Option<&Metadata>) -> Result<W, Box<dyn std::error::Error>>,
+    ) -> Result<(W, Option<Metadata>), Box<dyn std::error::Error>> {
+        let format = self.header.format();
+        let header = self.header.clone();
+
+        let content_length = self.content_length()?;
+
+        // 只解码内容部分，其后的填充在验证HMAC时读完
+        let mut body = self.body_reader(true)?;
+        let mut content = (&mut body).take(content_length);
+        let metadata = if header.metadata { Some(Metadata::read_from(&mut content)?) } else { None };
+
+        let mut writer = make_writer(metadata.as_ref())?;
+        let Some(algorithm) = header.digest else {
+            (format.decode_body)(&header, &mut content, &mut writer)?;
+
+            // 读取并验证存储的HMAC
+            body.finish()?;
+            return Ok((writer, metadata));
+        };
+
+        let mut digest_writer = DigestWriter::new(&mut writer, algorithm);
+        (format.decode_body)(&header, &mut content, &mut digest_writer)?;
+        let (_, actual) = digest_writer.finish();
+
+        let mut stored = [0u8; DIGEST_LENGTH];
+        body.read_exact(&mut stored)?;
+        body.finish()?;
+
+        // HMAC 只证明密文未被改动，摘要证明输出与加密时的原文相同
+        if stored != actual {
+            return Err(format!("明文摘要不一致（{}），解密结果与原文不同", algorithm).into());
+        }
+        self.digest = Some(actual);
+        Ok((writer, metadata))
+    }
+
+    /// 主体中内容部分（含元数据块）的长度：其后依次是明文摘要、填充与填充长度
+    fn content_length(&mut self) -> Result<u64, Box<dyn std::error::Error>> {
+        let digest_length = if self.header.digest.is_some() { DIGEST_LENGTH as u64 } else { 0 };
+        self.unpadded_length()?.checked_sub(digest_length)
+            .ok_or_else(|| "加密文件已截断".into())
+    }
+
+    /// 去掉填充后的主体长度：有填充时，先定位并解密主体末尾的填充长度
+    fn unpadded_length(&mut self) -> Result<u64, Box<dyn std::error::Error>> {
+        if self.header.padding.is_none() {
+            return Ok(self.data_length);
+        }
+
+        let trailer_offset = self.data_length.checked_sub(PADDING_TRAILER_LENGTH as u64)
+            .ok_or("加密文件已截断")?;
+
+        let mut trailer = [0u8; PADDING_TRAILER_LENGTH];
+        self.read_body_at(trailer_offset, &mut trailer)?;
+        self.reader.seek(SeekFrom::Start(self.header_bytes.len() as u64))?;
+
+        trailer_offset.checked_sub(u64::from_le_bytes(trailer))
+            .ok_or_else(|| "填充长度异常".into())
+    }
+
+    /// 解密主体中 `offset` 处的几个字节（不计算 HMAC）
+    ///
+    /// CTR 模式可直接定位到该处的密钥流；绕过缓冲只读取所需字节，
+    /// 定位已清空缓冲区，之后的读取仍从正确位置开始。
+    fn read_body_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), Box<dyn std::error::Error>> {
+        self.reader.seek(SeekFrom::Start(self.header_bytes.len() as u64 + offset))?;
+        self.reader.get_mut().read_exact(buf)?;
+
+        let mut cipher = Aes256Ctr::new(self.encryption_key.expose().into(), self.header.iv.as_slice().into());
+        cipher.seek(offset);
+        cipher.apply_keystream(buf);
+        Ok(())
+    }
+
+    /// 只解码明文中的 `range` 部分：未压缩时直接定位到起点，压缩时沿长度前缀跳到所在的块
+    ///
+    /// 不计算 HMAC，调用方须先验证整个文件。
+    fn decode_range_into(&mut self, range: Range<u64>, writer: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        let content_end = self.content_length()?;
+
+        // 跳过元数据块
+        let mut offset = 0;
+        if self.header.metadata {
+            let mut length = [0u8; 4];
+            self.read_body_at(0, &mut length)?;
+            offset = 4 + u32::from_le_bytes(length) as u64;
+        }
+
+        let Some(compression) = self.header.compression else {
+            let start = offset.saturating_add(range.start).min(content_end);
+            let end = offset.saturating_add(range.end).min(content_end);
+            let mut body = self.body_reader_at(start)?.take(end.saturating_sub(start));
+            crate::format::copy_body(&mut body, writer)?;
+            return Ok(());
+        };
+
+        // 除最后一块外，每块明文都是 block_size 字节
+        let block_size = compression.block_size as u64;
+        let mut plain_offset = 0u64;
+        let mut start = None;
+        while offset < content_end && plain_offset < range.end {
+            if start.is_none() && plain_offset + block_size > range.start {
+                start = Some((offset, plain_offset));
+            }
+            let mut length = [0u8; 4];
+            self.read_body_at(offset, &mut length)?;
+            offset += 4 + u32::from_le_bytes(length) as u64;
+            plain_offset += block_size;
+        }
+
+        let Some((start, start_plain)) = start else { return Ok(()) };
+        let mut body = self.body_reader_at(start)?.take(offset.min(content_end) - start);
+        let mut writer = RangeWriter {
+            inner: writer,
+            skip: range.start - start_plain,
+            remaining: range.end - range.start,
+        };
+        decompress_body(&compression, &mut body, &mut writer)?;
+        Ok(())
+    }
+
+    /// 读取主体：`decrypt` 为 false 时只计算 HMAC，不生成密钥流
+    fn body_reader(&mut self, decrypt: bool) -> Result<DecryptingReader<'_>, Box<dyn std::error::Error>> {
+        let hmac = self.hmac()?;
+
+        // parts 模式：仅当 parts==1 时持有单流解密器；否则使用并行处理
+        let parallel_parts = get_parts();
+        let single_cipher = if decrypt && parallel_parts == 1 {
+            Some(Aes256Ctr::new(self.encryption_key.expose().into(), self.header.iv.as_slice().into()))
+        } else { None };
+
+        Ok(DecryptingReader {
+            reader: &mut self.reader,
+            key: self.encryption_key.expose(),
+            iv: &self.header.iv,
+            hmac: Some(hmac),
+            decrypt,
+            parallel_parts,
+            single_cipher,
+            total_read: 0,
+            data_length: self.data_length,
+            progress: self.progress,
+        })
+    }
+
+    /// 从主体的 `offset` 处开始解密读取（CTR 定位），不计算 HMAC、不显示进度
+    fn body_reader_at(&mut self, offset: u64) -> Result<DecryptingReader<'_>, Box<dyn std::error::Error>> {
+        self.reader.seek(SeekFrom::Start(self.header_bytes.len() as u64 + offset))?;
+
+        let parallel_parts = get_parts();
+        let single_cipher = if parallel_parts == 1 {
+            let mut cipher = Aes256Ctr::new(self.encryption_key.expose().into(), self.header.iv.as_slice().into());
+            cipher.seek(offset);
+            Some(cipher)
+        } else { None };
+
+        Ok(DecryptingReader {
+            reader: &mut self.reader,
+            key: self.encryption_key.expose(),
+            iv: &self.header.iv,
+            hmac: None,
+            decrypt: true,
+            parallel_parts,
+            single_cipher,
+            total_read: offset,
+            data_length: self.data_length,
+            progress: false,
+        })
     }
-    
-    Ok(())
 }
 
-pub fn decrypt_with_mode(input_file_path: &str, output_file_path: &str, password: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let input_path = Path::new(input_file_path);
-    let output_path = Path::new(output_file_path);
+/// 跳过开头 `skip` 个字节，只写出其后的 `remaining` 个字节，其余丢弃
+struct RangeWriter<'a> {
+    inner: &'a mut dyn Write,
+    skip: u64,
+    remaining: u64,
+}
 
-    if !input_path.exists() || !input_path.is_file() {
-        return Err(format!("输入文件不存在: {}", input_file_path).into());
+impl Write for RangeWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let skipped = std::cmp::min(self.skip, buf.len() as u64) as usize;
+        self.skip -= skipped as u64;
+
+        let wanted = std::cmp::min(self.remaining, (buf.len() - skipped) as u64) as usize;
+        self.inner.write_all(&buf[skipped..skipped + wanted])?;
+        self.remaining -= wanted as u64;
+
+        Ok(buf.len())
     }
-    
-    // 启动计时器
-    let start_time = start_timer();
-    
-    // 读取文件头信息（使用缓冲读）
-    let mut file = BufReader::with_capacity(BUFFER_SIZE, File::open(input_path)?);
-    
-    // 跳过魔数和版本字节
-    file.seek(SeekFrom::Start((MAGIC_NUMBER.len() + 1) as u64))?;
-    
-    // 读取盐和IV
-    let mut salt = vec![0u8; SALT_LENGTH];
-    file.read_exact(&mut salt)?;
-    let mut iv = vec![0u8; IV_LENGTH];
-    file.read_exact(&mut iv)?;
-    
-    // 使用Argon2派生主密钥
-    let master_key = key_derivation::derive_master_key(password.as_bytes(), &salt)?;
-    
-    // 使用HKDF派生加密密钥和HMAC密钥
-    let (encryption_key, hmac_key) = key_derivation::derive_encryption_and_hmac_keys(&master_key)?;
-    
-    // 计算文件总长度和文件头长度
-    let total_file_length = input_path.metadata()?.len();
-    let header_length = (MAGIC_NUMBER.len() + 1 + SALT_LENGTH + IV_LENGTH) as u64;
-    let hmac_length = 32u64; // HMAC-SHA256的长度
-    let encrypted_data_length = total_file_length - header_length - hmac_length;
-    
-    // 创建输出文件（放大写缓冲）
-    let mut output_file = File::create(output_path)?;
-    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, &mut output_file);
-    
-    // parts 模式：仅当 parts==1 时持有单流解密器；否则使用并行处理
-    let parallel_parts = get_parts();
 
-    let mut single_cipher = if parallel_parts == 1 {
-        Some(Aes256Ctr::new(encryption_key.as_slice().into(), iv.as_slice().into()))
-    } else { None };
-    
-    // 创建HMAC计算器
-    let mut hmac = HmacValidator::new(&hmac_key)?;
-    
-    // 流式解密数据
-    let mut buffer = vec![0u8; BUFFER_SIZE];
-    let mut total_read = 0;
-    
-    while total_read < encrypted_data_length {
-        let bytes_to_read = std::cmp::min(BUFFER_SIZE as u64, encrypted_data_length - total_read) as usize;
-        let bytes_read = file.read(&mut buffer[..bytes_to_read])?;
-        
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 流式读取主体：对密文计算 HMAC，并按需解密
+struct DecryptingReader<'a> {
+    reader: &'a mut BufReader<Box<dyn ReadSeek>>,
+    key: &'a [u8],
+    iv: &'a [u8],
+    hmac: Option<HmacValidator>,
+    decrypt: bool,
+    parallel_parts: usize,
+    single_cipher: Option<Aes256Ctr>,
+    total_read: u64,
+    data_length: u64,
+    progress: bool,
+}
+
+impl Read for DecryptingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.data_length - self.total_read;
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let bytes_to_read = std::cmp::min(buf.len() as u64, remaining) as usize;
+        let bytes_read = self.reader.read(&mut buf[..bytes_to_read])?;
         if bytes_read == 0 {
-            break;
+            return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "加密文件已截断"));
         }
-        
-        let chunk = &mut buffer[..bytes_read];
-        
+
+        let chunk = &mut buf[..bytes_read];
+
         // 更新HMAC（对密文计算）
-        hmac.update(chunk);
-
-        if parallel_parts == 1 {
-            if let Some(cipher) = &mut single_cipher { cipher.apply_keystream(chunk); }
-        } else {
-            crate::parallel_handler::ctr_apply_in_parts(
-                encryption_key.as_slice(),
-                iv.as_slice(),
-                chunk,
-                total_read as usize
-            ).map_err(|e| format!("parallel decrypt error: {}", e))?;
+        if let Some(hmac) = &mut self.hmac { hmac.update(chunk); }
+
+        if self.decrypt {
+            if self.parallel_parts == 1 {
+                if let Some(cipher) = &mut self.single_cipher { cipher.apply_keystream(chunk); }
+            } else {
+                crate::parallel_handler::ctr_apply_in_parts(
+                    self.key,
+                    self.iv,
+                    chunk,
+                    self.total_read as usize
+                ).map_err(|e| std::io::Error::other(format!("parallel decrypt error: {}", e)))?;
+            }
         }
-        
-        // 写入解密后的数据
-        writer.write_all(chunk)?;
-        
-        total_read += bytes_read as u64;
-        
+
+        self.total_read += bytes_read as u64;
+
         // 更新进度显示
-        update_progress(total_read, encrypted_data_length);
+        if self.progress {
+            update_progress(self.total_read, self.data_length);
+        }
+
+        Ok(bytes_read)
+    }
+}
+
+impl DecryptingReader<'_> {
+    /// 读完剩余主体，并验证文件末尾存储的HMAC
+    fn finish(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        std::io::copy(&mut self, &mut std::io::sink())?;
+
+        let mut stored_hmac = vec![0u8; HMAC_LENGTH];
+        self.reader.read_exact(&mut stored_hmac)?;
+
+        let hmac = self.hmac.take().ok_or("HMAC already consumed")?;
+        hmac.verify(&stored_hmac)
     }
+}
+
+/// 解密到输出文件；文件中带有元数据时，在 HMAC 验证通过后恢复到输出文件上并返回
+pub fn decrypt_with_mode(input_file_path: &str, output_file_path: &str, password: &str) -> Result<Option<Metadata>, Box<dyn std::error::Error>> {
+    let output_path = Path::new(output_file_path);
+    
+    // 启动计时器
+    let start_time = start_timer();
+    reset_progress();
     
-    // 读取存储的HMAC
-    let mut stored_hmac = vec![0u8; hmac_length as usize];
-    file.read_exact(&mut stored_hmac)?;
+    let mut input = EncryptedFile::open(input_file_path, password)?;
+    let encrypted_data_length = input.data_length;
     
-    // 验证HMAC
-    hmac.verify(&stored_hmac)?;
+    // 创建输出文件（放大写缓冲）
+    let mut output_file = File::create(output_path)?;
+    
+    // 流式解密并验证HMAC
+    let (mut writer, metadata) = input.decode_into(|_| Ok(BufWriter::with_capacity(BUFFER_SIZE, &mut output_file)))
+        .map_err(|e| locate_damaged_volume(Path::new(input_file_path), e))?;
     
     writer.flush()?;
+    drop(writer);
+
+    // 恢复权限、修改时间等属性（输出到设备或管道时跳过）
+    if let Some(metadata) = &metadata
+        && output_file.metadata()?.is_file()
+        && let Err(e) = metadata.restore(&output_file)
+    {
+        eprintln!("DEC!: cannot restore file attributes: {}", e);
+    }
     
     // 显示进度完成
     let duration = start_time.elapsed();
     update_progress(encrypted_data_length, encrypted_data_length);
     println!("\u{001B}[0mDEC!: Done!  cost: {}", format_duration(duration));
+    if let (Some(algorithm), Some(digest)) = (input.header.digest, input.digest) {
+        println!("DEC!: {} {} verified", algorithm, digest::to_hex(&digest));
+    }
     
+    Ok(metadata)
+}
+
+/// 分卷输入解密失败时逐卷比对摘要：损坏的数据可能先在解压时出错，此时改为报告损坏的卷
+fn locate_damaged_volume(input_path: &Path, error: Box<dyn std::error::Error>) -> Box<dyn std::error::Error> {
+    let is_volume = File::open(input_path).and_then(|mut file| volume::is_volume(input_path, &mut file));
+    if !is_volume.unwrap_or(false) {
+        return error;
+    }
+    match VolumeReader::open(input_path).and_then(VolumeReader::check) {
+        Err(damaged) => damaged.into(),
+        Ok(()) => error,
+    }
+}
+
+/// 解密到输入文件所在目录，以文件中记录的原文件名保存，返回输出路径
+///
+/// 先写入同目录的临时文件，HMAC 验证通过后才以原文件名落盘；同名文件已存在时报错，不覆盖。
+pub fn decrypt_restoring_name(input_file_path: &str, password: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = Path::new(input_file_path).parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let temp_file = tempfile::NamedTempFile::new_in(dir)?;
+    let temp_path = temp_file.path().to_str().ok_or("临时文件路径无效")?.to_string();
+
+    let metadata = decrypt_with_mode(input_file_path, &temp_path, password)?;
+    let name = metadata.as_ref().and_then(Metadata::safe_name).ok_or("文件中没有记录有效的原文件名")?;
+
+    let output_path = dir.join(name);
+    temp_file.persist_noclobber(&output_path)
+        .map_err(|e| format!("无法写出 {}: {}", output_path.display(), e.error))?;
+    Ok(output_path)
+}
+
+/// 解密到由 `make_writer` 创建的写入器（不显示完成信息）
+///
+/// `make_writer` 在读出元数据块之后、写出内容之前调用；元数据此时尚未验证，
+/// 调用方须在本函数成功返回（HMAC 验证通过）后才信任写出的结果。
+pub fn decrypt_to_writer<W: Write>(
+    input_file_path: &str,
+    password: &str,
+    make_writer: impl FnOnce(Option<&Metadata>) -> Result<W, Box<dyn std::error::Error>>,
+) -> Result<W, Box<dyn std::error::Error>> {
+    reset_progress();
+
+    let mut input = EncryptedFile::open(input_file_path, password)?;
+    let (writer, _) = input.decode_into(make_writer)?;
+
+    Ok(writer)
+}
+
+/// 解密内存中的加密数据（不显示进度），HMAC 验证通过后才返回明文
+pub fn decrypt_bytes(encrypted: Vec<u8>, password: &str) -> Result<SecretBytes, Box<dyn std::error::Error>> {
+    let length = encrypted.len() as u64;
+    let mut input = EncryptedFile::from_reader(Box::new(Cursor::new(encrypted)), length, password, false)?;
+
+    // 未压缩时明文不超过密文长度，压缩时可能更长，由 PlaintextBuffer 负责扩容；验证失败时已解密的部分随之清零
+    let mut plaintext = PlaintextBuffer(zeroize::Zeroizing::new(Vec::with_capacity(input.data_length as usize)));
+    input.decode_into(|_| Ok(&mut plaintext))?;
+
+    Ok(SecretBytes::from(std::mem::take(&mut *plaintext.0)))
+}
+
+/// 内存中的明文：容量不足时换用更大的缓冲区，旧缓冲区清零后才释放，扩容不留下明文副本
+struct PlaintextBuffer(zeroize::Zeroizing<Vec<u8>>);
+
+impl Write for PlaintextBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.0.capacity() - self.0.len() < buf.len() {
+            let capacity = std::cmp::max(self.0.capacity() * 2, self.0.len() + buf.len());
+            let mut grown = zeroize::Zeroizing::new(Vec::with_capacity(capacity));
+            grown.extend_from_slice(&self.0);
+            self.0 = grown;
+        }
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 先验证整个文件，再把明文（`range` 指定时只取其中的字节范围）写入 `writer`（不显示进度）
+///
+/// 格式只有覆盖整个文件的 HMAC，没有分段认证，因此总是先读完密文验证 HMAC（不生成密钥流），
+/// 通过后才写出明文；验证失败时不写出任何内容。指定范围时再用 CTR 定位只解密所需的部分，
+/// 范围超出明文长度时截断到末尾。两遍读取之间文件被改动的情况无法发现。
+pub fn decrypt_range_to_writer<W: Write>(
+    input_file_path: &str,
+    password: &str,
+    range: Option<Range<u64>>,
+    mut writer: W,
+) -> Result<W, Box<dyn std::error::Error>> {
+    let input_path = Path::new(input_file_path);
+    if !input_path.is_file() {
+        return Err(format!("输入文件不存在: {}", input_file_path).into());
+    }
+
+    let (reader, total_file_length, _) = armor::open(input_path)?;
+    let mut input = EncryptedFile::from_reader(reader, total_file_length, password, false)?;
+    input.body_reader(false)?.finish()?;
+
+    match range {
+        Some(range) => {
+            input.decode_range_into(range, &mut writer)?;
+            Ok(writer)
+        }
+        None => {
+            input.reader.seek(SeekFrom::Start(input.header_bytes.len() as u64))?;
+            Ok(input.decode_into(|_| Ok(writer))?.0)
+        }
+    }
+}
+
+/// 验证 HMAC 后读出文件中保存的明文摘要（不解密内容），算法见文件头；文件中没有摘要时返回 `None`
+pub fn read_digest(input_file_path: &str, password: &str) -> Result<Option<DigestValue>, Box<dyn std::error::Error>> {
+    let (reader, total_file_length, _) = armor::open(Path::new(input_file_path))?;
+    let mut input = EncryptedFile::from_reader(reader, total_file_length, password, false)?;
+    input.body_reader(false)?.finish()?;
+
+    if input.header.digest.is_none() {
+        return Ok(None);
+    }
+    let offset = input.content_length()?;
+    let mut digest = [0u8; DIGEST_LENGTH];
+    input.read_body_at(offset, &mut digest)?;
+    Ok(Some(digest))
+}
+
+/// 只校验完整性与密码：对密文流计算 HMAC，不生成密钥流，也不创建输出文件
+pub fn verify_with_password(input_file_path: &str, password: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // 启动计时器
+    let start_time = start_timer();
+    reset_progress();
+
+    let mut input = EncryptedFile::open(input_file_path, password)?;
+    let encrypted_data_length = input.data_length;
+
+    // 读完密文并验证存储的HMAC
+    input.body_reader(false)?.finish()?;
+
+    // 显示进度完成
+    let duration = start_time.elapsed();
+    update_progress(encrypted_data_length, encrypted_data_length);
+    println!("\u{001B}[0mDEC!: Verified!  cost: {}", format_duration(duration));
+
     Ok(())
 }
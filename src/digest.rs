@@ -0,0 +1,150 @@
+use std::io::{self, Write};
+use sha2::{Digest, Sha256};
+use crate::crypto_utils::*;
+
+/// 明文摘要算法，记录在文件头中
+///
+/// 摘要（均为 32 字节）加密保存在主体中内容之后、填充之前，解密时与输出的明文比对，
+/// 证明解密结果与加密时的原文逐字节相同，而不仅是密文未被改动。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl DigestAlgorithm {
+    /// 解析 `--digest=ALGO`
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "sha256" => Ok(DigestAlgorithm::Sha256),
+            "blake3" => Ok(DigestAlgorithm::Blake3),
+            _ => Err(format!("unknown digest: {}", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// 编码为文件头字段：算法标识
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Sha256 => vec![DIGEST_ID_SHA256],
+            DigestAlgorithm::Blake3 => vec![DIGEST_ID_BLAKE3],
+        }
+    }
+
+    /// 从文件头字段解码
+    pub fn decode(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        match bytes {
+            [DIGEST_ID_SHA256] => Ok(DigestAlgorithm::Sha256),
+            [DIGEST_ID_BLAKE3] => Ok(DigestAlgorithm::Blake3),
+            [id] => Err(format!("不支持的摘要算法: {}", id).into()),
+            _ => Err("摘要参数损坏".into()),
+        }
+    }
+
+    pub fn hasher(&self) -> Hasher {
+        match self {
+            DigestAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            DigestAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+}
+
+impl std::fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 摘要值
+pub type DigestValue = [u8; DIGEST_LENGTH];
+
+/// 增量计算明文摘要
+pub enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => { hasher.update(data); }
+        }
+    }
+
+    pub fn finalize(self) -> DigestValue {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finalize().into(),
+            Hasher::Blake3(hasher) => hasher.finalize().into(),
+        }
+    }
+}
+
+/// 写出数据的同时计算摘要
+pub struct DigestWriter<W: Write> {
+    inner: W,
+    hasher: Hasher,
+}
+
+impl<W: Write> DigestWriter<W> {
+    pub fn new(inner: W, algorithm: DigestAlgorithm) -> Self {
+        Self { inner, hasher: algorithm.hasher() }
+    }
+
+    /// 返回内部写入器与摘要
+    pub fn finish(self) -> (W, DigestValue) {
+        (self.inner, self.hasher.finalize())
+    }
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 十六进制表示，与 `sha256sum`、`b3sum` 的输出一致
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        let mut writer = DigestWriter::new(Vec::new(), DigestAlgorithm::Sha256);
+        writer.write_all(b"abc").unwrap();
+        let (inner, digest) = writer.finish();
+        assert_eq!(inner, b"abc");
+        assert_eq!(to_hex(&digest), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let mut hasher = DigestAlgorithm::Blake3.hasher();
+        hasher.update(b"abc");
+        assert_eq!(to_hex(&hasher.finalize()), "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for algorithm in [DigestAlgorithm::Sha256, DigestAlgorithm::Blake3] {
+            assert_eq!(DigestAlgorithm::decode(&algorithm.encode()).unwrap(), algorithm);
+            assert_eq!(DigestAlgorithm::parse(algorithm.name()).unwrap(), algorithm);
+        }
+        assert!(DigestAlgorithm::decode(&[0xff]).is_err());
+        assert!(DigestAlgorithm::decode(&[]).is_err());
+        assert_eq!(DigestAlgorithm::parse("md5").unwrap_err(), "unknown digest: md5");
+    }
+}
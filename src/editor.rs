@@ -0,0 +1,411 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use crate::armor::{self, ArmorWriter};
+use crate::parity::{self, ParityWriter};
+use crate::volume;
+use crate::crypto_utils::*;
+use crate::decryptor;
+use crate::encryptor::{EncryptOptions, PayloadWriter};
+use crate::header::Header;
+use crate::metadata::Metadata;
+
+/// 编辑结果
+#[derive(Debug, PartialEq)]
+pub enum EditOutcome {
+    /// 内容未改动，原文件保持不变
+    Unchanged,
+    /// 已以原参数重新加密并替换原文件
+    Saved,
+}
+
+/// 存放明文临时文件的目录：优先 `$XDG_RUNTIME_DIR`、`/dev/shm`（tmpfs，不落盘），否则系统临时目录
+///
+/// 第二个值表示是否为内存文件系统。
+pub fn private_temp_base() -> (PathBuf, bool) {
+    let runtime = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+    for dir in runtime.into_iter().chain(Some(PathBuf::from("/dev/shm"))) {
+        if dir.is_dir() {
+            return (dir, true);
+        }
+    }
+    (std::env::temp_dir(), false)
+}
+
+/// 解密到 `temp_base` 下的私有目录（0700，文件 0600），用 `editor` 打开，内容有变化时以原参数重新加密
+///
+/// `editor` 经 `sh -c` 执行，可以带参数（如 `code --wait`）。无论成功与否，临时目录中的全部文件
+/// （包括编辑器的交换与备份文件）都会先以零覆盖再删除；编辑期间忽略 SIGINT/SIGTERM/SIGHUP，
+/// 由编辑器处理。被强制结束时残留的目录在下次 `dec edit` 时清理。
+pub fn edit_file(input_file_path: &str, password: &str, editor: &str, temp_base: &Path) -> Result<EditOutcome, Box<dyn std::error::Error>> {
+    let input_path = Path::new(input_file_path);
+    if !input_path.is_file() {
+        return Err(format!("输入文件不存在: {}", input_file_path).into());
+    }
+    if volume::is_volume(input_path, &mut File::open(input_path)?)? {
+        return Err("分卷文件不能原地改写".into());
+    }
+    sweep_stale(temp_base);
+
+    // 沿用原文件的加密参数
+    let (mut reader, _, armored) = armor::open_quietly(input_path)?;
+    let (header, _) = Header::read(&mut reader)?;
+    drop(reader);
+
+    let workspace = Workspace::new(temp_base)?;
+
+    // 解密并验证；临时文件名沿用原文件名，便于编辑器识别类型
+    let mut kept_metadata = None;
+    let mut temp_path = PathBuf::new();
+    decryptor::decrypt_to_writer(input_file_path, password, |metadata| {
+        let name = metadata.and_then(Metadata::safe_name).map(str::to_string)
+            .unwrap_or_else(|| default_name(input_path));
+        temp_path = workspace.dir.join(name);
+        kept_metadata = metadata.cloned();
+        let mut options = File::options();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        Ok(BufWriter::with_capacity(BUFFER_SIZE, options.open(&temp_path)?))
+    })?.flush()?;
+
+    let before = file_digest(&temp_path)?;
+    run_editor(editor, &temp_path)?;
+    if file_digest(&temp_path)? == before {
+        return Ok(EditOutcome::Unchanged);
+    }
+
+    // 元数据沿用原文件，修改时间取编辑后的时间
+    let metadata = kept_metadata.map(|mut metadata| {
+        metadata.mtime = Metadata::collect(&temp_path, false).ok().and_then(|m| m.mtime);
+        metadata
+    });
+    let options = EncryptOptions {
+        kdf: header.kdf,
+        compression: header.compression,
+        padding: header.padding,
+        metadata: metadata.is_some(),
+        xattrs: false,
+        armor: armored,
+        parity: parity::detect(input_path)?,
+        split: None,
+        verify: None,
+        digest: header.digest,
+    };
+    replace_encrypted(input_path, &temp_path, password, &options, metadata.as_ref())?;
+
+    Ok(EditOutcome::Saved)
+}
+
+/// 加密到同目录的临时文件，校验通过后原子替换原文件
+fn replace_encrypted(input_path: &Path, plaintext_path: &Path, password: &str, options: &EncryptOptions, metadata: Option<&Metadata>) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = input_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+
+    {
+        let mut plaintext = File::open(plaintext_path)?;
+        let writer = BufWriter::with_capacity(BUFFER_SIZE, temp_file.as_file_mut());
+        if options.armor {
+            let mut writer = PayloadWriter::new(ArmorWriter::new(writer)?, password, options, metadata)?;
+            std::io::copy(&mut plaintext, &mut writer)?;
+            writer.finish()?.finish()?.flush()?;
+        } else if let Some(parity) = options.parity {
+            let mut writer = PayloadWriter::new(ParityWriter::new(writer, parity)?, password, options, metadata)?;
+            std::io::copy(&mut plaintext, &mut writer)?;
+            writer.finish()?.finish()?.flush()?;
+        } else {
+            let mut writer = PayloadWriter::new(writer, password, options, metadata)?;
+            std::io::copy(&mut plaintext, &mut writer)?;
+            writer.finish()?.flush()?;
+        }
+    }
+    temp_file.as_file().sync_all()?;
+
+    let temp_path = temp_file.path().to_str().ok_or("临时文件路径无效")?.to_string();
+    decryptor::verify_with_password(&temp_path, password)
+        .map_err(|e| format!("新文件校验失败，保留原文件: {}", e))?;
+
+    std::fs::set_permissions(temp_file.path(), input_path.metadata()?.permissions())?;
+    temp_file.persist(input_path).map_err(|e| e.error)?;
+
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+/// 没有元数据时的临时文件名：去掉 `.asc` 与 `.decx` 后缀
+fn default_name(input_path: &Path) -> String {
+    let name = input_path.file_name().and_then(|n| n.to_str()).unwrap_or("plaintext");
+    let name = name.strip_suffix(".asc").unwrap_or(name);
+    let name = name.strip_suffix(".decx").unwrap_or(name);
+    if name.is_empty() { "plaintext".to_string() } else { name.to_string() }
+}
+
+fn file_digest(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = zeroize::Zeroizing::new(vec![0u8; BUFFER_SIZE]);
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 { break; }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// 运行编辑器并等待退出；编辑器异常退出或本进程收到终止信号时放弃修改
+fn run_editor(editor: &str, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    let mut command = {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(format!("{} \"$1\"", editor)).arg("sh").arg(path);
+        command
+    };
+    #[cfg(not(unix))]
+    let mut command = {
+        let mut command = std::process::Command::new(editor);
+        command.arg(path);
+        command
+    };
+
+    // 终端的 Ctrl-C 同时发给编辑器与本进程；本进程只记录，等编辑器退出后清理
+    #[cfg(unix)]
+    let interrupted = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    #[cfg(unix)]
+    let handlers = [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM, signal_hook::consts::SIGHUP]
+        .into_iter()
+        .map(|signal| signal_hook::flag::register(signal, interrupted.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let status = command.status();
+
+    #[cfg(unix)]
+    for handler in handlers {
+        signal_hook::low_level::unregister(handler);
+    }
+
+    let status = status.map_err(|e| format!("无法启动编辑器 {}: {}", editor, e))?;
+    #[cfg(unix)]
+    if interrupted.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err("编辑被中断，未保存修改".into());
+    }
+    if !status.success() {
+        return Err(format!("编辑器异常退出（{}），未保存修改", status).into());
+    }
+    Ok(())
+}
+
+/// 私有临时目录，离开作用域时清零并删除其中的全部文件
+struct Workspace {
+    dir: PathBuf,
+}
+
+impl Workspace {
+    fn new(base: &Path) -> std::io::Result<Self> {
+        // 目录为 0700；保留目录而不是交给 TempDir 删除，删除前需先清零
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("{}{}-", EDIT_TEMP_PREFIX, std::process::id()))
+            .tempdir_in(base)?
+            .keep();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(Self { dir })
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        wipe_dir(&self.dir);
+    }
+}
+
+/// 以零覆盖目录中的每个文件并落盘，然后删除整个目录
+///
+/// 不跟随符号链接：链接、设备等非普通文件只删除不写入，也不进入链接指向的目录。
+fn wipe_dir(dir: &Path) {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = std::fs::symlink_metadata(&path) else { continue };
+            if metadata.is_dir() {
+                wipe_dir(&path);
+                continue;
+            }
+            if metadata.is_file() && let Ok(mut file) = open_no_follow(&path) {
+                let length = file.metadata().map(|m| m.len()).unwrap_or(0);
+                let _ = std::io::copy(&mut std::io::repeat(0).take(length), &mut file);
+                let _ = file.sync_all();
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/// 以写方式打开文件；检查之后被换成符号链接时打开失败，不会写到链接指向的文件
+#[cfg(unix)]
+fn open_no_follow(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    File::options().write(true).custom_flags(libc::O_NOFOLLOW).open(path)
+}
+
+#[cfg(not(unix))]
+fn open_no_follow(path: &Path) -> std::io::Result<File> {
+    File::options().write(true).open(path)
+}
+
+/// 目录是否可以当作本用户的编辑残留清理：真实目录（非链接）、属于当前用户、权限为 0700
+#[cfg(unix)]
+fn is_own_workspace(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // SAFETY: getuid 总是成功，没有副作用
+    let uid = unsafe { libc::getuid() };
+    std::fs::symlink_metadata(path)
+        .is_ok_and(|m| m.is_dir() && m.uid() == uid && m.mode() & 0o777 == 0o700)
+}
+
+#[cfg(not(unix))]
+fn is_own_workspace(path: &Path) -> bool {
+    std::fs::symlink_metadata(path).is_ok_and(|m| m.is_dir())
+}
+
+/// 清理已退出进程残留的临时目录（仅在有 /proc 的系统上判断进程是否存在）
+///
+/// `/dev/shm` 等目录对所有用户可写，只清理属于当前用户的 0700 目录。
+fn sweep_stale(base: &Path) {
+    if !Path::new("/proc/self").exists() {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(base) else { return };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(pid) = name.to_str()
+            .and_then(|n| n.strip_prefix(EDIT_TEMP_PREFIX))
+            .and_then(|n| n.split('-').next())
+            .and_then(|pid| pid.parse::<u32>().ok()) else { continue };
+
+        if pid != std::process::id() && !Path::new(&format!("/proc/{}", pid)).exists() && is_own_workspace(&entry.path()) {
+            eprintln!("DEC!: removing temporary files left by an earlier edit: {}", entry.path().display());
+            wipe_dir(&entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_derivation::Kdf;
+
+    fn encrypt(dir: &Path, content: &[u8], options: &EncryptOptions) -> String {
+        let input = dir.join("notes.md");
+        std::fs::write(&input, content).unwrap();
+        let output = dir.join("notes.md.decx").to_str().unwrap().to_string();
+        crate::encryptor::encrypt_with_mode(input.to_str().unwrap(), &output, "pw", options).unwrap();
+        std::fs::remove_file(&input).unwrap();
+        output
+    }
+
+    fn decrypt(path: &str) -> Vec<u8> {
+        decryptor::decrypt_to_writer(path, "pw", |_| Ok(Vec::new())).unwrap()
+    }
+
+    #[test]
+    fn test_edit_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_base = tempfile::tempdir().unwrap();
+        let options = EncryptOptions {
+            kdf: Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS },
+            padding: Some(crate::padding::Padding::Bucket(1024)),
+            metadata: true,
+            ..EncryptOptions::default()
+        };
+        let path = encrypt(dir.path(), b"hello\n", &options);
+        let original = std::fs::read(&path).unwrap();
+
+        // 未改动时不重新加密
+        assert_eq!(edit_file(&path, "pw", "true", temp_base.path()).unwrap(), EditOutcome::Unchanged);
+        assert_eq!(std::fs::read(&path).unwrap(), original);
+
+        // 编辑器看到的是原文件名
+        let outcome = edit_file(&path, "pw", "test \"$(basename \"$1\")\" = notes.md && sed -i s/hello/bye/", temp_base.path()).unwrap();
+        assert_eq!(outcome, EditOutcome::Saved);
+        assert_eq!(decrypt(&path), b"bye\n");
+
+        // 参数沿用原文件
+        let (header, _) = Header::read(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!(header.kdf, options.kdf);
+        assert_eq!(header.padding, options.padding);
+        assert!(header.metadata);
+
+        // 临时目录已清除
+        assert_eq!(std::fs::read_dir(temp_base.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_edit_file_failures_keep_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_base = tempfile::tempdir().unwrap();
+        let options = EncryptOptions { kdf: Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS }, ..EncryptOptions::default() };
+        let path = encrypt(dir.path(), b"keep\n", &options);
+        let original = std::fs::read(&path).unwrap();
+
+        // 编辑器修改后异常退出
+        assert!(edit_file(&path, "pw", "sed -i s/keep/lost/ \"$1\" && false", temp_base.path()).is_err());
+        assert!(edit_file(&path, "wrong", "true", temp_base.path()).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), original);
+        assert_eq!(std::fs::read_dir(temp_base.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_sweep_stale() {
+        let temp_base = tempfile::tempdir().unwrap();
+        let stale = temp_base.path().join(format!("{}{}-abc", EDIT_TEMP_PREFIX, u32::MAX));
+        let live = temp_base.path().join(format!("{}{}-abc", EDIT_TEMP_PREFIX, std::process::id()));
+        for dir in [&stale, &live] {
+            std::fs::create_dir(dir).unwrap();
+            std::fs::write(dir.join("secret.txt"), b"plaintext").unwrap();
+        }
+
+        #[cfg(unix)]
+        let (outside, foreign) = {
+            use std::os::unix::fs::{symlink, PermissionsExt};
+            // 残留目录中指向外部的链接：只删除链接，不写入也不进入链接指向的位置
+            let outside = tempfile::tempdir().unwrap();
+            std::fs::write(outside.path().join("thesis.tex"), b"keep me").unwrap();
+            symlink(outside.path().join("thesis.tex"), stale.join("link")).unwrap();
+            symlink(outside.path(), stale.join("dir-link")).unwrap();
+            std::fs::set_permissions(&stale, std::fs::Permissions::from_mode(0o700)).unwrap();
+
+            // 不是 0700 的目录不是本程序创建的，不清理
+            let foreign = temp_base.path().join(format!("{}{}-xyz", EDIT_TEMP_PREFIX, u32::MAX));
+            std::fs::create_dir(&foreign).unwrap();
+            std::fs::set_permissions(&foreign, std::fs::Permissions::from_mode(0o755)).unwrap();
+            (outside, foreign)
+        };
+
+        sweep_stale(temp_base.path());
+        if Path::new("/proc/self").exists() {
+            assert!(!stale.exists());
+        }
+        assert!(live.join("secret.txt").exists());
+
+        #[cfg(unix)]
+        {
+            assert_eq!(std::fs::read(outside.path().join("thesis.tex")).unwrap(), b"keep me");
+            assert!(foreign.exists());
+        }
+    }
+
+    #[test]
+    fn test_default_name() {
+        assert_eq!(default_name(Path::new("a/notes.md.decx")), "notes.md");
+        assert_eq!(default_name(Path::new("notes.md.decx.asc")), "notes.md");
+        assert_eq!(default_name(Path::new(".decx")), "plaintext");
+    }
+}
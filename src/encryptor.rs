@@ -4,16 +4,247 @@ use std::path::Path;
 use aes::Aes256;
 use ctr::Ctr128BE;
 use ctr::cipher::{KeyIvInit, StreamCipher};
+use sha2::{Digest, Sha256};
 use crate::crypto_utils::*;
 use crate::progress_utils::*;
-use crate::key_derivation;
+use crate::header::Header;
+use crate::key_derivation::{self, Kdf};
 use crate::hmac_validator::HmacValidator;
+use crate::compression::{Compression, Compressor};
+use crate::padding::Padding;
+use crate::metadata::Metadata;
+use crate::armor::ArmorWriter;
+use crate::parity::{Parity, ParityWriter};
+use crate::volume::{self, VolumeWriter};
+use crate::decryptor;
+use crate::digest::{DigestAlgorithm, Hasher};
+use crate::secret::SecretBytes;
 
 type Aes256Ctr = Ctr128BE<Aes256>;
 
-pub fn encrypt_with_mode(input_file_path: &str, output_file_path: &str, password: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// 加密选项
+#[derive(Debug, Clone, Default)]
+pub struct EncryptOptions {
+    /// 密钥派生算法及参数
+    pub kdf: Kdf,
+    /// 加密前压缩明文
+    pub compression: Option<Compression>,
+    /// 隐藏长度的填充
+    pub padding: Option<Padding>,
+    /// 在主体中保存原文件名、权限与修改时间
+    pub metadata: bool,
+    /// 元数据中一并保存扩展属性
+    pub xattrs: bool,
+    /// 输出 ASCII 封装（base64 + 起止行 + 校验和）
+    pub armor: bool,
+    /// 加密后附加 Reed-Solomon 纠错数据（不能与 ASCII 封装同时使用）
+    pub parity: Option<Parity>,
+    /// 按此大小（含卷头）把输出拆成 `.001`、`.002`……分卷
+    pub split: Option<u64>,
+    /// 写完后重新读取输出自检
+    pub verify: Option<Verification>,
+    /// 在主体中保存明文摘要，解密时比对
+    pub digest: Option<DigestAlgorithm>,
+}
+
+/// 加密后的自检方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    /// 落盘后重新读取输出，重新计算并比对 HMAC
+    Mac,
+    /// 在此之上完整解密输出，与加密时计算的明文 SHA-256 比对
+    Content,
+}
+
+impl Verification {
+    /// 解析 `--verify=MODE`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "mac" => Ok(Verification::Mac),
+            "content" => Ok(Verification::Content),
+            _ => Err(format!("unknown verification: {}", value)),
+        }
+    }
+}
+
+/// 流式加密写入器：创建时写出文件头，写入的明文加密后输出，`finish` 时追加HMAC
+pub struct EncryptingWriter<W: Write> {
+    writer: W,
+    header: Header,
+    encryption_key: SecretBytes,
+    hmac: HmacValidator,
+    parallel_parts: usize,
+    single_cipher: Option<Aes256Ctr>,
+    buffer: SecretBytes,
+    total_written: u64,
+}
+
+impl<W: Write> EncryptingWriter<W> {
+    /// 派生密钥并写入文件头（`header` 由 `Header::new` 生成，带有随机盐和IV）
+    pub fn new(writer: W, password: &str, header: Header) -> Result<Self, Box<dyn std::error::Error>> {
+        // 派生主密钥（密码先按文件头记录的方式规范化）
+        let password = header.normalization.apply(password);
+        let master_key = key_derivation::derive_master_key(&header.kdf, password.expose().as_bytes(), &header.salt)?;
+
+        Self::with_master_key(writer, &master_key, header)
+    }
+
+    /// 使用已派生的主密钥（须与文件头的 KDF、盐和规范化方式一致）写入文件头
+    pub fn with_master_key(mut writer: W, master_key: &SecretBytes, mut header: Header) -> Result<Self, Box<dyn std::error::Error>> {
+        // 使用HKDF派生加密密钥和HMAC密钥
+        let (encryption_key, hmac_key) = key_derivation::derive_encryption_and_hmac_keys(master_key)?;
+
+        // 派生口令校验值，解密时可在写出任何数据前识别错误密码
+        header.key_check = Some(key_derivation::derive_key_check(master_key)?);
+
+        // 写入文件头（魔数、版本、KDF 参数、盐、IV）
+        let header_bytes = header.encode();
+        writer.write_all(&header_bytes)?;
+
+        // 创建HMAC计算器（文件头同样受保护）
+        let mut hmac = HmacValidator::new(hmac_key.expose())?;
+        hmac.update(&header_bytes);
+
+        // 单流加密器（仅在 parts==1 时使用）
+        let parallel_parts = get_parts();
+        let single_cipher = if parallel_parts == 1 {
+            Some(Aes256Ctr::new(encryption_key.expose().into(), header.iv.as_slice().into()))
+        } else { None };
+
+        Ok(Self {
+            writer,
+            header,
+            encryption_key,
+            hmac,
+            parallel_parts,
+            single_cipher,
+            buffer: SecretBytes::zeroed(BUFFER_SIZE),
+            total_written: 0,
+        })
+    }
+
+    /// 已写入的主体字节数
+    pub fn bytes_written(&self) -> u64 {
+        self.total_written
+    }
+
+    /// 获取并写入HMAC，返回内部写入器
+    pub fn finish(self) -> Result<W, Box<dyn std::error::Error>> {
+        let Self { mut writer, hmac, .. } = self;
+        writer.write_all(&hmac.finalize())?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for EncryptingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let bytes = std::cmp::min(buf.len(), BUFFER_SIZE);
+        let chunk = &mut self.buffer.expose_mut()[..bytes];
+        chunk.copy_from_slice(&buf[..bytes]);
+
+        if self.parallel_parts == 1 {
+            // 使用CTR模式加密数据
+            if let Some(cipher) = &mut self.single_cipher { cipher.apply_keystream(chunk); }
+        } else {
+            // 并行处理：根据绝对偏移计算子流位置
+            crate::parallel_handler::ctr_apply_in_parts(
+                self.encryption_key.expose(),
+                self.header.iv.as_slice(),
+                chunk,
+                self.total_written as usize,
+            ).map_err(|e| std::io::Error::other(format!("parallel encrypt error: {}", e)))?;
+        }
+
+        // 更新HMAC（对密文计算）
+        self.hmac.update(chunk);
+        // 写入加密后的数据
+        self.writer.write_all(chunk)?;
+
+        self.total_written += bytes as u64;
+        Ok(bytes)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// 明文写入器：按加密选项处理明文（如压缩），再交给 `EncryptingWriter`
+pub struct PayloadWriter<W: Write> {
+    writer: EncryptingWriter<W>,
+    compressor: Option<Compressor>,
+    padding: Option<Padding>,
+    digest: Option<Hasher>,
+}
+
+impl<W: Write> PayloadWriter<W> {
+    /// 派生密钥并写入文件头；有元数据时，先写出元数据块（不压缩）
+    pub fn new(writer: W, password: &str, options: &EncryptOptions, metadata: Option<&Metadata>) -> Result<Self, Box<dyn std::error::Error>> {
+        // 生成盐和IV
+        let mut header = Header::new(options.kdf);
+        header.compression = options.compression;
+        header.padding = options.padding;
+        header.metadata = metadata.is_some();
+        header.digest = options.digest;
+
+        let mut writer = EncryptingWriter::new(writer, password, header)?;
+        if let Some(metadata) = metadata {
+            metadata.write_to(&mut writer)?;
+        }
+
+        Ok(Self {
+            writer,
+            compressor: options.compression.map(Compressor::new),
+            padding: options.padding,
+            digest: options.digest.map(|algorithm| algorithm.hasher()),
+        })
+    }
+
+    /// 写出剩余数据与HMAC，返回内部写入器
+    pub fn finish(mut self) -> Result<W, Box<dyn std::error::Error>> {
+        if let Some(compressor) = &mut self.compressor {
+            compressor.finish(&mut self.writer)?;
+        }
+
+        // 内容之后追加明文摘要
+        if let Some(hasher) = self.digest.take() {
+            self.writer.write_all(&hasher.finalize())?;
+        }
+
+        // 追加全零填充与填充长度
+        if let Some(padding) = self.padding {
+            let padding_length = padding.padding_length(self.writer.bytes_written())?;
+            std::io::copy(&mut std::io::repeat(0).take(padding_length), &mut self.writer)?;
+            self.writer.write_all(&padding_length.to_le_bytes())?;
+        }
+
+        self.writer.finish()
+    }
+}
+
+impl<W: Write> Write for PayloadWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = match &mut self.compressor {
+            Some(compressor) => {
+                compressor.write(buf, &mut self.writer)?;
+                buf.len()
+            }
+            None => self.writer.write(buf)?,
+        };
+        if let Some(hasher) = &mut self.digest {
+            hasher.update(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub fn encrypt_with_mode(input_file_path: &str, output_file_path: &str, password: &str, options: &EncryptOptions) -> Result<(), Box<dyn std::error::Error>> {
     let input_path = Path::new(input_file_path);
-    let output_path = Path::new(output_file_path);
 
     // 检查输入文件是否存在
     if !input_path.exists() || !input_path.is_file() {
@@ -22,87 +253,162 @@ pub fn encrypt_with_mode(input_file_path: &str, output_file_path: &str, password
     
     // 启动计时器
     let start_time = start_timer();
+    reset_progress();
+
+    // 读取原文件的元数据
+    let metadata = if options.metadata { Some(Metadata::collect(input_path, options.xattrs)?) } else { None };
     
-    // 生成盐和IV
-    let salt = generate_salt();
-    let iv = generate_iv();
-    
-    // 使用Argon2派生主密钥
-    let master_key = key_derivation::derive_master_key(password.as_bytes(), &salt)?;
-    
-    // 使用HKDF派生加密密钥和HMAC密钥
-    let (encryption_key, hmac_key) = key_derivation::derive_encryption_and_hmac_keys(&master_key)?;
-    
-    // 创建输出文件（放大写缓冲）
-    let mut output_file = File::create(output_path)?;
-    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, &mut output_file);
-    
-    // 写入魔数
-    writer.write_all(MAGIC_NUMBER.as_bytes())?;
-    
-    // 写入版本字节
-    writer.write_all(&[VERSION_SIGN])?;
-    
-    // 写入盐
-    writer.write_all(&salt)?;
-    
-    // 写入IV
-    writer.write_all(&iv)?;
+    // 创建输出文件或第一卷（放大写缓冲），写入文件头
+    let mut output = Output::create(output_file_path, options.split)?;
+    let writer = BufWriter::with_capacity(BUFFER_SIZE, &mut output);
+    let file_size = input_path.metadata()?.len();
+
+    // 需要比对内容时，加密的同时计算明文摘要
+    let mut digest = (options.verify == Some(Verification::Content)).then(Sha256::new);
+
+    if options.armor {
+        let writer = PayloadWriter::new(ArmorWriter::new(writer)?, password, options, metadata.as_ref())?;
+        encrypt_file(input_path, writer, digest.as_mut())?.finish()?.flush()?;
+    } else if let Some(parity) = options.parity {
+        let writer = PayloadWriter::new(ParityWriter::new(writer, parity)?, password, options, metadata.as_ref())?;
+        encrypt_file(input_path, writer, digest.as_mut())?.finish()?.flush()?;
+    } else {
+        let writer = PayloadWriter::new(writer, password, options, metadata.as_ref())?;
+        encrypt_file(input_path, writer, digest.as_mut())?.flush()?;
+    }
     
-    // 创建HMAC计算器
-    let mut hmac = HmacValidator::new(&hmac_key)?;
+    // 显示完成状态
+    let duration = start_time.elapsed();
+    update_progress(file_size, file_size);
+    println!("\u{001B}[0mDEC!: Done!  cost: {}", format_duration(duration));
+
+    let output_file_path = output.finish(output_file_path, options.verify.is_some())?;
+    if let Some(verification) = options.verify {
+        self_check(&output_file_path, password, verification, digest)
+            .map_err(|e| format!("输出文件未通过自检，不可使用: {}", e))?;
+    }
     
+    Ok(())
+}
+
+/// 输出目标：单个文件，或按大小拆分的一组分卷
+enum Output {
+    File(File),
+    Volumes(Box<VolumeWriter>),
+}
+
+impl Output {
+    fn create(output_file_path: &str, split: Option<u64>) -> std::io::Result<Self> {
+        match split {
+            Some(volume_size) => Ok(Output::Volumes(Box::new(VolumeWriter::create(output_file_path, volume_size)?))),
+            None => Ok(Output::File(File::create(output_file_path)?)),
+        }
+    }
+
+    /// 结束写出，`sync` 为 true 时确保落盘（分卷总是逐卷落盘）；返回读取时使用的路径
+    fn finish(self, output_file_path: &str, sync: bool) -> std::io::Result<String> {
+        match self {
+            Output::File(file) => {
+                if sync {
+                    file.sync_all()?;
+                }
+                Ok(output_file_path.to_string())
+            }
+            Output::Volumes(writer) => {
+                writer.finish()?;
+                Ok(volume::volume_path(output_file_path, 1))
+            }
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Output::File(file) => file.write(buf),
+            Output::Volumes(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Output::File(file) => file.flush(),
+            Output::Volumes(writer) => writer.flush(),
+        }
+    }
+}
+
+/// 从磁盘重新读取刚写出的文件：校验 HMAC，或完整解密并比对明文摘要
+fn self_check(output_file_path: &str, password: &str, verification: Verification, digest: Option<Sha256>) -> Result<(), Box<dyn std::error::Error>> {
+    match (verification, digest) {
+        (Verification::Content, Some(digest)) => {
+            let decrypted = decryptor::decrypt_to_writer(output_file_path, password, |_| Ok(Sha256::new()))?;
+            if decrypted.finalize() != digest.finalize() {
+                return Err("解密结果与输入的 SHA-256 不一致".into());
+            }
+            println!("\u{001B}[0mDEC!: Content verified!");
+            Ok(())
+        }
+        _ => decryptor::verify_with_password(output_file_path, password),
+    }
+}
+
+/// 流式读取输入文件并加密，写入HMAC后返回内部写入器
+fn encrypt_file<W: Write>(input_path: &Path, mut writer: PayloadWriter<W>, mut digest: Option<&mut Sha256>) -> Result<W, Box<dyn std::error::Error>> {
     // 打开输入文件（放大读缓冲）
     let input_file = File::open(input_path)?;
     let mut reader = BufReader::with_capacity(BUFFER_SIZE, input_file);
     let file_size = input_path.metadata()?.len();
     
     // 流式加密数据
-    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut buffer = SecretBytes::zeroed(BUFFER_SIZE);
     let mut total_read: u64 = 0;
-    let parallel_parts = get_parts();
-
-    // 单流加密器（仅在 parts==1 时使用）
-    let mut single_cipher = if parallel_parts == 1 {
-        Some(Aes256Ctr::new(encryption_key.as_slice().into(), iv.as_slice().into()))
-    } else { None };
     
     loop {
-        let bytes_read = reader.read(&mut buffer)?;
+        let bytes_read = reader.read(buffer.expose_mut())?;
         if bytes_read == 0 { break; }
-        let chunk = &mut buffer[..bytes_read];
-
-        if parallel_parts == 1 {
-            // 使用CTR模式加密数据
-            if let Some(cipher) = &mut single_cipher { cipher.apply_keystream(chunk); }
-        } else {
-            // 并行处理：根据绝对偏移计算子流位置
-            crate::parallel_handler::ctr_apply_in_parts(
-                encryption_key.as_slice(),
-                iv.as_slice(),
-                chunk,
-                total_read as usize,
-            ).map_err(|e| format!("parallel encrypt error: {}", e))?;
-        }
 
-        // 更新HMAC（对密文计算）
-        hmac.update(chunk);
-        // 写入加密后的数据
-        writer.write_all(chunk)?;
+        writer.write_all(&buffer.expose()[..bytes_read])?;
+        if let Some(digest) = digest.as_mut() { digest.update(&buffer.expose()[..bytes_read]); }
 
         total_read += bytes_read as u64;
         update_progress(total_read, file_size);
     }
     
     // 获取并写入HMAC
-    let hmac_code = hmac.finalize();
-    writer.write_all(&hmac_code)?;
-    writer.flush()?;
-    
-    // 显示完成状态
-    let duration = start_time.elapsed();
-    update_progress(file_size, file_size);
-    println!("\u{001B}[0mDEC!: Done!  cost: {}", format_duration(duration));
-    
-    Ok(())
-}
\ No newline at end of file
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("plain.txt");
+        let output = dir.path().join("plain.txt.decx");
+        std::fs::write(&input, b"check me").unwrap();
+
+        let options = EncryptOptions {
+            kdf: Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS },
+            verify: Some(Verification::Content),
+            ..EncryptOptions::default()
+        };
+        let output = output.to_str().unwrap();
+        encrypt_with_mode(input.to_str().unwrap(), output, "pw", &options).unwrap();
+
+        // 摘要不符时失败
+        let mut digest = Sha256::new();
+        digest.update(b"check m3");
+        assert!(self_check(output, "pw", Verification::Content, Some(digest)).is_err());
+        assert!(self_check(output, "pw", Verification::Mac, None).is_ok());
+
+        // 输出被改动时失败
+        let mut bytes = std::fs::read(output).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(output, &bytes).unwrap();
+        assert!(self_check(output, "pw", Verification::Mac, None).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use std::io::{self, Read, Write};
+use crate::crypto_utils::*;
+use crate::header::Header;
+use crate::secret::SecretBytes;
+
+pub mod v2;
+pub mod v3;
+
+/// 文件头解析器：解析版本字节之后的部分，并把读到的字节追加到原始字节中
+pub type HeaderReader = fn(&mut dyn Read, &mut Vec<u8>) -> Result<Header, Box<dyn std::error::Error>>;
+
+/// 主体解码器：将解密后的主体解码为明文，返回写出的字节数
+pub type BodyDecoder = fn(&Header, &mut dyn Read, &mut dyn Write) -> io::Result<u64>;
+
+/// 一个文件格式版本：各自的文件头解析器与主体解码器
+pub struct Format {
+    /// 版本字节
+    pub version: u8,
+    pub read_header: HeaderReader,
+    /// HMAC 是否覆盖文件头
+    pub authenticated_header: bool,
+    pub decode_body: BodyDecoder,
+}
+
+/// 所有可读取的格式版本，新版本追加在末尾
+pub static FORMATS: [Format; 2] = [
+    Format {
+        version: LEGACY_VERSION_SIGN,
+        read_header: v2::read_header,
+        authenticated_header: false,
+        decode_body: v2::decode_body,
+    },
+    Format {
+        version: VERSION_SIGN,
+        read_header: v3::read_header,
+        authenticated_header: true,
+        decode_body: v3::decode_body,
+    },
+];
+
+/// 按版本字节查找格式
+pub fn lookup(version: u8) -> Result<&'static Format, Box<dyn std::error::Error>> {
+    FORMATS.iter()
+        .find(|f| f.version == version)
+        .ok_or_else(|| format!("不支持的文件版本: {}", version).into())
+}
+
+/// 原样复制主体（放大缓冲，保证并行解密的分块足够大；缓冲区存放明文，用后清零）
+pub fn copy_body(reader: &mut dyn Read, writer: &mut dyn Write) -> io::Result<u64> {
+    let mut buffer = SecretBytes::zeroed(BUFFER_SIZE);
+    let mut total = 0u64;
+
+    loop {
+        let bytes_read = reader.read(buffer.expose_mut())?;
+        if bytes_read == 0 { break; }
+        writer.write_all(&buffer.expose()[..bytes_read])?;
+        total += bytes_read as u64;
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup(LEGACY_VERSION_SIGN).unwrap().version, LEGACY_VERSION_SIGN);
+        assert_eq!(lookup(VERSION_SIGN).unwrap().version, VERSION_SIGN);
+        assert!(lookup(0x01).is_err());
+        assert!(lookup(0xff).is_err());
+    }
+
+    #[test]
+    fn test_current_version_registered_last() {
+        // 新文件总是以最新版本写出
+        assert_eq!(FORMATS.last().unwrap().version, VERSION_SIGN);
+    }
+}
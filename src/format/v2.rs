@@ -0,0 +1,35 @@
+use std::io::{self, Read, Write};
+use crate::crypto_utils::*;
+use crate::header::Header;
+use crate::key_derivation::{Kdf, PasswordNormalization};
+
+/// v2 文件头：盐 + IV，KDF 固定为默认参数的 Argon2id
+pub fn read_header(reader: &mut dyn Read, raw: &mut Vec<u8>) -> Result<Header, Box<dyn std::error::Error>> {
+    let mut salt = vec![0u8; SALT_LENGTH];
+    reader.read_exact(&mut salt)?;
+    let mut iv = vec![0u8; IV_LENGTH];
+    reader.read_exact(&mut iv)?;
+
+    raw.extend_from_slice(&salt);
+    raw.extend_from_slice(&iv);
+
+    Ok(Header {
+        version: LEGACY_VERSION_SIGN,
+        kdf: Kdf::default(),
+        salt,
+        iv,
+        key_check: None,
+        created: None,
+        creator: None,
+        normalization: PasswordNormalization::None,
+        compression: None,
+        padding: None,
+        metadata: false,
+        digest: None,
+    })
+}
+
+/// v2 主体：解密结果即明文
+pub fn decode_body(_header: &Header, reader: &mut dyn Read, writer: &mut dyn Write) -> io::Result<u64> {
+    crate::format::copy_body(reader, writer)
+}
@@ -0,0 +1,124 @@
+use std::io::{self, Read, Write};
+use crate::compression::{decompress_body, Compression};
+use crate::crypto_utils::*;
+use crate::digest::DigestAlgorithm;
+use crate::header::Header;
+use crate::key_derivation::{Kdf, PasswordNormalization};
+use crate::padding::Padding;
+
+/// v3 文件头：u32 头部长度（小端）+ TLV 字段（u8 标签 + u16 长度 + 值），整个文件头参与 HMAC 计算
+pub fn encode_header(header: &Header) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_field(&mut body, TAG_KDF, &header.kdf.encode());
+    push_field(&mut body, TAG_SALT, &header.salt);
+    push_field(&mut body, TAG_IV, &header.iv);
+    if let Some(key_check) = &header.key_check {
+        push_field(&mut body, TAG_KEY_CHECK, key_check);
+    }
+    if let Some(created) = header.created {
+        push_field(&mut body, TAG_CREATED, &created.to_le_bytes());
+    }
+    if let Some(creator) = &header.creator {
+        push_field(&mut body, TAG_CREATOR, creator.as_bytes());
+    }
+    if header.normalization != PasswordNormalization::None {
+        push_field(&mut body, TAG_PASSWORD_NORMALIZATION, &[header.normalization.id()]);
+    }
+    if let Some(compression) = &header.compression {
+        push_field(&mut body, TAG_COMPRESSION, &compression.encode());
+    }
+    if let Some(padding) = &header.padding {
+        push_field(&mut body, TAG_PADDING, &padding.encode());
+    }
+    if header.metadata {
+        push_field(&mut body, TAG_METADATA, &[]);
+    }
+    if let Some(digest) = &header.digest {
+        push_field(&mut body, TAG_DIGEST, &digest.encode());
+    }
+
+    let mut out = Vec::with_capacity(MAGIC_NUMBER.len() + 5 + body.len());
+    out.extend_from_slice(MAGIC_NUMBER.as_bytes());
+    out.push(VERSION_SIGN);
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(&body);
+    out
+}
+
+/// 解析 v3 文件头
+pub fn read_header(reader: &mut dyn Read, raw: &mut Vec<u8>) -> Result<Header, Box<dyn std::error::Error>> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_le_bytes(length) as usize;
+    if length > HEADER_MAX_LENGTH {
+        return Err("文件头长度异常".into());
+    }
+
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+    raw.extend_from_slice(&(length as u32).to_le_bytes());
+    raw.extend_from_slice(&body);
+
+    parse_fields(&body)
+}
+
+/// v3 主体：解密结果即明文，或按文件头记录的参数分块压缩
+pub fn decode_body(header: &Header, reader: &mut dyn Read, writer: &mut dyn Write) -> io::Result<u64> {
+    match &header.compression {
+        Some(compression) => decompress_body(compression, reader, writer),
+        None => crate::format::copy_body(reader, writer),
+    }
+}
+
+/// 写入一个 TLV 字段
+fn push_field(out: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    out.push(tag);
+    out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+/// 解析 TLV 字段
+fn parse_fields(mut body: &[u8]) -> Result<Header, Box<dyn std::error::Error>> {
+    let mut kdf: Option<Kdf> = None;
+    let mut salt = None;
+    let mut iv = None;
+    let mut key_check = None;
+    let mut created = None;
+    let mut creator = None;
+    let mut normalization = PasswordNormalization::None;
+    let mut compression = None;
+    let mut padding = None;
+    let mut metadata = false;
+    let mut digest = None;
+
+    while !body.is_empty() {
+        if body.len() < 3 {
+            return Err("文件头字段损坏".into());
+        }
+        let tag = body[0];
+        let length = u16::from_le_bytes([body[1], body[2]]) as usize;
+        let value = body.get(3..3 + length).ok_or("文件头字段损坏")?;
+        body = &body[3 + length..];
+
+        match tag {
+            TAG_KDF => kdf = Some(Kdf::decode(value)?),
+            TAG_SALT if (8..=64).contains(&value.len()) => salt = Some(value.to_vec()),
+            TAG_IV if value.len() == IV_LENGTH => iv = Some(value.to_vec()),
+            TAG_KEY_CHECK if value.len() == KEY_CHECK_LENGTH => key_check = Some(value.to_vec()),
+            TAG_CREATED if value.len() == 8 => created = Some(u64::from_le_bytes(value.try_into()?)),
+            TAG_CREATOR => creator = Some(String::from_utf8_lossy(value).into_owned()),
+            TAG_PASSWORD_NORMALIZATION if value.len() == 1 => normalization = PasswordNormalization::from_id(value[0])?,
+            TAG_COMPRESSION => compression = Some(Compression::decode(value)?),
+            TAG_PADDING => padding = Some(Padding::decode(value)?),
+            TAG_METADATA if value.is_empty() => metadata = true,
+            TAG_DIGEST => digest = Some(DigestAlgorithm::decode(value)?),
+            // 未知字段可能改变解密方式，不能忽略
+            _ => return Err(format!("不支持的文件头字段: {}", tag).into()),
+        }
+    }
+
+    match (kdf, salt, iv) {
+        (Some(kdf), Some(salt), Some(iv)) => Ok(Header { version: VERSION_SIGN, kdf, salt, iv, key_check, created, creator, normalization, compression, padding, metadata, digest }),
+        _ => Err("文件头缺少必要字段".into()),
+    }
+}
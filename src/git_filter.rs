@@ -0,0 +1,226 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use crate::crypto_utils::*;
+use crate::decryptor;
+use crate::encryptor::EncryptingWriter;
+use crate::header::Header;
+use crate::hmac_validator::HmacValidator;
+use crate::key_derivation::{self, Kdf, PasswordNormalization};
+use crate::secret::SecretBytes;
+
+/// 仓库的加密参数，保存在工作树根目录的 `.decgit` 中并随仓库提交，所有协作者共用
+///
+/// 固定的盐使同一密码总是派生出同一主密钥，配合由明文决定的 IV，
+/// 未改动的文件每次 clean 都得到相同的密文，不会显示为已修改。
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoParams {
+    pub kdf: Kdf,
+    pub salt: Vec<u8>,
+}
+
+impl RepoParams {
+    /// 以随机盐创建
+    pub fn new(kdf: Kdf) -> Self {
+        Self { kdf, salt: generate_salt() }
+    }
+
+    /// 读取 `kdf = ...`、`salt = ...` 两行
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+
+        let (mut kdf, mut salt) = (None, None);
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            match line.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                Some(("kdf", value)) => kdf = Some(Kdf::parse(value)?),
+                Some(("salt", value)) => salt = Some(STANDARD.decode(value).map_err(|_| "仓库参数中的盐损坏")?),
+                _ => return Err(format!("无法识别的仓库参数: {}", line).into()),
+            }
+        }
+
+        match (kdf, salt) {
+            (Some(kdf), Some(salt)) if salt.len() == SALT_LENGTH => Ok(Self { kdf, salt }),
+            _ => Err(format!("{} 缺少 kdf 或 salt", path.display()).into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, format!(
+            "# dec git 过滤器参数，随仓库提交；密码另行分发\nkdf = {}\nsalt = {}\n",
+            self.kdf, STANDARD.encode(&self.salt)
+        ))
+    }
+
+    /// 在当前目录（git 在工作树根目录运行过滤器）或仓库根目录查找参数文件
+    pub fn find() -> Result<Self, Box<dyn std::error::Error>> {
+        let local = Path::new(GIT_PARAMS_FILE);
+        if local.exists() {
+            return Self::load(local);
+        }
+        Self::load(&git_toplevel(Path::new("."))?.join(GIT_PARAMS_FILE))
+    }
+}
+
+/// clean：把工作树中的明文加密为要存入仓库的内容
+///
+/// 输出是普通的加密文件（可直接 `dec -d`），但盐取自仓库参数、IV 由明文的 HMAC 决定，
+/// 且不写创建时间与创建程序，相同明文总得到相同密文。代价是相同的文件在仓库中可以被看出相同。
+/// 已经是加密文件的输入原样返回。
+pub fn clean(plaintext: &[u8], password: &str, params: &RepoParams) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if Header::read(&mut &plaintext[..]).is_ok() {
+        return Ok(plaintext.to_vec());
+    }
+
+    let password = PasswordNormalization::Nfc.apply(password);
+    let master_key = key_derivation::derive_master_key(&params.kdf, password.expose().as_bytes(), &params.salt)?;
+
+    let header = Header {
+        salt: params.salt.clone(),
+        iv: synthetic_iv(&master_key, plaintext)?,
+        created: None,
+        creator: None,
+        ..Header::new(params.kdf)
+    };
+
+    let mut writer = EncryptingWriter::with_master_key(Vec::new(), &master_key, header)?;
+    writer.write_all(plaintext)?;
+    writer.finish()
+}
+
+/// smudge：把仓库中的加密内容解密为工作树中的明文；未加密的内容（如启用过滤器之前提交的文件）原样返回
+pub fn smudge(stored: Vec<u8>, password: &str) -> Result<SecretBytes, Box<dyn std::error::Error>> {
+    if Header::read(&mut stored.as_slice()).is_err() {
+        return Ok(SecretBytes::from(stored));
+    }
+    decryptor::decrypt_bytes(stored, password)
+}
+
+/// 由明文决定的 IV：HMAC-SHA256(HKDF(主密钥, "dec-git-iv"), 明文) 的前 16 字节
+fn synthetic_iv(master_key: &SecretBytes, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut iv_key = SecretBytes::key(HMAC_KEY_LENGTH);
+    Hkdf::<Sha256>::new(None, master_key.expose())
+        .expand(b"dec-git-iv", iv_key.expose_mut())
+        .map_err(|_| "Failed to derive IV key")?;
+
+    let mut hmac = HmacValidator::new(iv_key.expose())?;
+    hmac.update(plaintext);
+    Ok(hmac.finalize()[..IV_LENGTH].to_vec())
+}
+
+/// 在仓库中启用过滤器：写入参数文件（已有则保留）、`git config` 过滤器命令与 `.gitattributes`
+///
+/// 过滤器命令以 `exe` 运行本程序，`password_option` 是写进命令的非交互密码来源；返回工作树根目录。
+pub fn init(dir: &Path, exe: &Path, patterns: &[String], password_option: (&str, &str), kdf: Kdf) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let root = git_toplevel(dir)?;
+
+    let params_path = root.join(GIT_PARAMS_FILE);
+    if !params_path.exists() {
+        RepoParams::new(kdf).save(&params_path)?;
+    }
+
+    let (option, value) = password_option;
+    for mode in ["clean", "smudge"] {
+        let command = format!("{} git-filter {} {} {}",
+            shell_quote(&exe.to_string_lossy()), mode, option, shell_quote(value));
+        git_config(&root, &format!("filter.{}.{}", GIT_FILTER_NAME, mode), &command)?;
+    }
+    // 过滤器失败时让 git 报错，而不是把明文存进仓库
+    git_config(&root, &format!("filter.{}.required", GIT_FILTER_NAME), "true")?;
+
+    let attributes_path = root.join(".gitattributes");
+    let mut attributes = std::fs::read_to_string(&attributes_path).unwrap_or_default();
+    for pattern in patterns {
+        let line = format!("{} filter={}", pattern, GIT_FILTER_NAME);
+        if !attributes.lines().any(|l| l.trim() == line) {
+            if !attributes.is_empty() && !attributes.ends_with('\n') {
+                attributes.push('\n');
+            }
+            attributes.push_str(&line);
+            attributes.push('\n');
+        }
+    }
+    std::fs::write(&attributes_path, attributes)?;
+
+    Ok(root)
+}
+
+/// 工作树根目录
+fn git_toplevel(dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let output = Command::new("git").args(["rev-parse", "--show-toplevel"]).current_dir(dir).output()
+        .map_err(|e| format!("无法运行 git: {}", e))?;
+    if !output.status.success() {
+        return Err("不在 git 仓库中".into());
+    }
+    Ok(PathBuf::from(String::from_utf8(output.stdout)?.trim_end()))
+}
+
+fn git_config(root: &Path, key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("git").args(["config", key, value]).current_dir(root).status()
+        .map_err(|e| format!("无法运行 git: {}", e))?;
+    if !status.success() {
+        return Err(format!("git config {} 失败", key).into());
+    }
+    Ok(())
+}
+
+/// 为 shell 加单引号
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light_params() -> RepoParams {
+        RepoParams::new(Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS })
+    }
+
+    #[test]
+    fn test_clean_is_deterministic() {
+        let params = light_params();
+
+        let first = clean(b"api_key = 123\n", "pw", &params).unwrap();
+        assert_eq!(first, clean(b"api_key = 123\n", "pw", &params).unwrap());
+        assert_ne!(first, clean(b"api_key = 124\n", "pw", &params).unwrap());
+        assert!(!first.windows(7).any(|w| w == b"api_key"));
+
+        // 不同仓库（盐不同）或不同密码得到不同密文
+        assert_ne!(first, clean(b"api_key = 123\n", "pw", &light_params()).unwrap());
+        assert_ne!(first, clean(b"api_key = 123\n", "other", &params).unwrap());
+
+        // 再次 clean 已加密的内容不变
+        assert_eq!(clean(&first, "pw", &params).unwrap(), first);
+
+        assert_eq!(smudge(first.clone(), "pw").unwrap().expose(), b"api_key = 123\n");
+        assert!(smudge(first, "other").is_err());
+
+        // 未加密的内容原样通过
+        assert_eq!(smudge(b"DEC! but plain".to_vec(), "pw").unwrap().expose(), b"DEC! but plain");
+        assert_eq!(smudge(Vec::new(), "pw").unwrap().expose(), b"");
+    }
+
+    #[test]
+    fn test_repo_params_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(GIT_PARAMS_FILE);
+
+        let params = light_params();
+        params.save(&path).unwrap();
+        assert_eq!(RepoParams::load(&path).unwrap(), params);
+
+        std::fs::write(&path, "kdf = pbkdf2\n").unwrap();
+        assert!(RepoParams::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/usr/bin/dec"), "'/usr/bin/dec'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
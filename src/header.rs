@@ -0,0 +1,141 @@
+use std::io::Read;
+use crate::compression::Compression;
+use crate::crypto_utils::*;
+use crate::digest::DigestAlgorithm;
+use crate::padding::Padding;
+use crate::format;
+use crate::key_derivation::{Kdf, PasswordNormalization};
+
+/// 加密文件头，各版本的具体布局见 `format` 模块
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub kdf: Kdf,
+    pub salt: Vec<u8>,
+    pub iv: Vec<u8>,
+    /// 口令校验值（v2 与早期 v3 文件没有）
+    pub key_check: Option<Vec<u8>>,
+    /// 创建时间（Unix 秒）
+    pub created: Option<u64>,
+    /// 创建文件的程序及版本
+    pub creator: Option<String>,
+    /// 密码的 Unicode 规范化方式（没有记录的旧文件为原样字节）
+    pub normalization: PasswordNormalization,
+    /// 主体压缩参数，未压缩时为 `None`
+    pub compression: Option<Compression>,
+    /// 隐藏长度的填充方式，未填充时为 `None`
+    pub padding: Option<Padding>,
+    /// 主体开头带有加密的原文件元数据块
+    pub metadata: bool,
+    /// 内容之后带有加密的明文摘要
+    pub digest: Option<DigestAlgorithm>,
+}
+
+impl Header {
+    /// 以当前版本创建文件头，并生成随机盐和IV
+    pub fn new(kdf: Kdf) -> Self {
+        let created = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .ok();
+
+        Self {
+            version: VERSION_SIGN,
+            kdf,
+            salt: generate_salt(),
+            iv: generate_iv(),
+            key_check: None,
+            created,
+            creator: Some(format!("dec {}", env!("CARGO_PKG_VERSION"))),
+            normalization: PasswordNormalization::Nfc,
+            compression: None,
+            padding: None,
+            metadata: false,
+            digest: None,
+        }
+    }
+
+    /// 编码为当前版本的文件头字节
+    pub fn encode(&self) -> Vec<u8> {
+        format::v3::encode_header(self)
+    }
+
+    /// 读取并解析文件头，按版本字节分派给对应格式，返回文件头和其原始字节
+    pub fn read<R: Read>(reader: &mut R) -> Result<(Header, Vec<u8>), Box<dyn std::error::Error>> {
+        let mut raw = vec![0u8; MAGIC_NUMBER.len() + 1];
+        reader.read_exact(&mut raw)
+            .map_err(|_| "无效的加密文件格式")?;
+
+        // 验证魔数
+        if &raw[..MAGIC_NUMBER.len()] != MAGIC_NUMBER.as_bytes() {
+            return Err("无效的加密文件格式".into());
+        }
+
+        let format = format::lookup(raw[MAGIC_NUMBER.len()])?;
+        let header = (format.read_header)(reader, &mut raw)?;
+        Ok((header, raw))
+    }
+
+    /// 此文件头对应的格式
+    pub fn format(&self) -> &'static format::Format {
+        format::lookup(self.version).expect("header version is always registered")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_header_roundtrip() {
+        let mut header = Header::new(Kdf::Scrypt { log_n: 15, r: 8, p: 1 });
+        header.key_check = Some(vec![7u8; KEY_CHECK_LENGTH]);
+        header.compression = Some(Compression::new(9));
+        header.padding = Some(Padding::Bucket(4096));
+        header.metadata = true;
+        let bytes = header.encode();
+
+        let (parsed, raw) = Header::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.normalization, PasswordNormalization::Nfc);
+        assert_eq!(raw, bytes);
+
+        // 未记录规范化方式的文件头按原样字节处理
+        header.normalization = PasswordNormalization::None;
+        let (parsed, _) = Header::read(&mut Cursor::new(&header.encode())).unwrap();
+        assert_eq!(parsed.normalization, PasswordNormalization::None);
+    }
+
+    #[test]
+    fn test_read_legacy_header() {
+        let mut bytes = MAGIC_NUMBER.as_bytes().to_vec();
+        bytes.push(LEGACY_VERSION_SIGN);
+        bytes.extend_from_slice(&[1u8; SALT_LENGTH]);
+        bytes.extend_from_slice(&[2u8; IV_LENGTH]);
+
+        let (parsed, raw) = Header::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(parsed.version, LEGACY_VERSION_SIGN);
+        assert_eq!(parsed.kdf, Kdf::default());
+        assert_eq!(parsed.salt, vec![1u8; SALT_LENGTH]);
+        assert_eq!(parsed.iv, vec![2u8; IV_LENGTH]);
+        assert_eq!(parsed.key_check, None);
+        assert_eq!(parsed.normalization, PasswordNormalization::None);
+        assert_eq!(raw.len(), bytes.len());
+    }
+
+    #[test]
+    fn test_reject_bad_header() {
+        // 魔数错误
+        assert!(Header::read(&mut Cursor::new(b"NOPE\x03")).is_err());
+        // 版本未知
+        assert!(Header::read(&mut Cursor::new(b"DEC!\x7f")).is_err());
+
+        // 未知字段
+        let mut bytes = Header::new(Kdf::default()).encode();
+        let body_len = u32::from_le_bytes(bytes[5..9].try_into().unwrap()) + 3;
+        bytes[5..9].copy_from_slice(&body_len.to_le_bytes());
+        bytes.extend_from_slice(&[0xee, 0, 0]);
+        assert!(Header::read(&mut Cursor::new(&bytes)).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::BufReader;
+use std::path::Path;
+use serde_json::json;
+use crate::armor;
+use crate::crypto_utils::*;
+use crate::decryptor;
+use crate::digest;
+use crate::header::Header;
+use crate::parity::{self, Parity};
+use crate::volume::{self, VolumeReader};
+
+/// 加密文件的元数据，无需密码即可读取
+#[derive(Debug, Clone)]
+pub struct FileInfo {
+    pub path: String,
+    pub header: Header,
+    /// 是否为 ASCII 封装，长度均按解码后的二进制计算
+    pub armored: bool,
+    /// 纠错封装的参数，长度均按还原后的密文计算
+    pub parity: Option<Parity>,
+    /// 分卷的卷数，长度均按连接后的整体计算
+    pub volumes: Option<usize>,
+    pub header_length: u64,
+    pub ciphertext_length: u64,
+    /// 明文长度，无法在不解密的情况下确定时为 `None`
+    pub plaintext_length: Option<u64>,
+    /// 验证 HMAC 后读出的明文摘要，未提供密码时为 `None`
+    pub digest: Option<digest::DigestValue>,
+}
+
+/// 读取加密文件的文件头并计算各部分长度
+pub fn inspect(input_file_path: &str) -> Result<FileInfo, Box<dyn std::error::Error>> {
+    let input_path = Path::new(input_file_path);
+
+    if !input_path.exists() || !input_path.is_file() {
+        return Err(format!("输入文件不存在: {}", input_file_path).into());
+    }
+
+    let (reader, file_length, armored) = armor::open_quietly(input_path)?;
+    let parity = if armored { None } else { parity::detect(input_path)? };
+    let volumes = if volume::is_volume(input_path, &mut std::fs::File::open(input_path)?)? {
+        Some(VolumeReader::open(input_path)?.count())
+    } else {
+        None
+    };
+    let (header, header_bytes) = Header::read(&mut BufReader::new(reader))?;
+
+    let header_length = header_bytes.len() as u64;
+    let ciphertext_length = file_length
+        .checked_sub(header_length + HMAC_LENGTH as u64)
+        .ok_or("加密文件已截断")?;
+    let digest_length = if header.digest.is_some() { DIGEST_LENGTH as u64 } else { 0 };
+
+    Ok(FileInfo {
+        path: input_file_path.to_string(),
+        armored,
+        parity,
+        volumes,
+        header_length,
+        ciphertext_length,
+        // 压缩、填充或带有元数据块时，无法在不解密的情况下得知明文长度
+        plaintext_length: if header.compression.is_none() && header.padding.is_none() && !header.metadata { ciphertext_length.checked_sub(digest_length) } else { None },
+        digest: None,
+        header,
+    })
+}
+
+impl FileInfo {
+    /// 用密码验证整个文件的 HMAC，通过后读出加密保存的明文摘要
+    pub fn authenticate(&mut self, password: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.digest = decryptor::read_digest(&self.path, password)?;
+        Ok(())
+    }
+
+    /// 明文摘要的说明：算法，验证后附上十六进制值
+    fn digest_description(&self) -> String {
+        match (self.header.digest, self.digest) {
+            (Some(algorithm), Some(digest)) => format!("{} {}", algorithm, digest::to_hex(&digest)),
+            (Some(algorithm), None) => format!("{} (value needs the password)", algorithm),
+            (None, _) => "none".to_string(),
+        }
+    }
+
+    /// 加密套件名称
+    pub fn cipher_suite(&self) -> &'static str {
+        "aes-256-ctr+hmac-sha256"
+    }
+
+    /// 转为 JSON，便于其他工具分类
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "path": self.path,
+            "format_version": self.header.version,
+            "armored": self.armored,
+            "volumes": self.volumes,
+            "parity": self.parity.map(|p| json!({ "algorithm": "reed-solomon", "data_shards": p.data_shards, "parity_shards": p.parity_shards, "shard_size": p.shard_size })),
+            "cipher_suite": self.cipher_suite(),
+            "kdf": kdf_json(&self.header),
+            "key_slots": [{ "type": "password", "key_check": self.header.key_check.is_some() }],
+            "created": self.header.created,
+            "created_utc": self.header.created.map(format_utc),
+            "creator": self.header.creator,
+            "password_normalization": self.header.normalization.name(),
+            "metadata": self.header.metadata,
+            "digest": self.header.digest.map(|d| json!({ "algorithm": d.name(), "value": self.digest.map(|v| digest::to_hex(&v)) })),
+            "padding": self.header.padding.map(|p| p.to_string()),
+            "compression": self.header.compression.map(|c| json!({ "algorithm": "zstd", "level": c.level, "block_size": c.block_size })),
+            "header_length": self.header_length,
+            "ciphertext_length": self.ciphertext_length,
+            "plaintext_length": self.plaintext_length,
+        })
+    }
+}
+
+impl std::fmt::Display for FileInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unknown = || "unknown".to_string();
+
+        writeln!(f, "file:\t\t{}", self.path)?;
+        writeln!(f, "format:\t\tv{}{}", self.header.version, if self.armored { " (ASCII armor)" } else { "" })?;
+        writeln!(f, "volumes:\t{}", self.volumes.map(|n| n.to_string()).unwrap_or_else(|| "none".to_string()))?;
+        writeln!(f, "parity:\t\t{}", self.parity.map(|p| p.to_string()).unwrap_or_else(|| "none".to_string()))?;
+        writeln!(f, "cipher:\t\t{}", self.cipher_suite())?;
+        writeln!(f, "kdf:\t\t{}", self.header.kdf)?;
+        writeln!(f, "key slots:\t1 (password{})",
+            if self.header.key_check.is_some() { ", key check" } else { "" })?;
+        writeln!(f, "password:\t{} normalization", self.header.normalization.name())?;
+        writeln!(f, "metadata:\t{}", if self.header.metadata { "encrypted (name, mode, mtime)" } else { "none" })?;
+        writeln!(f, "padding:\t{}", self.header.padding.map(|p| p.to_string()).unwrap_or_else(|| "none".to_string()))?;
+        writeln!(f, "digest:\t\t{}", self.digest_description())?;
+        writeln!(f, "compression:\t{}", self.header.compression.map(|c| c.to_string()).unwrap_or_else(|| "none".to_string()))?;
+        writeln!(f, "created:\t{}", self.header.created.map(format_utc).unwrap_or_else(unknown))?;
+        writeln!(f, "creator:\t{}", self.header.creator.clone().unwrap_or_else(unknown))?;
+        writeln!(f, "ciphertext:\t{} bytes", self.ciphertext_length)?;
+        write!(f, "plaintext:\t{}", self.plaintext_length.map(|n| format!("{} bytes", n)).unwrap_or_else(unknown))
+    }
+}
+
+/// KDF 参数的 JSON 表示
+fn kdf_json(header: &Header) -> serde_json::Value {
+    use crate::key_derivation::Kdf;
+
+    match header.kdf {
+        Kdf::Argon2id { memory_kib, iterations, parallelism } =>
+            json!({ "name": header.kdf.name(), "memory_kib": memory_kib, "iterations": iterations, "parallelism": parallelism }),
+        Kdf::Scrypt { log_n, r, p } =>
+            json!({ "name": header.kdf.name(), "log_n": log_n, "r": r, "p": p }),
+        Kdf::Pbkdf2Sha256 { iterations } =>
+            json!({ "name": header.kdf.name(), "iterations": iterations }),
+    }
+}
+
+/// 将 Unix 秒格式化为 UTC 时间（`YYYY-MM-DDTHH:MM:SSZ`）
+pub fn format_utc(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // 公历日期换算（Howard Hinnant 的 civil_from_days 算法）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc(1_767_225_599), "2025-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_inspect_without_password() {
+        use std::io::Write;
+        use crate::key_derivation::Kdf;
+
+        let mut header = Header::new(Kdf::Scrypt { log_n: 14, r: 8, p: 1 });
+        header.key_check = Some(vec![0u8; KEY_CHECK_LENGTH]);
+        let header_bytes = header.encode();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&header_bytes).unwrap();
+        file.write_all(&[0u8; 100 + HMAC_LENGTH]).unwrap();
+
+        let info = inspect(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(info.header.kdf, Kdf::Scrypt { log_n: 14, r: 8, p: 1 });
+        assert_eq!(info.ciphertext_length, 100);
+        assert_eq!(info.plaintext_length, Some(100));
+
+        let json = info.to_json();
+        assert_eq!(json["format_version"], VERSION_SIGN);
+        assert_eq!(json["kdf"]["name"], "scrypt");
+        assert_eq!(json["kdf"]["log_n"], 14);
+        assert_eq!(json["key_slots"][0]["key_check"], true);
+        assert_eq!(json["password_normalization"], "nfc");
+        assert_eq!(json["header_length"], header_bytes.len() as u64);
+    }
+}
@@ -1,42 +1,291 @@
 use argon2::{Algorithm, Argon2, Params, Version};
 use hkdf::Hkdf;
 use sha2::Sha256;
-use crate::crypto_utils::{ARGON2_ITERATIONS, ARGON2_MEMORY_KIB, ARGON2_PARALLELISM, ENCRYPTION_KEY_LENGTH, HMAC_KEY_LENGTH, MASTER_KEY_LENGTH};
+use unicode_normalization::UnicodeNormalization;
+use crate::crypto_utils::*;
+use crate::secret::{SecretBytes, SecretString};
 
-pub fn derive_master_key(password: &[u8], salt: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let params = Params::new(
-        ARGON2_MEMORY_KIB,
-        ARGON2_ITERATIONS,
-        ARGON2_PARALLELISM,
-        Some(MASTER_KEY_LENGTH),
-    ).map_err(|e| format!("Failed to create Argon2 params: {}", e))?;
+/// 密钥派生函数（KDF）及其参数，标识与参数一起写入文件头
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    /// Argon2id（默认）
+    Argon2id { memory_kib: u32, iterations: u32, parallelism: u32 },
+    /// scrypt，`log_n` 为 N 的以 2 为底的对数
+    Scrypt { log_n: u8, r: u32, p: u32 },
+    /// PBKDF2-HMAC-SHA256，用于兼容其他系统
+    Pbkdf2Sha256 { iterations: u32 },
+}
+
+impl Default for Kdf {
+    fn default() -> Self {
+        Kdf::Argon2id {
+            memory_kib: ARGON2_MEMORY_KIB,
+            iterations: ARGON2_ITERATIONS,
+            parallelism: ARGON2_PARALLELISM,
+        }
+    }
+}
+
+impl Kdf {
+    /// KDF 在文件头中的标识
+    pub fn id(&self) -> u8 {
+        match self {
+            Kdf::Argon2id { .. } => KDF_ID_ARGON2ID,
+            Kdf::Scrypt { .. } => KDF_ID_SCRYPT,
+            Kdf::Pbkdf2Sha256 { .. } => KDF_ID_PBKDF2_SHA256,
+        }
+    }
+
+    /// KDF 名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kdf::Argon2id { .. } => "argon2id",
+            Kdf::Scrypt { .. } => "scrypt",
+            Kdf::Pbkdf2Sha256 { .. } => "pbkdf2-sha256",
+        }
+    }
+
+    /// 以默认参数创建指定名称的 KDF
+    pub fn from_name(name: &str) -> Option<Kdf> {
+        match name {
+            "argon2id" | "argon2" => Some(Kdf::default()),
+            "scrypt" => Some(Kdf::Scrypt { log_n: SCRYPT_LOG_N, r: SCRYPT_R, p: SCRYPT_P }),
+            "pbkdf2" | "pbkdf2-sha256" => Some(Kdf::Pbkdf2Sha256 { iterations: PBKDF2_ITERATIONS }),
+            _ => None,
+        }
+    }
+
+    /// 解析命令行参数，格式：`NAME[:KEY=VALUE,...]`
+    ///
+    /// - argon2id: `m`（KiB）、`t`、`p`
+    /// - scrypt: `n`（log2 N）、`r`、`p`
+    /// - pbkdf2: `i`
+    pub fn parse(spec: &str) -> Result<Kdf, String> {
+        let (name, params) = match spec.split_once(':') {
+            Some((name, params)) => (name, Some(params)),
+            None => (spec, None),
+        };
+
+        let mut kdf = Kdf::from_name(name).ok_or_else(|| format!("unknown kdf: {}", name))?;
+
+        for pair in params.into_iter().flat_map(|p| p.split(',')) {
+            let (key, value) = pair.split_once('=')
+                .ok_or_else(|| format!("bad kdf parameter: {}", pair))?;
+            let value: u32 = value.parse()
+                .map_err(|_| format!("bad kdf parameter: {}", pair))?;
+
+            match (&mut kdf, key) {
+                (Kdf::Argon2id { memory_kib, .. }, "m") => *memory_kib = value,
+                (Kdf::Argon2id { iterations, .. }, "t") => *iterations = value,
+                (Kdf::Argon2id { parallelism, .. }, "p") => *parallelism = value,
+                (Kdf::Scrypt { log_n, .. }, "n") => {
+                    *log_n = u8::try_from(value).map_err(|_| format!("bad kdf parameter: {}", pair))?
+                }
+                (Kdf::Scrypt { r, .. }, "r") => *r = value,
+                (Kdf::Scrypt { p, .. }, "p") => *p = value,
+                (Kdf::Pbkdf2Sha256 { iterations }, "i") => *iterations = value,
+                _ => return Err(format!("unknown kdf parameter: {}", key)),
+            }
+        }
+
+        kdf.validate()?;
+        Ok(kdf)
+    }
+
+    /// 检查参数范围，避免文件头中的异常参数耗尽内存或时间
+    pub fn validate(&self) -> Result<(), String> {
+        let ok = match *self {
+            Kdf::Argon2id { memory_kib, iterations, parallelism } => {
+                (8 * parallelism.min(ARGON2_MAX_PARALLELISM)..=ARGON2_MAX_MEMORY_KIB).contains(&memory_kib)
+                    && (1..=ARGON2_MAX_ITERATIONS).contains(&iterations)
+                    && (1..=ARGON2_MAX_PARALLELISM).contains(&parallelism)
+            }
+            Kdf::Scrypt { log_n, r, p } => {
+                (1..=SCRYPT_MAX_LOG_N).contains(&log_n)
+                    && (1..=SCRYPT_MAX_R).contains(&r)
+                    && (1..=SCRYPT_MAX_P).contains(&p)
+                    && 128 * r as u64 * (1u64 << log_n) <= SCRYPT_MAX_MEMORY
+                    && p * r <= SCRYPT_MAX_PR
+            }
+            Kdf::Pbkdf2Sha256 { iterations } => {
+                (PBKDF2_MIN_ITERATIONS..=PBKDF2_MAX_ITERATIONS).contains(&iterations)
+            }
+        };
+
+        if ok { Ok(()) } else { Err(format!("kdf parameters out of range: {}", self)) }
+    }
+
+    /// 编码为文件头字段：标识字节 + 小端参数
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![self.id()];
+        match *self {
+            Kdf::Argon2id { memory_kib, iterations, parallelism } => {
+                out.extend_from_slice(&memory_kib.to_le_bytes());
+                out.extend_from_slice(&iterations.to_le_bytes());
+                out.extend_from_slice(&parallelism.to_le_bytes());
+            }
+            Kdf::Scrypt { log_n, r, p } => {
+                out.push(log_n);
+                out.extend_from_slice(&r.to_le_bytes());
+                out.extend_from_slice(&p.to_le_bytes());
+            }
+            Kdf::Pbkdf2Sha256 { iterations } => {
+                out.extend_from_slice(&iterations.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    /// 从文件头字段解码
+    pub fn decode(bytes: &[u8]) -> Result<Kdf, Box<dyn std::error::Error>> {
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+
+        let kdf = match (bytes.first().copied(), bytes.len()) {
+            (Some(KDF_ID_ARGON2ID), 13) => Kdf::Argon2id {
+                memory_kib: u32_at(1),
+                iterations: u32_at(5),
+                parallelism: u32_at(9),
+            },
+            (Some(KDF_ID_SCRYPT), 10) => Kdf::Scrypt { log_n: bytes[1], r: u32_at(2), p: u32_at(6) },
+            (Some(KDF_ID_PBKDF2_SHA256), 5) => Kdf::Pbkdf2Sha256 { iterations: u32_at(1) },
+            (Some(id), _) => return Err(format!("不支持的密钥派生算法: {}", id).into()),
+            (None, _) => return Err("密钥派生参数为空".into()),
+        };
+
+        kdf.validate()?;
+        Ok(kdf)
+    }
+}
+
+impl std::fmt::Display for Kdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.name())?;
+        match self {
+            Kdf::Argon2id { memory_kib, iterations, parallelism } =>
+                write!(f, "m={},t={},p={}", memory_kib, iterations, parallelism),
+            Kdf::Scrypt { log_n, r, p } =>
+                write!(f, "n={},r={},p={}", log_n, r, p),
+            Kdf::Pbkdf2Sha256 { iterations } =>
+                write!(f, "i={}", iterations),
+        }
+    }
+}
+
+/// 密码进入 KDF 前的 Unicode 规范化方式，记录在文件头中
+///
+/// 同一个可见密码在不同系统的输入法下可能是 NFC 或 NFD 字节序列，规范化后派生出相同的密钥。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PasswordNormalization {
+    /// 原样使用密码字节（v2 与早期 v3 文件）
+    #[default]
+    None,
+    /// Unicode NFC（新文件默认）
+    Nfc,
+}
+
+impl PasswordNormalization {
+    /// 在文件头中的标识
+    pub fn id(&self) -> u8 {
+        match self {
+            PasswordNormalization::None => NORMALIZATION_NONE,
+            PasswordNormalization::Nfc => NORMALIZATION_NFC,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self, Box<dyn std::error::Error>> {
+        match id {
+            NORMALIZATION_NONE => Ok(PasswordNormalization::None),
+            NORMALIZATION_NFC => Ok(PasswordNormalization::Nfc),
+            _ => Err(format!("不支持的密码规范化方式: {}", id).into()),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PasswordNormalization::None => "none",
+            PasswordNormalization::Nfc => "nfc",
+        }
+    }
+
+    /// 规范化密码
+    pub fn apply(&self, password: &str) -> SecretString {
+        match self {
+            PasswordNormalization::None => SecretString::from(password.to_string()),
+            PasswordNormalization::Nfc => SecretString::from(password.nfc().collect::<String>()),
+        }
+    }
+}
+
+/// 使用文件头记录的 KDF 派生主密钥
+pub fn derive_master_key(kdf: &Kdf, password: &[u8], salt: &[u8]) -> Result<SecretBytes, Box<dyn std::error::Error>> {
+    let mut master_key = SecretBytes::key(MASTER_KEY_LENGTH);
 
-    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
-    let mut master_key = vec![0u8; MASTER_KEY_LENGTH];
+    match *kdf {
+        Kdf::Argon2id { memory_kib, iterations, parallelism } => {
+            let params = Params::new(
+                memory_kib,
+                iterations,
+                parallelism,
+                Some(MASTER_KEY_LENGTH),
+            ).map_err(|e| format!("Failed to create Argon2 params: {}", e))?;
 
-    argon2.hash_password_into(password, salt, &mut master_key)
-        .map_err(|e| format!("Failed to derive master key: {}", e))?;
+            let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+            argon2.hash_password_into(password, salt, master_key.expose_mut())
+                .map_err(|e| format!("Failed to derive master key: {}", e))?;
+        }
+        Kdf::Scrypt { log_n, r, p } => {
+            let params = scrypt::Params::new(log_n, r, p, MASTER_KEY_LENGTH)
+                .map_err(|e| format!("Failed to create scrypt params: {}", e))?;
+            scrypt::scrypt(password, salt, &params, master_key.expose_mut())
+                .map_err(|e| format!("Failed to derive master key: {}", e))?;
+        }
+        Kdf::Pbkdf2Sha256 { iterations } => {
+            pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, iterations, master_key.expose_mut());
+        }
+    }
 
     Ok(master_key)
 }
 
 /// 使用HKDF从主密钥派生加密密钥和HMAC密钥
-pub fn derive_encryption_and_hmac_keys(master_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Box<dyn std::error::Error>> {
-    let hk = Hkdf::<Sha256>::new(None, master_key);
+pub fn derive_encryption_and_hmac_keys(master_key: &SecretBytes) -> Result<(SecretBytes, SecretBytes), Box<dyn std::error::Error>> {
+    let hk = Hkdf::<Sha256>::new(None, master_key.expose());
 
     // 派生加密密钥
-    let mut encryption_key = vec![0u8; ENCRYPTION_KEY_LENGTH];
-    hk.expand(b"dec-encryption", &mut encryption_key)
+    let mut encryption_key = SecretBytes::key(ENCRYPTION_KEY_LENGTH);
+    hk.expand(b"dec-encryption", encryption_key.expose_mut())
         .map_err(|_| "Failed to derive encryption key")?;
 
     // 派生HMAC密钥
-    let mut hmac_key = vec![0u8; HMAC_KEY_LENGTH];
-    hk.expand(b"dec-hmac", &mut hmac_key)
+    let mut hmac_key = SecretBytes::key(HMAC_KEY_LENGTH);
+    hk.expand(b"dec-hmac", hmac_key.expose_mut())
         .map_err(|_| "Failed to derive HMAC key")?;
 
     Ok((encryption_key, hmac_key))
 }
 
+/// 使用HKDF从主密钥派生口令校验值，写入文件头，用于在解密前快速识别错误密码
+pub fn derive_key_check(master_key: &SecretBytes) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let hk = Hkdf::<Sha256>::new(None, master_key.expose());
+
+    let mut key_check = vec![0u8; KEY_CHECK_LENGTH];
+    hk.expand(b"dec-key-check", &mut key_check)
+        .map_err(|_| "Failed to derive key check")?;
+
+    Ok(key_check)
+}
+
+/// 校验口令校验值（常量时间比较）
+pub fn verify_key_check(master_key: &SecretBytes, stored: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let expected = derive_key_check(master_key)?;
+    let diff = expected.iter().zip(stored).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+
+    if expected.len() != stored.len() || diff != 0 {
+        return Err("密码错误".into());
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -47,11 +296,11 @@ mod tests {
         let password = b"test_password";
         let salt = vec![0u8; SALT_LENGTH];
 
-        let result = derive_master_key(password, &salt);
+        let result = derive_master_key(&Kdf::default(), password, &salt);
         assert!(result.is_ok());
 
         let master_key = result.unwrap();
-        assert_eq!(master_key.len(), MASTER_KEY_LENGTH);
+        assert_eq!(master_key.expose().len(), MASTER_KEY_LENGTH);
     }
 
     #[test]
@@ -59,16 +308,36 @@ mod tests {
         // 先生成一个主密钥
         let password = b"test_password";
         let salt = vec![0u8; SALT_LENGTH];
-        let master_key = derive_master_key(password, &salt).unwrap();
+        let master_key = derive_master_key(&Kdf::default(), password, &salt).unwrap();
 
         let result = derive_encryption_and_hmac_keys(&master_key);
         assert!(result.is_ok());
 
         let (encryption_key, hmac_key) = result.unwrap();
-        assert_eq!(encryption_key.len(), ENCRYPTION_KEY_LENGTH);
-        assert_eq!(hmac_key.len(), HMAC_KEY_LENGTH);
+        assert_eq!(encryption_key.expose().len(), ENCRYPTION_KEY_LENGTH);
+        assert_eq!(hmac_key.expose().len(), HMAC_KEY_LENGTH);
         // 确保两个密钥不同
-        assert_ne!(encryption_key, hmac_key);
+        assert_ne!(encryption_key.expose(), hmac_key.expose());
+    }
+
+    #[test]
+    fn test_key_check() {
+        let kdf = Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS };
+        let salt = vec![3u8; SALT_LENGTH];
+        let master_key = derive_master_key(&kdf, b"right", &salt).unwrap();
+        let wrong_key = derive_master_key(&kdf, b"wrong", &salt).unwrap();
+
+        let key_check = derive_key_check(&master_key).unwrap();
+        assert_eq!(key_check.len(), KEY_CHECK_LENGTH);
+
+        // 校验值与加密/HMAC 密钥互不相同
+        let (encryption_key, hmac_key) = derive_encryption_and_hmac_keys(&master_key).unwrap();
+        assert_ne!(key_check[..], encryption_key.expose()[..KEY_CHECK_LENGTH]);
+        assert_ne!(key_check[..], hmac_key.expose()[..KEY_CHECK_LENGTH]);
+
+        assert!(verify_key_check(&master_key, &key_check).is_ok());
+        assert!(verify_key_check(&wrong_key, &key_check).is_err());
+        assert!(verify_key_check(&master_key, &key_check[..8]).is_err());
     }
 
     #[test]
@@ -77,9 +346,91 @@ mod tests {
         let salt = vec![1u8; SALT_LENGTH];
 
         // 多次调用应该产生相同的结果
-        let key1 = derive_master_key(password, &salt).unwrap();
-        let key2 = derive_master_key(password, &salt).unwrap();
+        let key1 = derive_master_key(&Kdf::default(), password, &salt).unwrap();
+        let key2 = derive_master_key(&Kdf::default(), password, &salt).unwrap();
 
-        assert_eq!(key1, key2);
+        assert_eq!(key1.expose(), key2.expose());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_alternative_kdfs() {
+        let password = b"test_password";
+        let salt = vec![2u8; SALT_LENGTH];
+
+        let scrypt = Kdf::Scrypt { log_n: 10, r: 8, p: 1 };
+        let pbkdf2 = Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS };
+
+        let key1 = derive_master_key(&scrypt, password, &salt).unwrap();
+        let key2 = derive_master_key(&pbkdf2, password, &salt).unwrap();
+
+        assert_eq!(key1.expose().len(), MASTER_KEY_LENGTH);
+        assert_eq!(key2.expose().len(), MASTER_KEY_LENGTH);
+        // 不同 KDF 产生不同的主密钥
+        assert_ne!(key1.expose(), key2.expose());
+    }
+
+    #[test]
+    fn test_pbkdf2_known_answer() {
+        // RFC 7914 第 11 节的 PBKDF2-HMAC-SHA256 测试向量
+        let kdf = Kdf::Pbkdf2Sha256 { iterations: 1 };
+        let key = derive_master_key(&kdf, b"passwd", b"salt").unwrap();
+        assert_eq!(key.expose()[..16], [
+            0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f,
+            0xec, 0x16, 0x91, 0xc2, 0x25, 0x44, 0xb6, 0x05,
+        ]);
+    }
+
+    #[test]
+    fn test_password_normalization() {
+        // "é" 的 NFD（e + 组合重音）与 NFC 形式
+        let nfd = "caf\u{65}\u{301}";
+        let nfc = "caf\u{e9}";
+
+        assert_eq!(PasswordNormalization::Nfc.apply(nfd).expose(), nfc);
+        assert_eq!(PasswordNormalization::Nfc.apply(nfc).expose(), nfc);
+        assert_eq!(PasswordNormalization::None.apply(nfd).expose(), nfd);
+
+        // 中文密码本身已是 NFC，规范化不改变字节
+        assert_eq!(PasswordNormalization::Nfc.apply("密码").expose(), "密码");
+
+        for normalization in [PasswordNormalization::None, PasswordNormalization::Nfc] {
+            assert_eq!(PasswordNormalization::from_id(normalization.id()).unwrap(), normalization);
+        }
+        assert!(PasswordNormalization::from_id(0xff).is_err());
+    }
+
+    #[test]
+    fn test_kdf_encode_decode() {
+        for kdf in [
+            Kdf::default(),
+            Kdf::Scrypt { log_n: 15, r: 8, p: 1 },
+            Kdf::Pbkdf2Sha256 { iterations: 600_000 },
+        ] {
+            assert_eq!(Kdf::decode(&kdf.encode()).unwrap(), kdf);
+        }
+
+        // 未知标识与超范围参数应被拒绝
+        assert!(Kdf::decode(&[0xff]).is_err());
+        assert!(Kdf::decode(&Kdf::Scrypt { log_n: 60, r: 8, p: 1 }.encode()).is_err());
+
+        // 各参数都在范围内，但合计的内存（128·32·2^22 = 16 GiB）或 p·r 超出上限
+        assert!(Kdf::decode(&Kdf::Scrypt { log_n: 22, r: 32, p: 1 }.encode()).is_err());
+        assert!(Kdf::decode(&Kdf::Scrypt { log_n: 10, r: 32, p: 16 }.encode()).is_err());
+        assert!(Kdf::decode(&Kdf::Scrypt { log_n: 22, r: 8, p: 16 }.encode()).is_ok());
+    }
+
+    #[test]
+    fn test_kdf_parse() {
+        assert_eq!(Kdf::parse("argon2id").unwrap(), Kdf::default());
+        assert_eq!(
+            Kdf::parse("argon2id:m=16384,t=2,p=1").unwrap(),
+            Kdf::Argon2id { memory_kib: 16384, iterations: 2, parallelism: 1 }
+        );
+        assert_eq!(Kdf::parse("scrypt:n=14").unwrap(), Kdf::Scrypt { log_n: 14, r: SCRYPT_R, p: SCRYPT_P });
+        assert_eq!(Kdf::parse("pbkdf2:i=310000").unwrap(), Kdf::Pbkdf2Sha256 { iterations: 310_000 });
+
+        assert!(Kdf::parse("bcrypt").is_err());
+        assert!(Kdf::parse("scrypt:i=5").is_err());
+        assert!(Kdf::parse("pbkdf2:i=10").is_err());
+    }
+}
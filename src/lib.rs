@@ -1,8 +1,28 @@
 pub mod args;
+pub mod compression;
 pub mod crypto_utils;
 pub mod decryptor;
 pub mod encryptor;
+pub mod format;
+pub mod header;
 pub mod hmac_validator;
+pub mod info;
+pub mod padding;
+pub mod metadata;
+pub mod armor;
+pub mod seal;
+pub mod structured;
+pub mod git_filter;
+pub mod editor;
+pub mod shred;
+pub mod digest;
+pub mod parity;
+pub mod volume;
 pub mod key_derivation;
 pub mod parallel_handler;
-pub mod progress_utils;
\ No newline at end of file
+pub mod passgen;
+pub mod password_policy;
+pub mod password_source;
+pub mod progress_utils;
+pub mod secret;
+pub mod upgrader;
\ No newline at end of file
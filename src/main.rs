@@ -3,16 +3,38 @@ mod encryptor;
 mod crypto_utils;
 mod progress_utils;
 mod hmac_validator;
+mod header;
+mod format;
 mod key_derivation;
 mod parallel_handler;
 mod args;
+mod info;
+mod upgrader;
+mod secret;
+mod password_source;
+mod password_policy;
+mod passgen;
+mod compression;
+mod padding;
+mod metadata;
+mod armor;
+pub mod seal; // `seal::seal` 是只给库的使用者的入口，pub 使其在二进制中不算未使用
+mod structured;
+mod git_filter;
+mod editor;
+mod shred;
+mod digest;
+mod parity;
+mod volume;
 
 use args::*;
+use secret::SecretString;
 use rpassword::read_password;
 use std::env;
 use std::io;
-use std::io::Write;
+use std::io::{IsTerminal, Read, Write};
 use std::path::Path;
+use zeroize::Zeroize;
 
 fn print_usage() {
     println!("Usage: dec [OPERATION] [INPUT_FILE] [OPTIONS]");
@@ -25,16 +47,113 @@ fn print_usage() {
     println!("  dec --encrypt input.tar -o dec_file\n");
 
     println!("  # Decrypt `example.tar.decx` (outputs `example.tar`)");
-    println!("  dec -d example.tar.decx");
+    println!("  dec -d example.tar.decx\n");
+
+    println!("  # Check integrity and password of archives without writing plaintext");
+    println!("  dec verify a.decx b.decx\n");
+
+    println!("  # Show header metadata (no password needed), one JSON object per line");
+    println!("  dec info *.decx --json\n");
+
+    println!("  # Encrypt with a generated 8-word passphrase and keep a copy readable only by you");
+    println!("  dec -e backup.tar --generate-password --password-out backup.pass\n");
+
+    println!("  # Encrypt and delete the original once the output is verified");
+    println!("  dec -e diary.txt --remove-source\n");
+
+    println!("  # Encrypt to base64 text for pasting into tickets or emails (outputs `key.pem.decx.asc`)");
+    println!("  dec -e key.pem --armor\n");
+
+    println!("  # Encrypt a short secret to a one-line token, and read it back");
+    println!("  dec -e --text \"ghp_xxx\"");
+    println!("  echo ghp_xxx | dec seal --password-env DEC_PASSWORD");
+    println!("  dec open dec:...\n");
+
+    println!("  # Encrypt only the values of a config file, keeping keys readable for diffs");
+    println!("  dec sops-encrypt config.yaml -o config.enc.yaml");
+    println!("  dec exec-env secrets.enc.env -- ./server\n");
+
+    println!("  # Store matching paths encrypted in git, decrypted in the working tree");
+    println!("  dec git-init 'secrets/**' --password-file ~/.config/dec/repo.pass\n");
+
+    println!("  # Search an encrypted log, or print only bytes 1M to 2M of it");
+    println!("  dec cat app.log.decx | grep ERROR");
+    println!("  dec cat app.log.decx --range 1M:2M\n");
+
+    println!("  # Edit an encrypted file in $VISUAL/$EDITOR; saved back with the same settings");
+    println!("  dec edit notes.md.decx\n");
+
+    println!("  # Add 10% Reed-Solomon parity for cold storage, and heal a damaged copy later");
+    println!("  dec -e photos.tar --parity");
+    println!("  dec repair photos.tar.decx\n");
+
+    println!("  # Split into 4 GiB volumes (backup.tar.decx.001, .002, ...), decrypt from the first one");
+    println!("  dec -e backup.tar --split 4G");
+    println!("  dec -d backup.tar.decx.001\n");
+
+    println!("  # Re-encrypt old archives in place with the current format and KDF");
+    println!("  dec upgrade archive/*.decx");
 
     println!("Operations:");
     println!("  -e, --encrypt\t\t\tencrypt a file");
     println!("  -d, --decrypt\t\t\tdecrypt a file");
+    println!("  verify FILE...\t\t\tverify files without decrypting");
+    println!("  info FILE...\t\t\tshow header metadata");
+    println!("  genpass\t\t\t\tgenerate a random passphrase");
+    println!("  upgrade FILE...\t\tre-encrypt files in place with the current format");
+    println!("  seal\t\t\t\tencrypt stdin (or --text) and print a one-line token");
+    println!("  open TOKEN\t\t\tprint the plaintext of a token (`-` reads it from stdin)");
+    println!("  sops-encrypt FILE\t\tencrypt the values of a JSON/YAML/TOML/.env file");
+    println!("  sops-decrypt FILE\t\tverify and decrypt a file from `sops-encrypt`");
+    println!("  exec-env FILE -- CMD...\trun CMD with the decrypted top-level values as environment");
+    println!("  git-init PATTERN...\t\tset up the git filter for PATTERN in the current repository");
+    println!("  git-filter clean|smudge\tgit filter: encrypt (clean) or decrypt (smudge) stdin to stdout");
+    println!("  cat FILE\t\t\tverify, then print the plaintext to stdout");
+    println!("  edit FILE\t\t\topen the plaintext in $VISUAL/$EDITOR and re-encrypt it on change");
+    println!("  repair FILE\t\t\tfix damaged parts of a `--parity` file in place (or to -o), no password;");
+    println!("\t\t\t\tgiven one volume of a `--split` set, rewrites the whole set");
 
     println!("Options:");
-    println!("  -o, --output\t\t\tset output file name");
-    println!("  -p, --password\t\tset password");
+    println!("  -o, --output\t\t\tset output file name (`sops-*` print to stdout without it)");
+    println!("  -p, --password\t\tset password (visible in shell history and process list)");
+    println!("  --password-env NAME\t\tread password from an environment variable");
+    println!("  --password-file PATH\t\tread password from a file");
+    println!("  --password-fd N\t\tread password from a file descriptor");
+    println!("  --password-command CMD\tread password from the output of a command");
+    println!("\t\t\t\t(one trailing newline is stripped)");
     println!("  -q, --quiet\t\t\tno check");
+    println!("  --kdf KDF[:PARAMS]\t\tkey derivation for encryption and upgrade (default: argon2id)");
+    println!("\t\t\t\targon2id:m=KIB,t=N,p=N | scrypt:n=LOG2N,r=N,p=N | pbkdf2:i=N");
+    println!("  --compress[=LEVEL]\t\tzstd-compress before encryption, level 1-22 (default: 3)");
+    println!("  --pad SCHEME\t\t\thide the plaintext length: padme | bucket:SIZE | fixed:SIZE");
+    println!("  --no-metadata\t\t\tdo not store the file name, mode and mtime when encrypting");
+    println!("  --xattrs\t\t\talso store extended attributes when encrypting");
+    println!("  --random-name\t\t\tencrypt to a random file name in the same directory");
+    println!("  --restore-name\t\tdecrypt to the original file name stored in the file");
+    println!("  --digest[=ALGO]\t\tstore a sha256 (default) or blake3 digest of the plaintext,");
+    println!("\t\t\t\tchecked by `-d` and shown by `info` when given a password");
+    println!("  --verify[=MODE]\t\tre-read the output after encrypting and fail on mismatch");
+    println!("\t\t\t\tmac: recompute the HMAC (default) | content: also decrypt and compare SHA-256");
+    println!("  --remove-source\t\tdelete the input after the output is synced and verified");
+    println!("  --shred\t\t\toverwrite the input with random data before deleting it");
+    println!("\t\t\t\t(does not help on SSDs or copy-on-write filesystems)");
+    println!("  --armor\t\t\twrite base64 text with BEGIN/END lines (read back automatically)");
+    println!("  --split SIZE\t\t\twrite volumes of at most SIZE (e.g. 4G) named .001, .002, ...;");
+    println!("\t\t\t\tread back from any volume, missing or damaged volumes are named");
+    println!("  --parity[=PERCENT]\t\tadd Reed-Solomon parity after encryption (default: 10%);");
+    println!("\t\t\t\tdamage is repaired while reading, before the HMAC check");
+    println!("  --text TEXT\t\t\tplaintext for `seal` or `-e --text` (visible in the process list)");
+    println!("  --json\t\t\t\tJSON output for `info`");
+    println!("  --range START:END\t\tbytes START to END of the plaintext for `cat`, e.g. 1M:2M, 100:, :4K");
+    println!("  --generate-password\t\tencrypt with a generated passphrase, shown once");
+    println!("  --words N | --chars N\t\tpassphrase of N words (default: 8) or N random characters");
+    println!("  --password-out FILE\t\talso save the generated passphrase to FILE (mode 0600)");
+    println!("  --min-score N\t\t\tminimum password strength 0-4 for encryption (default: 2)");
+    println!("  --min-length N\t\tminimum password length for encryption (default: 8)");
+    println!("  --forbidden-words FILE\twords the password must not contain, one per line");
+    println!("  --password-policy MODE\twarn | refuse when the policy is not met (default: warn)");
+    println!("  --change-password\t\tset a new password during `upgrade`");
+    println!("  --force\t\t\tre-encrypt during `upgrade` even if already current");
 
     println!("Others:");
     println!("  -v, --version\t\t\tshow version");
@@ -50,10 +169,12 @@ const PREFIX: &str = "DEC!: ";
 const RESET: &str = "\u{001B}[0m";
 const BOLD: &str = "\u{001B}[1m";
 const RED: &str = "\u{001B}[31m";
+const GREEN: &str = "\u{001B}[32m";
 
 fn main() {
     // 收集参数
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut raw_args: Vec<String> = env::args().skip(1).collect();
+    let args = &raw_args;
 
     // 打印版本
     if args.len() == 1 && (args[0] == "-v" || args[0] == "--version") {
@@ -61,18 +182,47 @@ fn main() {
     }
 
     // 获得参数
-    let args = parse_args(&args).unwrap_or_else(|e| {
+    let args = parse_args(args).unwrap_or_else(|e| {
         eprintln!("{}{}{}{}", PREFIX, RED, e, RESET); print_usage(); std::process::exit(1);
     });
 
+    // 原始参数中可能含有密码，解析后清零
+    raw_args.zeroize();
+
     // 提取参数
     let op = args.op;
     let input_path = args.input_path;
+    let input_paths = args.input_paths;
     let output_path = args.output_path.clone();
-    let password = args.password;
+    // git-init 只把密码来源写进过滤器命令，不读取
+    let (password_source, password) = if op == Op::GitInit {
+        (args.password, None)
+    } else {
+        (None, read_password_source(args.password))
+    };
+    let options = encryptor::EncryptOptions {
+        kdf: args.kdf.unwrap_or_default(),
+        compression: args.compression,
+        padding: args.padding,
+        metadata: args.metadata,
+        xattrs: args.xattrs,
+        armor: args.armor,
+        parity: args.parity,
+        split: args.split,
+        verify: args.verify,
+        digest: args.digest,
+    };
 
-    // 检查输出文件是否已存在
-    if !args.quiet && Path::new(&output_path).exists() {
+    // 检查输出文件是否已存在（分卷检查第一卷）
+    let writes_volumes = match op {
+        Op::Enc => args.split.is_some(),
+        Op::Repair => !output_path.is_empty() && std::fs::File::open(&input_path)
+            .and_then(|mut file| volume::is_volume(Path::new(&input_path), &mut file))
+            .unwrap_or(false),
+        _ => false,
+    };
+    let first_output = if writes_volumes { volume::volume_path(&output_path, 1) } else { output_path.clone() };
+    if !args.quiet && Path::new(&first_output).exists() {
         print!("> output file already {}EXISTS{}, {}{}overwrite{}? [y/n]: ", BOLD, RESET, BOLD, RED, RESET);
         io::stdout().flush().unwrap();
         if !confirm() { return; }
@@ -80,18 +230,39 @@ fn main() {
 
     // 分配参数，进行下一步处理
     match op {
-        Op::Enc => handle_encrypt(input_path, output_path, password),
-        Op::Dec => handle_decrypt(input_path, output_path, password),
+        Op::Enc => {
+            // 生成的密码已显示给用户，无需再次确认
+            let password = if args.generate_password {
+                Some(generate_password(args.pass_style, args.password_out.as_deref()))
+            } else { password };
+            let removal = args.remove_source.then_some(args.shred);
+            handle_encrypt(input_path, output_path, password, &options, &args.policy, args.random_name, removal)
+        }
+        Op::Dec => handle_decrypt(input_path, output_path, password, args.restore_name),
+        Op::Verify => handle_verify(input_paths, password),
+        Op::Info => handle_info(input_paths, args.json, password),
+        Op::Upgrade => handle_upgrade(input_paths, password, args.change_password, args.force, &options, &args.policy),
+        Op::GenPass => { generate_password(args.pass_style, args.password_out.as_deref()); }
+        Op::Seal => handle_seal(args.text, password, &options, &args.policy),
+        Op::Open => handle_open(input_path, password),
+        Op::SopsEncrypt => handle_sops_encrypt(input_path, output_path, password, options.kdf, &args.policy),
+        Op::SopsDecrypt => handle_sops_decrypt(input_path, output_path, password),
+        Op::ExecEnv => handle_exec_env(input_path, password, args.command),
+        Op::GitFilter => handle_git_filter(&input_path, password),
+        Op::GitInit => handle_git_init(&args.patterns, password_source, options.kdf),
+        Op::Edit => handle_edit(&input_path, password),
+        Op::Cat => handle_cat(&input_path, password, args.range),
+        Op::Repair => handle_repair(&input_path, &output_path),
     }
 }
 
 /*
  * 接手加密
  */
-fn handle_encrypt(input_path: String, output_path: String, mut password: Option<String>) {
+fn handle_encrypt(input_path: String, output_path: String, mut password: Option<SecretString>, options: &encryptor::EncryptOptions, policy: &password_policy::PasswordPolicy, random_name: bool, removal: Option<bool>) {
     // `confirmed` 用来区分 参数 和 输入
     let mut confirmed = true;
-    if password == None {
+    if password.is_none() {
         password = Some(get_password());
         confirmed = false;
     }
@@ -102,23 +273,63 @@ fn handle_encrypt(input_path: String, output_path: String, mut password: Option<
         _ => unreachable!()
     };
 
+    // 密码策略同样适用于 -p、文件等来源
+    check_password_policy(&password, policy, !confirmed);
+
     if !confirmed && !confirm_password(&password) {
         eprintln!("{}{}passwords mismatch{}", PREFIX, RED, RESET);
         return;
     }
 
-    match encryptor::encrypt_with_mode(&input_path, &output_path, &password) {
+    // 记录加密前源文件的状态，删除前确认加密期间没有被改动
+    let source_before = removal.and_then(|_| Path::new(&input_path).metadata().ok());
+
+    match encryptor::encrypt_with_mode(&input_path, &output_path, password.expose(), options) {
+        // 随机文件名需要告知用户
+        Ok(_) if random_name => println!("{}encrypted to {}{}{}", PREFIX, BOLD, output_path, RESET),
         Ok(_) => {},
-        Err(e) => eprintln!("[{}ERROR{}]: encryption failed: {}{}{}", RED, RESET, e, RED, RESET),
+        // 自检失败等错误须以非零状态退出，便于脚本发现
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: encryption failed: {}{}{}", RED, RESET, e, RED, RESET);
+            std::process::exit(1);
+        }
+    }
+
+    if let (Some(shred), Some(before)) = (removal, source_before) {
+        let output_path = if options.split.is_some() { volume::volume_path(&output_path, 1) } else { output_path };
+        remove_source(&input_path, &output_path, &password, shred, &before);
+    }
+}
+
+/*
+ * 加密后删除源文件：输出落盘并校验通过后才删除
+ */
+fn remove_source(input_path: &str, output_path: &str, password: &SecretString, shred: bool, before: &std::fs::Metadata) {
+    if shred {
+        eprintln!("{}{}warning{}: overwriting does not reliably erase data on SSDs or on copy-on-write and snapshotting \
+            filesystems (btrfs, ZFS, APFS); use full-disk encryption there", PREFIX, RED, RESET);
+    }
+    let links = shred::other_links(before);
+    if links > 0 {
+        let effect = if shred { "they will read the overwritten data" } else { "the plaintext stays reachable through them" };
+        eprintln!("{}{}warning{}: {} has {} other hard link(s); {}", PREFIX, RED, RESET, input_path, links, effect);
+    }
+
+    match shred::remove_source(input_path, output_path, password.expose(), shred, before) {
+        Ok(()) => println!("{}{} {}{}{}", PREFIX, if shred { "shredded" } else { "removed" }, BOLD, input_path, RESET),
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: source kept: {}{}{}", RED, RESET, e, RED, RESET);
+            std::process::exit(1);
+        }
     }
 }
 
 /*
  * 接手解密
  */
-fn handle_decrypt(input_path: String, output_path: String, mut password: Option<String>) {
+fn handle_decrypt(input_path: String, output_path: String, mut password: Option<SecretString>, restore_name: bool) {
     // 获取密码
-    if password == None {
+    if password.is_none() {
         password = Some(get_password());
     }
 
@@ -133,31 +344,623 @@ fn handle_decrypt(input_path: String, output_path: String, mut password: Option<
         Ok(_) => {},
         Err(e) => {
             eprintln!("[{}ERROR{}]: version mismatch: {}{}{}", RED, RESET, e, RED, RESET);
-            return;
+            std::process::exit(1);
         }
     }
     
-    match decryptor::decrypt_with_mode(&input_path, &output_path, &password) {
-        Ok(_) => {},
-        Err(e) => eprintln!("[{}ERROR{}]: decryption failed: {}{}{}", RED, RESET, e, RED, RESET),
+    // 以原文件名保存时，输出路径在验证通过后才确定；失败时临时文件随之删除
+    if restore_name {
+        match decryptor::decrypt_restoring_name(&input_path, password.expose()) {
+            Ok(path) => println!("{}restored {}{}{}", PREFIX, BOLD, path.display(), RESET),
+            Err(e) => {
+                eprintln!("[{}ERROR{}]: decryption failed: {}{}{}", RED, RESET, e, RED, RESET);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let before = std::fs::symlink_metadata(&output_path).ok();
+    if let Err(e) = decryptor::decrypt_with_mode(&input_path, &output_path, password.expose()) {
+        eprintln!("[{}ERROR{}]: decryption failed: {}{}{}", RED, RESET, e, RED, RESET);
+        // 未通过校验的明文不能留下；输出尚未被改写（如密码错误）时保持原样
+        if let Ok(after) = std::fs::symlink_metadata(&output_path)
+            && after.is_file()
+            && before.is_none_or(|before| before.len() != after.len() || before.modified().ok() != after.modified().ok())
+        {
+            let _ = std::fs::remove_file(&output_path);
+        }
+        std::process::exit(1);
+    }
+}
+
+/*
+ * 接手校验
+ */
+fn handle_verify(input_paths: Vec<String>, mut password: Option<SecretString>) {
+    // 获取密码（批量校验共用同一密码）
+    if password.is_none() {
+        password = Some(get_password());
+    }
+
+    // 转换 password
+    let password = match password {
+        Some(p) => p,
+        _ => unreachable!()
+    };
+
+    let mut failed = 0;
+    for input_path in &input_paths {
+        println!("{}{}{}{}", PREFIX, BOLD, input_path, RESET);
+
+        let result = decryptor::check_version(input_path)
+            .and_then(|_| decryptor::verify_with_password(input_path, password.expose()));
+
+        match result {
+            Ok(_) => println!("[{}PASS{}]: {}", GREEN, RESET, input_path),
+            Err(e) => {
+                eprintln!("[{}FAIL{}]: {}: {}{}{}", RED, RESET, input_path, RED, e, RESET);
+                failed += 1;
+            }
+        }
+    }
+
+    if input_paths.len() > 1 {
+        println!("{}{} passed, {} failed", PREFIX, input_paths.len() - failed, failed);
+    }
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/*
+ * 接手元数据查看
+ */
+fn handle_info(input_paths: Vec<String>, json: bool, password: Option<SecretString>) {
+    let mut failed = false;
+    for (i, input_path) in input_paths.iter().enumerate() {
+        // 提供了密码时，验证后显示加密保存的明文摘要
+        let info = info::inspect(input_path).and_then(|mut info| {
+            if let Some(password) = &password && info.header.digest.is_some() {
+                info.authenticate(password.expose())?;
+            }
+            Ok(info)
+        });
+
+        match (info, json) {
+            (Ok(info), true) => println!("{}", info.to_json()),
+            (Ok(info), false) => {
+                if i > 0 { println!(); }
+                println!("{}", info);
+            }
+            (Err(e), true) => {
+                println!("{}", serde_json::json!({ "path": input_path, "error": e.to_string() }));
+                failed = true;
+            }
+            (Err(e), false) => {
+                eprintln!("[{}ERROR{}]: {}: {}{}{}", RED, RESET, input_path, RED, e, RESET);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/*
+ * 接手格式升级
+ */
+fn handle_upgrade(input_paths: Vec<String>, mut password: Option<SecretString>, change_password: bool, force: bool, options: &encryptor::EncryptOptions, policy: &password_policy::PasswordPolicy) {
+    // 获取密码（批量升级共用同一密码）
+    if password.is_none() {
+        password = Some(get_password());
+    }
+
+    // 转换 password
+    let password = match password {
+        Some(p) => p,
+        _ => unreachable!()
+    };
+
+    // 需要时读取新密码，并确认
+    let new_password = if change_password {
+        print!("> {}new password:{} ", BOLD, RESET);
+        io::stdout().flush().unwrap();
+        let new_password = SecretString::from(read_password().unwrap());
+        check_password_policy(&new_password, policy, true);
+        if !confirm_password(&new_password) {
+            eprintln!("{}{}passwords mismatch{}", PREFIX, RED, RESET);
+            return;
+        }
+        Some(new_password)
+    } else { None };
+
+    let mut failed = 0;
+    for input_path in &input_paths {
+        println!("{}{}{}{}", PREFIX, BOLD, input_path, RESET);
+
+        match upgrader::upgrade_file(input_path, password.expose(), new_password.as_ref().map(|p| p.expose()), options, force) {
+            Ok(upgrader::UpgradeOutcome::Upgraded { from_version }) =>
+                println!("[{}DONE{}]: {}: v{} -> v{}", GREEN, RESET, input_path, from_version, crypto_utils::VERSION_SIGN),
+            Ok(upgrader::UpgradeOutcome::UpToDate) =>
+                println!("[{}SKIP{}]: {}: already up to date", GREEN, RESET, input_path),
+            Err(e) => {
+                eprintln!("[{}FAIL{}]: {}: {}{}{}", RED, RESET, input_path, RED, e, RESET);
+                failed += 1;
+            }
+        }
+    }
+
+    if input_paths.len() > 1 {
+        println!("{}{} upgraded or current, {} failed", PREFIX, input_paths.len() - failed, failed);
+    }
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/*
+ * 接手短字符串加密：令牌单独一行写到标准输出，提示都写到标准错误
+ */
+fn handle_seal(text: Option<SecretString>, mut password: Option<SecretString>, options: &encryptor::EncryptOptions, policy: &password_policy::PasswordPolicy) {
+    // 未指定 --text 时读取标准输入，去掉末尾一个换行
+    let plaintext = match text {
+        Some(text) => zeroize::Zeroizing::new(text.expose().as_bytes().to_vec()),
+        None => {
+            let mut input = zeroize::Zeroizing::new(Vec::new());
+            if let Err(e) = io::stdin().read_to_end(&mut input) {
+                eprintln!("[{}ERROR{}]: cannot read stdin: {}{}{}", RED, RESET, RED, e, RESET);
+                std::process::exit(1);
+            }
+            if input.ends_with(b"\n") { input.pop(); }
+            if input.ends_with(b"\r") { input.pop(); }
+            input
+        }
+    };
+
+    // `confirmed` 用来区分 参数 和 输入
+    let mut confirmed = true;
+    if password.is_none() {
+        password = Some(get_password_on_stderr("password"));
+        confirmed = false;
+    }
+
+    // 转换 password
+    let password = match password {
+        Some(p) => p,
+        _ => unreachable!()
+    };
+
+    // 强度提示会混入标准输出，这里只检查策略
+    check_password_policy(&password, policy, false);
+
+    if !confirmed && password.expose() != get_password_on_stderr("confirm password").expose() {
+        eprintln!("{}{}passwords mismatch{}", PREFIX, RED, RESET);
+        std::process::exit(1);
+    }
+
+    match seal::seal_with_options(&plaintext, password.expose(), options) {
+        Ok(token) => println!("{}", token),
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: encryption failed: {}{}{}", RED, RESET, e, RED, RESET);
+            std::process::exit(1);
+        }
     }
 }
 
+/*
+ * 接手令牌解密：明文原样写到标准输出（终端上另加换行）
+ */
+fn handle_open(token: String, mut password: Option<SecretString>) {
+    // `-` 表示从标准输入读取令牌
+    let token = if token == "-" {
+        let mut token = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut token) {
+            eprintln!("[{}ERROR{}]: cannot read stdin: {}{}{}", RED, RESET, RED, e, RESET);
+            std::process::exit(1);
+        }
+        token
+    } else { token };
+
+    if password.is_none() {
+        password = Some(get_password_on_stderr("password"));
+    }
+
+    // 转换 password
+    let password = match password {
+        Some(p) => p,
+        _ => unreachable!()
+    };
+
+    match seal::open(&token, password.expose()) {
+        Ok(plaintext) => {
+            let mut stdout = io::stdout().lock();
+            let newline: &[u8] = if stdout.is_terminal() { b"\n" } else { b"" };
+            if let Err(e) = stdout.write_all(plaintext.expose()).and_then(|_| stdout.write_all(newline)).and_then(|_| stdout.flush()) {
+                eprintln!("[{}ERROR{}]: {}{}{}", RED, RESET, RED, e, RESET);
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: decryption failed: {}{}{}", RED, RESET, e, RED, RESET);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * 接手配置文件逐值加密：未指定 -o 时输出到标准输出
+ */
+fn handle_sops_encrypt(input_path: String, output_path: String, mut password: Option<SecretString>, kdf: key_derivation::Kdf, policy: &password_policy::PasswordPolicy) {
+    let (text, format) = read_config(&input_path);
+
+    // `confirmed` 用来区分 参数 和 输入
+    let mut confirmed = true;
+    if password.is_none() {
+        password = Some(get_password_on_stderr("password"));
+        confirmed = false;
+    }
+
+    // 转换 password
+    let password = match password {
+        Some(p) => p,
+        _ => unreachable!()
+    };
+
+    check_password_policy(&password, policy, false);
+
+    if !confirmed && password.expose() != get_password_on_stderr("confirm password").expose() {
+        eprintln!("{}{}passwords mismatch{}", PREFIX, RED, RESET);
+        std::process::exit(1);
+    }
+
+    match structured::encrypt_document(&text, format, password.expose(), kdf) {
+        Ok(encrypted) => write_config(&output_path, &encrypted, false),
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: encryption failed: {}{}{}", RED, RESET, e, RED, RESET);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * 接手配置文件解密：写出的明文文件为 0600
+ */
+fn handle_sops_decrypt(input_path: String, output_path: String, mut password: Option<SecretString>) {
+    let (text, format) = read_config(&input_path);
+
+    if password.is_none() {
+        password = Some(get_password_on_stderr("password"));
+    }
+
+    // 转换 password
+    let password = match password {
+        Some(p) => p,
+        _ => unreachable!()
+    };
+
+    match structured::decrypt_document(&text, format, password.expose()) {
+        Ok(plaintext) => write_config(&output_path, plaintext.expose(), true),
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: decryption failed: {}{}{}", RED, RESET, e, RED, RESET);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * 接手 exec-env：以解密后的顶层值为环境变量执行命令，明文不落盘
+ */
+fn handle_exec_env(input_path: String, mut password: Option<SecretString>, command: Vec<String>) {
+    let (text, format) = read_config(&input_path);
+
+    if password.is_none() {
+        password = Some(get_password_on_stderr("password"));
+    }
+
+    // 转换 password
+    let password = match password {
+        Some(p) => p,
+        _ => unreachable!()
+    };
+
+    let env = structured::decrypt_env(&text, format, password.expose()).unwrap_or_else(|e| {
+        eprintln!("[{}ERROR{}]: decryption failed: {}{}{}", RED, RESET, e, RED, RESET);
+        std::process::exit(1);
+    });
+
+    let mut child = std::process::Command::new(&command[0]);
+    child.args(&command[1..]).envs(env.iter().map(|(key, value)| (key, value.expose())));
+
+    // Unix 上直接替换当前进程，退出码与信号原样传递
+    #[cfg(unix)]
+    let error = std::os::unix::process::CommandExt::exec(&mut child);
+    #[cfg(not(unix))]
+    let error = match child.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => e,
+    };
+
+    eprintln!("[{}ERROR{}]: cannot run {}: {}{}{}", RED, RESET, command[0], RED, error, RESET);
+    std::process::exit(127);
+}
+
+/*
+ * 接手 git 过滤器：标准输入到标准输出，由 git 调用，不能交互输入密码
+ */
+fn handle_git_filter(mode: &str, password: Option<SecretString>) {
+    let password = password.unwrap_or_else(|| {
+        eprintln!("{}{}git-filter needs --password-env, --password-file or --password-command{}", PREFIX, RED, RESET);
+        std::process::exit(1);
+    });
+
+    let mut input = Vec::new();
+    if let Err(e) = io::stdin().read_to_end(&mut input) {
+        eprintln!("[{}ERROR{}]: cannot read stdin: {}{}{}", RED, RESET, RED, e, RESET);
+        std::process::exit(1);
+    }
+
+    let result = if mode == "clean" {
+        let input = zeroize::Zeroizing::new(input);
+        git_filter::RepoParams::find()
+            .and_then(|params| git_filter::clean(&input, password.expose(), &params))
+            .map(secret::SecretBytes::from)
+    } else {
+        git_filter::smudge(input, password.expose())
+    };
+
+    let output = result.unwrap_or_else(|e| {
+        eprintln!("[{}ERROR{}]: git-filter {} failed: {}{}{}", RED, RESET, mode, RED, e, RESET);
+        std::process::exit(1);
+    });
+
+    let mut stdout = io::stdout().lock();
+    if let Err(e) = stdout.write_all(output.expose()).and_then(|_| stdout.flush()) {
+        eprintln!("[{}ERROR{}]: {}{}{}", RED, RESET, RED, e, RESET);
+        std::process::exit(1);
+    }
+}
+
+/*
+ * 接手 git-init：写入仓库参数、过滤器配置与 .gitattributes
+ */
+fn handle_git_init(patterns: &[String], password_source: Option<password_source::PasswordSource>, kdf: key_derivation::Kdf) {
+    // 由参数解析保证是可重新读取的来源；文件路径转为绝对路径，过滤器在仓库根目录运行
+    let Some((option, value)) = password_source.as_ref().and_then(|s| s.to_option()) else { unreachable!() };
+    let value = if option == "--password-file" {
+        std::fs::canonicalize(&value).map(|p| p.to_string_lossy().into_owned()).unwrap_or(value)
+    } else { value };
+
+    // 过滤器命令使用当前程序的绝对路径
+    let result = std::env::current_exe().map_err(|e| e.into())
+        .and_then(|exe| git_filter::init(Path::new("."), &exe, patterns, (option, &value), kdf));
+
+    match result {
+        Ok(root) => {
+            println!("{}git filter `{}` configured in {}{}{}", PREFIX, crypto_utils::GIT_FILTER_NAME, BOLD, root.display(), RESET);
+            println!("{}commit {} and .gitattributes; share the password separately", PREFIX, crypto_utils::GIT_PARAMS_FILE);
+            println!("{}files already committed stay plaintext until `git add --renormalize .`", PREFIX);
+        }
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: git-init failed: {}{}{}", RED, RESET, e, RED, RESET);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * 接手 cat：验证后把明文（或其中一段）写到标准输出
+ */
+fn handle_cat(input_path: &str, password: Option<SecretString>, range: Option<std::ops::Range<u64>>) {
+    // 标准输出是明文，提示写到标准错误
+    let password = password.unwrap_or_else(|| get_password_on_stderr("password"));
+
+    let stdout = io::BufWriter::with_capacity(crypto_utils::BUFFER_SIZE, io::stdout().lock());
+    let result = decryptor::decrypt_range_to_writer(input_path, password.expose(), range, stdout)
+        .and_then(|mut writer| Ok(writer.flush()?));
+
+    if let Err(e) = result {
+        // 下游提前关闭管道（如 `| head`）不算错误
+        if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe) {
+            return;
+        }
+        eprintln!("[{}ERROR{}]: {}: {}{}{}", RED, RESET, input_path, RED, e, RESET);
+        std::process::exit(1);
+    }
+}
+
+/*
+ * 接手修复：用纠错数据还原损坏的块，重新计算校验块写出
+ */
+fn handle_repair(input_path: &str, output_path: &str) {
+    let output = (!output_path.is_empty()).then_some(output_path);
+
+    match parity::repair_file(input_path, output) {
+        Ok(parity::RepairOutcome::Intact) => println!("[{}SKIP{}]: {}: no damage found", GREEN, RESET, input_path),
+        Ok(parity::RepairOutcome::Repaired { segments, shards, envelope }) => {
+            println!("[{}DONE{}]: {}: {} damaged shard(s) in {} segment(s) repaired{}, written to {}", GREEN, RESET,
+                input_path, shards, segments, if envelope { ", header rebuilt" } else { "" }, output.unwrap_or(input_path));
+            println!("{}run `dec verify` to check the repaired file with the password", PREFIX);
+        }
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: {}: {}{}{}", RED, RESET, input_path, RED, e, RESET);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * 接手编辑：解密到私有临时目录、调用编辑器，有改动时以原参数重新加密
+ */
+fn handle_edit(input_path: &str, password: Option<SecretString>) {
+    let password = password.unwrap_or_else(get_password);
+
+    // 与 git 相同的编辑器选择顺序
+    let editor = ["VISUAL", "EDITOR"].iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    let (temp_base, in_memory) = editor::private_temp_base();
+    if !in_memory {
+        eprintln!("{}{}warning{}: no tmpfs found, the plaintext is written to {} while editing",
+            PREFIX, RED, RESET, temp_base.display());
+    }
+
+    match editor::edit_file(input_path, password.expose(), &editor, &temp_base) {
+        Ok(editor::EditOutcome::Saved) => println!("[{}DONE{}]: {}: saved", GREEN, RESET, input_path),
+        Ok(editor::EditOutcome::Unchanged) => println!("[{}SKIP{}]: {}: no changes", GREEN, RESET, input_path),
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: edit failed: {}{}{}", RED, RESET, e, RED, RESET);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * 接手密码生成：只显示一次，可另存为 0600 文件
+ */
+fn generate_password(style: passgen::PassStyle, password_out: Option<&str>) -> SecretString {
+    let password = passgen::generate(style).unwrap_or_else(|e| {
+        eprintln!("[{}ERROR{}]: password generation failed: {}{}{}", RED, RESET, e, RED, RESET);
+        std::process::exit(1);
+    });
+
+    if let Some(path) = password_out {
+        if let Err(e) = passgen::write_password_file(path, &password) {
+            eprintln!("[{}ERROR{}]: {}{}{}", RED, RESET, RED, e, RESET);
+            std::process::exit(1);
+        }
+        eprintln!("{}password saved to {}{}{} (mode 0600)", PREFIX, BOLD, path, RESET);
+    }
+
+    // 说明写到标准错误，密码单独一行写到标准输出，便于管道使用
+    eprintln!("{}generated password ({}, ~{:.0} bits), shown only once:", PREFIX, style, style.entropy_bits());
+    println!("{}", password.expose());
+
+    password
+}
+
 /*
  * 以下都是辅助函数
  */
-fn get_password() -> String {
+/// 检查密码策略：交互输入时显示强度；不满足时按策略警告，或拒绝并退出
+fn check_password_policy(password: &SecretString, policy: &password_policy::PasswordPolicy, interactive: bool) {
+    if interactive {
+        let strength = password_policy::estimate(password.expose());
+        let color = if strength.score < policy.min_score { RED } else { GREEN };
+        println!("> {}strength:{} {}{}{}", BOLD, RESET, color, strength, RESET);
+    }
+
+    let violations = policy.check(password.expose());
+    if violations.is_empty() {
+        return;
+    }
+
+    let refuse = policy.mode == password_policy::PolicyMode::Refuse;
+    for violation in &violations {
+        eprintln!("{}{}{}{}: password {}", PREFIX, RED, if refuse { "error" } else { "warning" }, RESET, violation);
+    }
+
+    if refuse {
+        eprintln!("{}{}password rejected by policy{}", PREFIX, RED, RESET);
+        std::process::exit(1);
+    }
+}
+
+/// 读取配置文件并按扩展名识别格式
+fn read_config(input_path: &str) -> (String, structured::ConfigFormat) {
+    let format = structured::ConfigFormat::from_path(input_path).unwrap_or_else(|| {
+        eprintln!("[{}ERROR{}]: {}unknown config format: {} (expected .json, .yaml, .yml, .toml or .env){}", RED, RESET, RED, input_path, RESET);
+        std::process::exit(1);
+    });
+
+    match std::fs::read_to_string(input_path) {
+        Ok(text) => (text, format),
+        Err(e) => {
+            eprintln!("[{}ERROR{}]: {}: {}{}{}", RED, RESET, input_path, RED, e, RESET);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// 写出配置文件；未指定路径时写到标准输出
+///
+/// `private` 时先写入同目录下权限为 0600 的临时文件再替换目标，已存在的文件原有的宽松权限不会沿用到明文上。
+fn write_config(output_path: &str, text: &str, private: bool) {
+    let result = if output_path.is_empty() {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush())
+    } else if private {
+        write_private(Path::new(output_path), text.as_bytes())
+    } else {
+        std::fs::write(output_path, text)
+    };
+
+    if let Err(e) = result {
+        let target = if output_path.is_empty() { "stdout" } else { output_path };
+        eprintln!("[{}ERROR{}]: {}: {}{}{}", RED, RESET, target, RED, e, RESET);
+        std::process::exit(1);
+    }
+}
+
+/// 写入同目录的临时文件（tempfile 在 unix 上以 0600 创建）后原子替换目标
+fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        temp_file.as_file().set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    temp_file.write_all(bytes)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+fn read_password_source(source: Option<password_source::PasswordSource>) -> Option<SecretString> {
+    let source = source?;
+
+    if matches!(source, password_source::PasswordSource::Literal(_)) {
+        eprintln!("{}{}warning{}: -p exposes the password in shell history and the process list; \
+            prefer --password-env, --password-file, --password-fd or --password-command", PREFIX, RED, RESET);
+    }
+
+    match source.read() {
+        Ok(password) => Some(password),
+        Err(e) => {
+            eprintln!("{}{}{}{}", PREFIX, RED, e, RESET);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn get_password() -> SecretString {
     print!("> {}password:{} ", BOLD, RESET);
     io::stdout().flush().unwrap();
-    let password = read_password().unwrap();
-    password
+    SecretString::from(read_password().unwrap())
+}
+
+/// 提示写到标准错误，标准输出留给令牌或明文
+fn get_password_on_stderr(label: &str) -> SecretString {
+    eprint!("> {}{}:{} ", BOLD, label, RESET);
+    io::stderr().flush().unwrap();
+    SecretString::from(read_password().unwrap())
 }
 
-fn confirm_password(password: &String) -> bool {
+fn confirm_password(password: &SecretString) -> bool {
     print!("> {}confirm password:{} ", BOLD, RESET);
     io::stdout().flush().unwrap();
-    let local_password = read_password().unwrap();
-    password == &local_password
+    let local_password = SecretString::from(read_password().unwrap());
+    password.expose() == local_password.expose()
 }
 
 fn confirm() -> bool {
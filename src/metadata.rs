@@ -0,0 +1,254 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path};
+use std::time::{Duration, UNIX_EPOCH};
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::crypto_utils::*;
+
+/// 原文件的元数据，加密后放在主体开头，与内容一起受 HMAC 保护
+///
+/// 编码为 u32 小端长度 + 字段（u8 标签 + u32 小端长度 + 值），各字段均可缺省。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// 原文件名（不含目录）
+    pub name: Option<String>,
+    /// Unix 权限位
+    pub mode: Option<u32>,
+    /// 修改时间（Unix 秒，纳秒）
+    pub mtime: Option<(u64, u32)>,
+    /// 扩展属性（名称，值）
+    pub xattrs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Metadata {
+    /// 读取文件的名称、权限与修改时间，`xattrs` 为 true 时一并读取扩展属性
+    pub fn collect(path: &Path, xattrs: bool) -> io::Result<Self> {
+        let file_metadata = path.metadata()?;
+
+        #[cfg(unix)]
+        let mode = Some(std::os::unix::fs::PermissionsExt::mode(&file_metadata.permissions()) & 0o7777);
+        #[cfg(not(unix))]
+        let mode = None;
+
+        let mtime = file_metadata.modified().ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| (d.as_secs(), d.subsec_nanos()));
+
+        Ok(Self {
+            name: path.file_name().and_then(|n| n.to_str()).map(str::to_string),
+            mode,
+            mtime,
+            xattrs: if xattrs { read_xattrs(path)? } else { Vec::new() },
+        })
+    }
+
+    /// 写出元数据块（长度 + 字段）
+    pub fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let mut body = Vec::new();
+        if let Some(name) = &self.name {
+            push_field(&mut body, METADATA_FIELD_NAME, name.as_bytes());
+        }
+        if let Some(mode) = self.mode {
+            push_field(&mut body, METADATA_FIELD_MODE, &mode.to_le_bytes());
+        }
+        if let Some((secs, nanos)) = self.mtime {
+            push_field(&mut body, METADATA_FIELD_MTIME, &[&secs.to_le_bytes()[..], &nanos.to_le_bytes()].concat());
+        }
+        for (name, value) in &self.xattrs {
+            let field = [&(name.len() as u16).to_le_bytes()[..], name, value].concat();
+            push_field(&mut body, METADATA_FIELD_XATTR, &field);
+        }
+
+        if body.len() > METADATA_MAX_LENGTH {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "元数据过长"));
+        }
+        writer.write_all(&(body.len() as u32).to_le_bytes())?;
+        writer.write_all(&body)
+    }
+
+    /// 读取元数据块；此时尚未验证 HMAC，调用方须在验证通过后才使用
+    pub fn read_from(reader: &mut dyn Read) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut length = [0u8; 4];
+        reader.read_exact(&mut length)?;
+        let length = u32::from_le_bytes(length) as usize;
+        if length > METADATA_MAX_LENGTH {
+            return Err("元数据长度异常".into());
+        }
+
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body)?;
+        Self::decode(&body)
+    }
+
+    /// 解析元数据字段
+    fn decode(mut body: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut metadata = Metadata::default();
+
+        while !body.is_empty() {
+            if body.len() < 5 {
+                return Err("元数据字段损坏".into());
+            }
+            let tag = body[0];
+            let length = u32::from_le_bytes(body[1..5].try_into()?) as usize;
+            let value = body.get(5..5 + length).ok_or("元数据字段损坏")?;
+            body = &body[5 + length..];
+
+            match tag {
+                METADATA_FIELD_NAME => metadata.name = Some(String::from_utf8(value.to_vec())?),
+                METADATA_FIELD_MODE if value.len() == 4 => metadata.mode = Some(u32::from_le_bytes(value.try_into()?)),
+                METADATA_FIELD_MTIME if value.len() == 12 => {
+                    metadata.mtime = Some((u64::from_le_bytes(value[..8].try_into()?), u32::from_le_bytes(value[8..].try_into()?)));
+                }
+                METADATA_FIELD_XATTR if value.len() >= 2 => {
+                    let name_length = u16::from_le_bytes([value[0], value[1]]) as usize;
+                    let name = value.get(2..2 + name_length).ok_or("元数据字段损坏")?;
+                    metadata.xattrs.push((name.to_vec(), value[2 + name_length..].to_vec()));
+                }
+                _ => return Err(format!("不支持的元数据字段: {}", tag).into()),
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// 可安全用作输出文件名的原文件名：单个普通路径分量，不含目录
+    pub fn safe_name(&self) -> Option<&str> {
+        let name = self.name.as_deref()?;
+        let mut components = Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(n)), None) if n == name => Some(name),
+            _ => None,
+        }
+    }
+
+    /// 恢复到已写完的输出文件上；权限只恢复 rwx 位，不恢复 setuid/setgid/sticky
+    pub fn restore(&self, file: &File) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(mode & 0o777))?;
+        }
+
+        #[cfg(unix)]
+        for (name, value) in &self.xattrs {
+            use std::os::unix::ffi::OsStrExt;
+            xattr::FileExt::set_xattr(file, std::ffi::OsStr::from_bytes(name), value)?;
+        }
+
+        if let Some((secs, nanos)) = self.mtime {
+            file.set_modified(UNIX_EPOCH + Duration::new(secs, nanos))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 写入一个元数据字段
+fn push_field(out: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    out.push(tag);
+    out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+/// 读取全部扩展属性；文件系统不支持时返回空
+#[cfg(unix)]
+fn read_xattrs(path: &Path) -> io::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    if !xattr::SUPPORTED_PLATFORM {
+        return Ok(Vec::new());
+    }
+
+    let mut xattrs = Vec::new();
+    for name in xattr::list(path)? {
+        if let Some(value) = xattr::get(path, &name)? {
+            xattrs.push((name.as_bytes().to_vec(), value));
+        }
+    }
+    Ok(xattrs)
+}
+
+#[cfg(not(unix))]
+fn read_xattrs(_path: &Path) -> io::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    Ok(Vec::new())
+}
+
+/// 生成随机的外部文件名，不透露原文件名
+pub fn random_file_name() -> String {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new().fill(&mut bytes).expect("Failed to generate file name");
+    let name: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}.decx", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let metadata = Metadata {
+            name: Some("报告.pdf".to_string()),
+            mode: Some(0o640),
+            mtime: Some((1_700_000_000, 123_456_789)),
+            xattrs: vec![(b"user.comment".to_vec(), b"hello".to_vec())],
+        };
+
+        let mut bytes = Vec::new();
+        metadata.write_to(&mut bytes).unwrap();
+        assert_eq!(Metadata::read_from(&mut bytes.as_slice()).unwrap(), metadata);
+
+        // 空元数据只有长度
+        let mut bytes = Vec::new();
+        Metadata::default().write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, [0, 0, 0, 0]);
+
+        // 未知字段
+        let bytes = [6u8, 0, 0, 0, 0x7f, 1, 0, 0, 0, 0];
+        assert!(Metadata::read_from(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_safe_name() {
+        let named = |name: &str| Metadata { name: Some(name.to_string()), ..Metadata::default() };
+
+        assert_eq!(named("plain.txt").safe_name(), Some("plain.txt"));
+        assert_eq!(named("../etc/passwd").safe_name(), None);
+        assert_eq!(named("/etc/passwd").safe_name(), None);
+        assert_eq!(named("a/b").safe_name(), None);
+        assert_eq!(named("..").safe_name(), None);
+        assert_eq!(named("").safe_name(), None);
+        assert_eq!(Metadata::default().safe_name(), None);
+    }
+
+    #[test]
+    fn test_collect_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.txt");
+        let target = dir.path().join("target.txt");
+        std::fs::write(&source, b"x").unwrap();
+        std::fs::write(&target, b"x").unwrap();
+        File::options().write(true).open(&source).unwrap().set_modified(std::time::SystemTime::now() - Duration::from_secs(86400 * 30)).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&source, std::fs::Permissions::from_mode(0o600)).unwrap();
+        }
+
+        let metadata = Metadata::collect(&source, false).unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("source.txt"));
+
+        metadata.restore(&File::options().write(true).open(&target).unwrap()).unwrap();
+        let (source, target) = (source.metadata().unwrap(), target.metadata().unwrap());
+        assert_eq!(source.modified().unwrap(), target.modified().unwrap());
+        assert_eq!(source.permissions(), target.permissions());
+    }
+
+    #[test]
+    fn test_random_file_name() {
+        let name = random_file_name();
+        assert_eq!(name.len(), 32 + ".decx".len());
+        assert_ne!(name, random_file_name());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::crypto_utils::*;
+
+/// 隐藏长度的填充方式，记录在文件头中
+///
+/// 主体末尾依次为：全零填充 + u64 小端填充长度（尾部），二者与明文一起加密并参与 HMAC 计算。
+/// 填充后的长度按“内容 + 尾部”计算。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// Padmé：长度只保留 O(log log L) 位有效信息，开销不超过 12%
+    Padme,
+    /// 向上取整到指定大小的整数倍
+    Bucket(u64),
+    /// 固定为指定大小，内容超出时报错
+    Fixed(u64),
+}
+
+impl Padding {
+    /// 解析命令行参数：`padme`、`bucket:SIZE`、`fixed:SIZE`（SIZE 可带 K/M/G/T 后缀）
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let padding = match spec.split_once(':') {
+            None if spec == "padme" => Padding::Padme,
+            Some(("bucket", size)) => Padding::Bucket(parse_size(size)?),
+            Some(("fixed", size)) => Padding::Fixed(parse_size(size)?),
+            _ => return Err(format!("unknown padding: {}", spec)),
+        };
+
+        padding.validate()?;
+        Ok(padding)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match *self {
+            Padding::Bucket(0) => Err("bucket size must be positive".to_string()),
+            Padding::Fixed(size) if size <= PADDING_TRAILER_LENGTH as u64 => {
+                Err(format!("fixed size must be larger than {} bytes", PADDING_TRAILER_LENGTH))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 编码为文件头字段：标识 + u64 小端参数（padmé 无参数）
+    pub fn encode(&self) -> Vec<u8> {
+        match *self {
+            Padding::Padme => vec![PADDING_ID_PADME],
+            Padding::Bucket(size) => [&[PADDING_ID_BUCKET][..], &size.to_le_bytes()].concat(),
+            Padding::Fixed(size) => [&[PADDING_ID_FIXED][..], &size.to_le_bytes()].concat(),
+        }
+    }
+
+    /// 从文件头字段解码
+    pub fn decode(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let padding = match bytes {
+            [PADDING_ID_PADME] => Padding::Padme,
+            [PADDING_ID_BUCKET, size @ ..] if size.len() == 8 => Padding::Bucket(u64::from_le_bytes(size.try_into()?)),
+            [PADDING_ID_FIXED, size @ ..] if size.len() == 8 => Padding::Fixed(u64::from_le_bytes(size.try_into()?)),
+            [id, ..] => return Err(format!("不支持的填充方式: {}", id).into()),
+            [] => return Err("填充参数为空".into()),
+        };
+
+        padding.validate()?;
+        Ok(padding)
+    }
+
+    /// 计算内容长度为 `content_length` 时的填充长度（不含尾部）
+    pub fn padding_length(&self, content_length: u64) -> Result<u64, String> {
+        let length = content_length + PADDING_TRAILER_LENGTH as u64;
+
+        let padded = match *self {
+            Padding::Padme => padme(length),
+            Padding::Bucket(size) => length.div_ceil(size).checked_mul(size).ok_or("padded size overflows")?,
+            Padding::Fixed(size) if length <= size => size,
+            Padding::Fixed(size) => return Err(format!("content ({} bytes) does not fit fixed size {}", length, size)),
+        };
+
+        Ok(padded - length)
+    }
+}
+
+impl std::fmt::Display for Padding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Padding::Padme => write!(f, "padme"),
+            Padding::Bucket(size) => write!(f, "bucket:{}", size),
+            Padding::Fixed(size) => write!(f, "fixed:{}", size),
+        }
+    }
+}
+
+/// Padmé（PURBs, Nikitin et al. 2019）：保留指数的有效位，截去低位
+fn padme(length: u64) -> u64 {
+    if length < 2 {
+        return length;
+    }
+
+    let e = 63 - length.leading_zeros() as u64;
+    let s = 64 - e.leading_zeros() as u64;
+    let last_bits = e - s;
+    let mask = (1u64 << last_bits) - 1;
+    (length + mask) & !mask
+}
+
+/// 解析带 K/M/G/T 后缀（1024 进制）的大小
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let bad = || format!("bad size: {}", value);
+    let (number, shift) = match value.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&value[..i], 10),
+        Some((i, 'M' | 'm')) => (&value[..i], 20),
+        Some((i, 'G' | 'g')) => (&value[..i], 30),
+        Some((i, 'T' | 't')) => (&value[..i], 40),
+        _ => (value, 0),
+    };
+
+    let number: u64 = number.parse().map_err(|_| bad())?;
+    number.checked_mul(1 << shift).ok_or_else(bad)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_padme() {
+        // 论文中的示例
+        assert_eq!(padme(9), 10);
+        assert_eq!(padme(100), 104);
+        assert_eq!(padme(1000), 1024);
+        assert_eq!(padme(1_000_000), 1_015_808);
+
+        // 开销不超过 12%
+        for length in [17u64, 4097, 123_457, 987_654_321] {
+            let padded = padme(length);
+            assert!(padded >= length && (padded - length) * 100 <= length * 12);
+        }
+    }
+
+    #[test]
+    fn test_padding_length() {
+        let trailer = PADDING_TRAILER_LENGTH as u64;
+        assert_eq!(Padding::Bucket(4096).padding_length(1).unwrap(), 4096 - 1 - trailer);
+        assert_eq!(Padding::Bucket(4096).padding_length(4096 - trailer).unwrap(), 0);
+        assert_eq!(Padding::Fixed(1 << 20).padding_length(10).unwrap(), (1 << 20) - 10 - trailer);
+        assert!(Padding::Fixed(100).padding_length(100).is_err());
+        assert_eq!(Padding::Padme.padding_length(1000 - trailer).unwrap(), 24);
+    }
+
+    #[test]
+    fn test_parse_and_encode() {
+        assert_eq!(Padding::parse("padme").unwrap(), Padding::Padme);
+        assert_eq!(Padding::parse("bucket:64K").unwrap(), Padding::Bucket(64 * 1024));
+        assert_eq!(Padding::parse("fixed:1M").unwrap(), Padding::Fixed(1024 * 1024));
+        assert!(Padding::parse("bucket:0").is_err());
+        assert!(Padding::parse("fixed:4").is_err());
+        assert!(Padding::parse("random").is_err());
+
+        for padding in [Padding::Padme, Padding::Bucket(4096), Padding::Fixed(1 << 30)] {
+            assert_eq!(Padding::decode(&padding.encode()).unwrap(), padding);
+        }
+        assert!(Padding::decode(&[0x7f]).is_err());
+
+        assert_eq!(parse_size("4G").unwrap(), 4 << 30);
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert!(parse_size("4X").is_err());
+    }
+}
@@ -40,7 +40,7 @@ pub fn ctr_apply_in_parts(
     }
 
     // 计算每个数据块的大小，使用向上取整，确保覆盖所有数据
-    let chunk_size = (total_len + num_parts - 1) / num_parts;
+    let chunk_size = total_len.div_ceil(num_parts);
 
     // 将数据分成多个可变切片
     let mut chunks: Vec<&mut [u8]> = data.chunks_mut(chunk_size).collect();
@@ -0,0 +1,676 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use crate::crypto_utils::*;
+use crate::volume::{self, VolumeReader, VolumeWriter};
+
+/// Reed-Solomon 纠错参数
+///
+/// 纠错数据在加密之后作为外层封装加入：密文按段切分，每段 64 个数据块加若干校验块，
+/// 每块附带校验和。读取时校验和不符的块视为丢失，只要一段中损坏的块不超过校验块数即可还原，
+/// 因此修复在 HMAC 校验之前完成，HMAC 仍然验证还原后的密文。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parity {
+    pub data_shards: u8,
+    pub parity_shards: u8,
+    pub shard_size: u32,
+}
+
+impl Parity {
+    /// 校验块占数据块的百分比（1 至 100），向上取整为块数
+    pub fn with_percent(percent: u32) -> Result<Self, String> {
+        if !(1..=PARITY_MAX_PERCENT).contains(&percent) {
+            return Err(format!("parity must be 1% to {}%", PARITY_MAX_PERCENT));
+        }
+        let parity_shards = (PARITY_DATA_SHARDS as u32 * percent).div_ceil(100) as u8;
+        Ok(Self { data_shards: PARITY_DATA_SHARDS, parity_shards, shard_size: PARITY_SHARD_SIZE })
+    }
+
+    /// 解析 `--parity=PERCENT`，如 `10` 或 `10%`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let percent = value.strip_suffix('%').unwrap_or(value).parse::<u32>()
+            .map_err(|_| format!("bad parity: {}", value))?;
+        Self::with_percent(percent)
+    }
+
+    fn total_shards(&self) -> usize {
+        self.data_shards as usize + self.parity_shards as usize
+    }
+
+    /// 一个完整段中的数据长度
+    fn segment_length(&self) -> u64 {
+        self.data_shards as u64 * self.shard_size as u64
+    }
+
+    /// 第 `index` 段的数据长度与块长度（最后一段按剩余数据缩小块长度）
+    fn segment_layout(&self, length: u64, index: u64) -> (usize, usize) {
+        let data_length = std::cmp::min(self.segment_length(), length - index * self.segment_length()) as usize;
+        (data_length, data_length.div_ceil(self.data_shards as usize))
+    }
+
+    /// 封装 `length` 字节后各段所占的长度（不含文件头与尾部）；长度取自尾部，溢出视为损坏
+    fn stored_length(&self, length: u64) -> io::Result<u64> {
+        let full = length / self.segment_length();
+        let rest = length % self.segment_length();
+        let segment = |shard_size: u64| self.total_shards() as u64 * (shard_size + PARITY_CHECKSUM_LENGTH as u64);
+        let last = if rest > 0 { segment(rest.div_ceil(self.data_shards as u64)) } else { 0 };
+        full.checked_mul(segment(self.shard_size as u64))
+            .and_then(|stored| stored.checked_add(last))
+            .ok_or_else(|| parity_error("长度溢出".to_string()))
+    }
+
+    /// 魔数 + 版本 + 数据块数 + 校验块数 + u32 块长度
+    fn encode_fields(&self) -> Vec<u8> {
+        let mut bytes = PARITY_MAGIC.to_vec();
+        bytes.push(PARITY_VERSION);
+        bytes.push(self.data_shards);
+        bytes.push(self.parity_shards);
+        bytes.extend_from_slice(&self.shard_size.to_le_bytes());
+        bytes
+    }
+
+    fn decode_fields(bytes: &[u8]) -> Option<Self> {
+        if &bytes[..4] != PARITY_MAGIC || bytes[4] != PARITY_VERSION {
+            return None;
+        }
+        let parity = Self {
+            data_shards: bytes[5],
+            parity_shards: bytes[6],
+            shard_size: u32::from_le_bytes(bytes[7..11].try_into().unwrap()),
+        };
+        let valid = parity.data_shards > 0 && parity.parity_shards > 0 && parity.shard_size > 0;
+        valid.then_some(parity)
+    }
+
+    /// 文件头：参数 + 校验和
+    fn encode_header(&self) -> Vec<u8> {
+        let mut bytes = self.encode_fields();
+        let sum = checksum(&[&bytes]);
+        bytes.extend_from_slice(&sum);
+        bytes
+    }
+
+    fn decode_header(bytes: &[u8]) -> Option<Self> {
+        let (fields, sum) = bytes.split_at(PARITY_HEADER_LENGTH - PARITY_CHECKSUM_LENGTH);
+        if checksum(&[fields]) != sum {
+            return None;
+        }
+        Self::decode_fields(fields)
+    }
+
+    /// 尾部：参数 + u64 密文长度 + 校验和，写两份
+    fn encode_trailer(&self, length: u64) -> Vec<u8> {
+        let mut bytes = self.encode_fields();
+        bytes.extend_from_slice(&length.to_le_bytes());
+        let sum = checksum(&[&bytes]);
+        bytes.extend_from_slice(&sum);
+        bytes
+    }
+
+    fn decode_trailer(bytes: &[u8]) -> Option<(Self, u64)> {
+        let (fields, sum) = bytes.split_at(PARITY_TRAILER_LENGTH - PARITY_CHECKSUM_LENGTH);
+        if checksum(&[fields]) != sum {
+            return None;
+        }
+        let length = u64::from_le_bytes(fields[11..19].try_into().unwrap());
+        Self::decode_fields(fields).map(|parity| (parity, length))
+    }
+}
+
+impl std::fmt::Display for Parity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "reed-solomon {}+{} shards of {} bytes", self.data_shards, self.parity_shards, self.shard_size)
+    }
+}
+
+/// BLAKE3 截取前 8 字节；只用于发现意外损坏，防篡改由 HMAC 负责
+fn checksum(parts: &[&[u8]]) -> [u8; PARITY_CHECKSUM_LENGTH] {
+    let mut hasher = blake3::Hasher::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut sum = [0u8; PARITY_CHECKSUM_LENGTH];
+    sum.copy_from_slice(&hasher.finalize().as_bytes()[..PARITY_CHECKSUM_LENGTH]);
+    sum
+}
+
+/// 块的校验和包含段号与块号，错位的块同样被识别为损坏
+fn shard_checksum(segment: u64, shard: usize, data: &[u8]) -> [u8; PARITY_CHECKSUM_LENGTH] {
+    checksum(&[&segment.to_le_bytes(), &[shard as u8], data])
+}
+
+fn parity_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("纠错封装损坏: {}", message))
+}
+
+fn codec(parity: Parity) -> io::Result<ReedSolomon> {
+    ReedSolomon::new(parity.data_shards as usize, parity.parity_shards as usize)
+        .map_err(|e| parity_error(e.to_string()))
+}
+
+/// 流是否带纠错封装：开头或结尾有纠错魔数（其中一处损坏时仍能识别），读取后回到开头
+pub fn is_parity<R: Read + Seek>(file: &mut R) -> io::Result<bool> {
+    let length = file.seek(SeekFrom::End(0))?;
+    let mut magic = [0u8; 4];
+    let mut found = false;
+
+    if length >= (PARITY_HEADER_LENGTH + 2 * PARITY_TRAILER_LENGTH) as u64 {
+        for offset in [0, length - PARITY_TRAILER_LENGTH as u64] {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut magic)?;
+            found |= &magic == PARITY_MAGIC;
+        }
+    }
+    file.rewind()?;
+
+    Ok(found)
+}
+
+/// 读取文件的纠错参数；文件没有纠错封装时返回 `None`
+pub fn detect(path: &Path) -> io::Result<Option<Parity>> {
+    let mut file = File::open(path)?;
+    if !is_parity(&mut file)? {
+        return Ok(None);
+    }
+    Ok(Some(ParityReader::new(file, false)?.parity))
+}
+
+/// 纠错封装写入器：文件头 + 每凑满一段写出数据块与校验块 + 两份尾部
+pub struct ParityWriter<W: Write> {
+    inner: W,
+    parity: Parity,
+    codec: ReedSolomon,
+    buffer: Vec<u8>,
+    segment: u64,
+    length: u64,
+}
+
+impl<W: Write> ParityWriter<W> {
+    /// 写出文件头
+    pub fn new(mut inner: W, parity: Parity) -> io::Result<Self> {
+        inner.write_all(&parity.encode_header())?;
+        let capacity = parity.total_shards() * parity.shard_size as usize;
+        Ok(Self { inner, parity, codec: codec(parity)?, buffer: Vec::with_capacity(capacity), segment: 0, length: 0 })
+    }
+
+    /// 计算校验块并写出缓冲中的一段
+    fn write_segment(&mut self) -> io::Result<()> {
+        let shard_size = self.buffer.len().div_ceil(self.parity.data_shards as usize);
+        self.buffer.resize(shard_size * self.parity.total_shards(), 0);
+
+        let mut shards: Vec<&mut [u8]> = self.buffer.chunks_mut(shard_size).collect();
+        self.codec.encode(&mut shards).map_err(|e| parity_error(e.to_string()))?;
+
+        for (index, shard) in shards.iter().enumerate() {
+            self.inner.write_all(shard)?;
+            self.inner.write_all(&shard_checksum(self.segment, index, shard))?;
+        }
+        self.segment += 1;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// 写出最后一段与尾部，返回内部写入器
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.buffer.is_empty() {
+            self.write_segment()?;
+        }
+        let trailer = self.parity.encode_trailer(self.length);
+        self.inner.write_all(&trailer)?;
+        self.inner.write_all(&trailer)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ParityWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let space = self.parity.segment_length() as usize - self.buffer.len();
+        let count = std::cmp::min(space, buf.len());
+        self.buffer.extend_from_slice(&buf[..count]);
+        self.length += count as u64;
+
+        if self.buffer.len() == self.parity.segment_length() as usize {
+            self.write_segment()?;
+        }
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 纠错封装读取器：按段读取并校验每一块，损坏的块由校验块还原，支持定位
+pub struct ParityReader<R: Read + Seek> {
+    inner: R,
+    parity: Parity,
+    codec: ReedSolomon,
+    length: u64,
+    position: u64,
+    /// 最近还原的一段：段号与数据
+    segment: Option<(u64, Vec<u8>)>,
+    /// 是否在标准错误报告修复的段
+    report: bool,
+    /// 有损坏的段号与损坏的块数
+    damaged: BTreeMap<u64, usize>,
+    /// 文件头或某一份尾部损坏
+    envelope_damaged: bool,
+}
+
+impl<R: Read + Seek> ParityReader<R> {
+    /// 读取文件头与尾部；文件头损坏时参数取自尾部，尾部两份中任意一份完好即可
+    pub fn new(mut inner: R, report: bool) -> io::Result<Self> {
+        let file_length = inner.seek(SeekFrom::End(0))?;
+        let envelope_length = (PARITY_HEADER_LENGTH + 2 * PARITY_TRAILER_LENGTH) as u64;
+        if file_length < envelope_length {
+            return Err(parity_error("文件过短".to_string()));
+        }
+
+        let mut header = [0u8; PARITY_HEADER_LENGTH];
+        inner.rewind()?;
+        inner.read_exact(&mut header)?;
+        let mut trailers = [0u8; 2 * PARITY_TRAILER_LENGTH];
+        inner.seek(SeekFrom::Start(file_length - trailers.len() as u64))?;
+        inner.read_exact(&mut trailers)?;
+
+        let header = Parity::decode_header(&header);
+        let copies: Vec<_> = trailers.chunks(PARITY_TRAILER_LENGTH).map(Parity::decode_trailer).collect();
+        let (parity, length) = copies.iter().flatten().next().copied()
+            .ok_or_else(|| parity_error("两份尾部均已损坏".to_string()))?;
+
+        if header.is_some_and(|header| header != parity) {
+            return Err(parity_error("文件头与尾部的参数不一致".to_string()));
+        }
+        if envelope_length.checked_add(parity.stored_length(length)?) != Some(file_length) {
+            return Err(parity_error("文件长度不符，可能被截断".to_string()));
+        }
+
+        Ok(Self {
+            inner,
+            parity,
+            codec: codec(parity)?,
+            length,
+            position: 0,
+            segment: None,
+            report,
+            damaged: BTreeMap::new(),
+            envelope_damaged: header.is_none() || copies.iter().any(Option::is_none),
+        })
+    }
+
+    pub fn parity(&self) -> Parity {
+        self.parity
+    }
+
+    /// 还原后的密文长度
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// 已读到的损坏块总数
+    pub fn damaged_shards(&self) -> usize {
+        self.damaged.values().sum()
+    }
+
+    /// 已读到的有损坏的段数
+    pub fn damaged_segments(&self) -> usize {
+        self.damaged.len()
+    }
+
+    pub fn envelope_damaged(&self) -> bool {
+        self.envelope_damaged
+    }
+
+    /// 读取第 `index` 段，校验每一块，必要时用校验块还原数据块
+    fn load_segment(&mut self, index: u64) -> io::Result<()> {
+        let (data_length, shard_size) = self.parity.segment_layout(self.length, index);
+        let stored_shard = shard_size + PARITY_CHECKSUM_LENGTH;
+        let offset = PARITY_HEADER_LENGTH as u64 + self.parity.stored_length(index * self.parity.segment_length())?;
+
+        let mut raw = vec![0u8; stored_shard * self.parity.total_shards()];
+        self.inner.seek(SeekFrom::Start(offset))?;
+        self.inner.read_exact(&mut raw)?;
+
+        let mut shards: Vec<Option<Vec<u8>>> = raw.chunks(stored_shard).enumerate().map(|(shard, stored)| {
+            let (data, sum) = stored.split_at(shard_size);
+            (shard_checksum(index, shard, data) == sum).then(|| data.to_vec())
+        }).collect();
+
+        let bad = shards.iter().filter(|shard| shard.is_none()).count();
+        if bad > self.parity.parity_shards as usize {
+            return Err(parity_error(format!("第 {} 段有 {} 个块损坏，超过可修复的 {} 个",
+                index + 1, bad, self.parity.parity_shards)));
+        }
+        if bad > 0 {
+            self.codec.reconstruct_data(&mut shards).map_err(|e| parity_error(e.to_string()))?;
+            if self.damaged.insert(index, bad).is_none() && self.report {
+                eprintln!("DEC!: segment {}: {} damaged shard(s) repaired from parity", index + 1, bad);
+            }
+        }
+
+        let mut data = Vec::with_capacity(shard_size * self.parity.data_shards as usize);
+        for shard in shards.iter().take(self.parity.data_shards as usize).flatten() {
+            data.extend_from_slice(shard);
+        }
+        data.truncate(data_length);
+        self.segment = Some((index, data));
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for ParityReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.length {
+            return Ok(0);
+        }
+
+        let index = self.position / self.parity.segment_length();
+        if self.segment.as_ref().map(|(loaded, _)| *loaded) != Some(index) {
+            self.load_segment(index)?;
+        }
+        let data = &self.segment.as_ref().unwrap().1;
+        let start = (self.position - index * self.parity.segment_length()) as usize;
+        let count = std::cmp::min(buf.len(), data.len() - start);
+        buf[..count].copy_from_slice(&data[start..start + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> Seek for ParityReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.length.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek"))?;
+        Ok(self.position)
+    }
+}
+
+/// `dec repair` 的结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum RepairOutcome {
+    /// 没有发现损坏，未改动文件
+    Intact,
+    /// 写出了修复后的文件（校验块重新计算）
+    Repaired { segments: usize, shards: usize, envelope: bool },
+}
+
+/// 读取并还原带纠错封装的文件，重新计算校验块写出完好的副本（不需要密码）
+///
+/// 未指定 `output_file_path` 时原地替换：先写到同目录的临时文件并重新读取校验，再原子重命名；
+/// 没有发现损坏时不改动原文件。分卷按原来的卷大小重新写出，`output_file_path` 为新分卷的公共名。
+pub fn repair_file(input_file_path: &str, output_file_path: Option<&str>) -> Result<RepairOutcome, Box<dyn std::error::Error>> {
+    let input_path = Path::new(input_file_path);
+    if !input_path.is_file() {
+        return Err(format!("输入文件不存在: {}", input_file_path).into());
+    }
+
+    let mut file = File::open(input_path)?;
+    if volume::is_volume(input_path, &mut file)? {
+        return repair_volumes(input_path, output_file_path);
+    }
+    if !is_parity(&mut file)? {
+        return Err(NO_PARITY.into());
+    }
+    let mut reader = ParityReader::new(file, false)?;
+
+    let target = output_file_path.map(Path::new).unwrap_or(input_path);
+    let dir = parent_dir(target);
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+
+    rewrite(&mut reader, BufWriter::with_capacity(BUFFER_SIZE, temp_file.as_file_mut()))?.flush()?;
+    if output_file_path.is_none() && is_intact(&reader) {
+        return Ok(RepairOutcome::Intact);
+    }
+    temp_file.as_file().sync_all()?;
+
+    // 从磁盘重新读取副本，每一块的校验和都应完好
+    check_copy(ParityReader::new(File::open(temp_file.path())?, false)?, reader.length())?;
+
+    if output_file_path.is_none() {
+        std::fs::set_permissions(temp_file.path(), input_path.metadata()?.permissions())?;
+    }
+    temp_file.persist(target).map_err(|e| e.error)?;
+
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+
+    Ok(repaired(&reader))
+}
+
+const NO_PARITY: &str = "文件没有纠错数据（加密时使用 --parity）";
+
+/// 修复一组分卷：损坏的卷交给纠错数据还原，新分卷先写到同目录的临时目录，校验后逐卷重命名
+fn repair_volumes(input_path: &Path, output_file_path: Option<&str>) -> Result<RepairOutcome, Box<dyn std::error::Error>> {
+    let mut volumes = VolumeReader::open(input_path)?;
+    volumes.tolerate_damage(false);
+    if !is_parity(&mut volumes)? {
+        return Err(NO_PARITY.into());
+    }
+    let volume_size = volumes.volume_size();
+    let mut reader = ParityReader::new(volumes, false)?;
+
+    let name = input_path.to_str().unwrap_or_default();
+    let (source_base, _) = volume::split_volume_name(name)
+        .ok_or_else(|| format!("无法从文件名确定卷号: {}", name))?;
+    let target_base = output_file_path.unwrap_or(source_base);
+    let temp_dir = tempfile::tempdir_in(parent_dir(Path::new(target_base)))?;
+    let temp_base = temp_dir.path().join("repair").to_str().ok_or("临时目录路径不是 UTF-8")?.to_string();
+
+    let writer = rewrite(&mut reader, BufWriter::with_capacity(BUFFER_SIZE, VolumeWriter::create(&temp_base, volume_size)?))?;
+    let count = writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+    if output_file_path.is_none() && is_intact(&reader) {
+        return Ok(RepairOutcome::Intact);
+    }
+
+    // 各卷写完时已落盘；从磁盘重新读取，卷摘要与每一块的校验和都应完好
+    check_copy(ParityReader::new(VolumeReader::open(Path::new(&volume::volume_path(&temp_base, 1)))?, false)?, reader.length())?;
+
+    for index in 1..=count {
+        let from = volume::volume_path(&temp_base, index);
+        let target = volume::volume_path(target_base, index);
+        if output_file_path.is_none() && let Ok(metadata) = std::fs::metadata(&target) {
+            std::fs::set_permissions(&from, metadata.permissions())?;
+        }
+        std::fs::rename(&from, &target)?;
+    }
+
+    #[cfg(unix)]
+    File::open(parent_dir(Path::new(target_base)))?.sync_all()?;
+
+    Ok(repaired(&reader))
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+/// 把还原后的数据重新封装写出（校验块重新计算），返回内部写入器
+fn rewrite<R: Read + Seek, W: Write>(reader: &mut ParityReader<R>, writer: W) -> io::Result<W> {
+    let mut writer = ParityWriter::new(writer, reader.parity())?;
+    io::copy(reader, &mut writer)?;
+    writer.finish()
+}
+
+fn is_intact<R: Read + Seek>(reader: &ParityReader<R>) -> bool {
+    reader.damaged_shards() == 0 && !reader.envelope_damaged()
+}
+
+fn repaired<R: Read + Seek>(reader: &ParityReader<R>) -> RepairOutcome {
+    RepairOutcome::Repaired {
+        segments: reader.damaged_segments(),
+        shards: reader.damaged_shards(),
+        envelope: reader.envelope_damaged(),
+    }
+}
+
+/// 读完写出的副本，确认没有任何损坏、长度与原数据相同
+fn check_copy<R: Read + Seek>(mut check: ParityReader<R>, length: u64) -> Result<(), Box<dyn std::error::Error>> {
+    io::copy(&mut check, &mut io::sink())?;
+    if !is_intact(&check) || check.length() != length {
+        return Err("修复后的文件校验失败，保留原文件".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn encode(data: &[u8], parity: Parity) -> Vec<u8> {
+        let mut writer = ParityWriter::new(Vec::new(), parity).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    type MemoryReader = ParityReader<Cursor<Vec<u8>>>;
+
+    fn decode(stored: Vec<u8>) -> io::Result<(Vec<u8>, MemoryReader)> {
+        let mut reader = ParityReader::new(Cursor::new(stored), false)?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok((data, reader))
+    }
+
+    fn sample(length: usize) -> Vec<u8> {
+        (0..length).map(|i| (i * 7 + i / 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let parity = Parity::parse("10%").unwrap();
+        assert_eq!((parity.data_shards, parity.parity_shards), (64, 7));
+        assert_eq!(Parity::parse("100").unwrap().parity_shards, 64);
+        assert_eq!(Parity::parse("1").unwrap().parity_shards, 1);
+        assert_eq!(Parity::parse("x").unwrap_err(), "bad parity: x");
+        assert!(Parity::parse("0").is_err());
+        assert!(Parity::parse("101%").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let parity = Parity::with_percent(10).unwrap();
+        let segment = parity.segment_length() as usize;
+        for length in [1, 100, segment - 1, segment, segment + 1, 3 * segment + 4321] {
+            let data = sample(length);
+            let stored = encode(&data, parity);
+            assert_eq!(stored.len() as u64, (PARITY_HEADER_LENGTH + 2 * PARITY_TRAILER_LENGTH) as u64 + parity.stored_length(length as u64).unwrap());
+
+            let (decoded, reader) = decode(stored).unwrap();
+            assert_eq!(decoded, data);
+            assert_eq!(reader.damaged_shards(), 0);
+            assert!(!reader.envelope_damaged());
+        }
+    }
+
+    #[test]
+    fn test_repairs_damage() {
+        let parity = Parity::with_percent(5).unwrap();
+        let data = sample(2 * parity.segment_length() as usize + 1000);
+        let mut stored = encode(&data, parity);
+        let stored_shard = parity.shard_size as usize + PARITY_CHECKSUM_LENGTH;
+
+        // 第一段损坏 4 个块（含一个校验块），第二段一个 4K 区块整体清零，文件头与一份尾部各翻转一位
+        for shard in [0, 10, 63, 66] {
+            stored[PARITY_HEADER_LENGTH + shard * stored_shard + 17] ^= 0x40;
+        }
+        let second = PARITY_HEADER_LENGTH + parity.total_shards() * stored_shard;
+        stored[second + 5000..second + 9096].fill(0);
+        stored[2] ^= 1;
+        let last = stored.len() - 1;
+        stored[last] ^= 1;
+
+        let (decoded, reader) = decode(stored).unwrap();
+        assert_eq!(decoded, data);
+        assert_eq!(reader.damaged_segments(), 2);
+        assert_eq!(reader.damaged_shards(), 6);
+        assert!(reader.envelope_damaged());
+    }
+
+    #[test]
+    fn test_seek() {
+        let parity = Parity::with_percent(10).unwrap();
+        let data = sample(3 * parity.segment_length() as usize + 10);
+        let mut reader = ParityReader::new(Cursor::new(encode(&data, parity)), false).unwrap();
+
+        for offset in [0, 1, parity.segment_length() - 3, 2 * parity.segment_length() + 99] {
+            reader.seek(SeekFrom::Start(offset)).unwrap();
+            let mut buf = [0u8; 16];
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, &data[offset as usize..offset as usize + 16]);
+        }
+        assert_eq!(reader.seek(SeekFrom::End(-4)).unwrap(), data.len() as u64 - 4);
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, &data[data.len() - 4..]);
+    }
+
+    #[test]
+    fn test_unrecoverable() {
+        let parity = Parity::with_percent(1).unwrap();
+        let mut stored = encode(&sample(10_000), parity);
+        let shard_size = 10_000usize.div_ceil(64) + PARITY_CHECKSUM_LENGTH;
+        stored[PARITY_HEADER_LENGTH] ^= 1;
+        stored[PARITY_HEADER_LENGTH + shard_size] ^= 1;
+        let error = decode(stored).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // 两份尾部都损坏时无法确定长度
+        let mut stored = encode(&sample(100), parity);
+        let length = stored.len();
+        stored[length - 1] ^= 1;
+        stored[length - 1 - PARITY_TRAILER_LENGTH] ^= 1;
+        assert!(decode(stored).is_err());
+
+        // 截断
+        let mut stored = encode(&sample(100), parity);
+        stored.remove(PARITY_HEADER_LENGTH);
+        assert!(decode(stored).is_err());
+
+        // 伪造的尾部声称极大的长度：报告损坏而不是溢出
+        let mut stored = encode(&sample(100), parity);
+        let trailers = stored.len() - 2 * PARITY_TRAILER_LENGTH;
+        stored.truncate(trailers);
+        stored.extend_from_slice(&parity.encode_trailer(u64::MAX).repeat(2));
+        assert_eq!(decode(stored).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_repair_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.decx");
+        let parity = Parity::with_percent(10).unwrap();
+        let data = sample(300_000);
+        let clean = encode(&data, parity);
+        std::fs::write(&path, &clean).unwrap();
+        let path_str = path.to_str().unwrap();
+
+        assert_eq!(repair_file(path_str, None).unwrap(), RepairOutcome::Intact);
+
+        let mut damaged = clean.clone();
+        damaged[PARITY_HEADER_LENGTH + 123] ^= 0xff;
+        std::fs::write(&path, &damaged).unwrap();
+
+        // 写出副本，原文件不变
+        let copy = dir.path().join("copy.decx");
+        let outcome = repair_file(path_str, Some(copy.to_str().unwrap())).unwrap();
+        assert_eq!(outcome, RepairOutcome::Repaired { segments: 1, shards: 1, envelope: false });
+        assert_eq!(std::fs::read(&copy).unwrap(), clean);
+        assert_eq!(std::fs::read(&path).unwrap(), damaged);
+
+        // 原地修复
+        repair_file(path_str, None).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), clean);
+
+        // 没有纠错封装的文件
+        let plain = dir.path().join("plain.decx");
+        std::fs::write(&plain, b"DEC!\x03 not protected").unwrap();
+        assert!(repair_file(plain.to_str().unwrap(), None).is_err());
+    }
+}
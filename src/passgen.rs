@@ -0,0 +1,154 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::secret::SecretString;
+
+/// 生成口令所用的词表（BIP39 英文词表，2048 个词，每词 11 比特）
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+/// 随机字符串的字符集：字母、数字及不需要 shell 转义的符号
+const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789!#%+-.:=@^_~";
+
+pub const DEFAULT_WORDS: usize = 8;
+pub const MIN_WORDS: usize = 4;
+pub const MAX_WORDS: usize = 64;
+pub const MIN_CHARS: usize = 8;
+pub const MAX_CHARS: usize = 256;
+
+/// 生成的密码形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PassStyle {
+    /// 以 `-` 连接的单词（diceware 风格）
+    Words(usize),
+    /// 随机字符串
+    Chars(usize),
+}
+
+impl Default for PassStyle {
+    fn default() -> Self {
+        PassStyle::Words(DEFAULT_WORDS)
+    }
+}
+
+impl PassStyle {
+    /// 熵（比特）
+    pub fn entropy_bits(&self) -> f64 {
+        match *self {
+            PassStyle::Words(count) => count as f64 * (words().count() as f64).log2(),
+            PassStyle::Chars(length) => length as f64 * (CHARSET.len() as f64).log2(),
+        }
+    }
+}
+
+impl std::fmt::Display for PassStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PassStyle::Words(count) => write!(f, "{} words", count),
+            PassStyle::Chars(length) => write!(f, "{} characters", length),
+        }
+    }
+}
+
+fn words() -> impl Iterator<Item = &'static str> {
+    WORDLIST.lines()
+}
+
+/// 使用系统随机数生成密码
+pub fn generate(style: PassStyle) -> Result<SecretString, Box<dyn std::error::Error>> {
+    let rng = SystemRandom::new();
+
+    // 预先分配足够容量，避免扩容时在内存中留下副本
+    let password = match style {
+        PassStyle::Words(count) => {
+            let list: Vec<&str> = words().collect();
+            let mut password = String::with_capacity(count * 9);
+            for i in 0..count {
+                if i > 0 { password.push('-'); }
+                password.push_str(list[random_below(&rng, list.len())?]);
+            }
+            password
+        }
+        PassStyle::Chars(length) => {
+            let mut password = String::with_capacity(length);
+            for _ in 0..length {
+                password.push(CHARSET[random_below(&rng, CHARSET.len())?] as char);
+            }
+            password
+        }
+    };
+
+    Ok(SecretString::from(password))
+}
+
+/// 均匀地取 `[0, n)` 中的随机数（拒绝采样，避免取模偏差）
+fn random_below(rng: &SystemRandom, n: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    let n = n as u32;
+    let zone = u32::MAX - u32::MAX % n;
+
+    loop {
+        let mut bytes = [0u8; 4];
+        rng.fill(&mut bytes).map_err(|_| "Failed to generate random number")?;
+        let value = u32::from_le_bytes(bytes);
+        if value < zone {
+            return Ok((value % n) as usize);
+        }
+    }
+}
+
+/// 将密码写入新文件（权限 0600），文件已存在时报错
+pub fn write_password_file(path: &str, password: &SecretString) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path)
+        .map_err(|e| format!("cannot create password file {}: {}", path, e))?;
+    file.write_all(password.expose().as_bytes())?;
+    file.write_all(b"\n")?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wordlist() {
+        let list: Vec<&str> = words().collect();
+        assert_eq!(list.len(), 2048);
+        assert!(list.iter().all(|w| !w.is_empty() && w.len() <= 8 && w.chars().all(|c| c.is_ascii_lowercase())));
+        assert_eq!(PassStyle::Words(8).entropy_bits(), 88.0);
+    }
+
+    #[test]
+    fn test_generate() {
+        let words = generate(PassStyle::Words(6)).unwrap();
+        assert_eq!(words.expose().split('-').count(), 6);
+
+        let chars = generate(PassStyle::Chars(32)).unwrap();
+        assert_eq!(chars.expose().len(), 32);
+        assert!(chars.expose().bytes().all(|b| CHARSET.contains(&b)));
+
+        assert_ne!(generate(PassStyle::default()).unwrap().expose(), generate(PassStyle::default()).unwrap().expose());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_password_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pw");
+        let path = path.to_str().unwrap();
+        let password = SecretString::from("abandon-ability".to_string());
+
+        write_password_file(path, &password).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "abandon-ability\n");
+        assert_eq!(std::fs::metadata(path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        // 不覆盖已有文件
+        assert!(write_password_file(path, &password).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+/// 常见密码（小写、去掉 leet 替换后比较），命中时强度按字典攻击估算
+const COMMON_PASSWORDS: &[&str] = &[
+    "password", "123456", "12345678", "123456789", "1234567890", "qwerty", "qwertyuiop",
+    "abc123", "111111", "123123", "admin", "letmein", "welcome", "monkey", "dragon", "master",
+    "sunshine", "princess", "football", "baseball", "iloveyou", "trustno1", "superman", "batman",
+    "shadow", "michael", "jennifer", "hunter", "ranger", "buster", "soccer", "hockey", "killer",
+    "starwars", "whatever", "freedom", "secret", "changeme", "default", "login", "access",
+    "flower", "hello", "charlie", "donald", "loveme", "zaq1zaq1", "qazwsx", "asdfgh", "zxcvbn",
+    "1q2w3e4r", "000000", "654321", "666666", "987654321", "computer", "internet", "mustang",
+];
+
+/// 密码强度估计
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strength {
+    /// 0（极弱）到 4（很强）
+    pub score: u8,
+    /// 估计的熵（比特）
+    pub bits: f64,
+}
+
+impl Strength {
+    pub fn label(&self) -> &'static str {
+        match self.score {
+            0 => "very weak",
+            1 => "weak",
+            2 => "fair",
+            3 => "strong",
+            _ => "very strong",
+        }
+    }
+}
+
+impl std::fmt::Display for Strength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}/4, ~{:.0} bits)", self.label(), self.score, self.bits)
+    }
+}
+
+/// 粗略估计密码强度
+///
+/// 按字符集大小估算每个字符的熵；与前一字符相同或相邻（`aaa`、`abc`、`321`）的字符只计 1 比特；
+/// 整体或去掉末尾数字/符号后是常见密码时，按字典大小估算。
+pub fn estimate(password: &str) -> Strength {
+    let chars: Vec<char> = password.chars().collect();
+    let per_char = (pool_size(&chars) as f64).log2();
+
+    let mut bits = 0.0;
+    for (i, c) in chars.iter().enumerate() {
+        let predictable = i > 0 && (*c as i64 - chars[i - 1] as i64).abs() <= 1;
+        bits += if predictable { 1.0 } else { per_char };
+    }
+
+    // 常见密码及其后缀变体（如 `Password1!`）
+    let lowercase = password.to_lowercase();
+    let core = lowercase.trim_end_matches(|c: char| c.is_ascii_digit() || c.is_ascii_punctuation());
+    if is_common(&lowercase) {
+        bits = f64::min(bits, 10.0);
+    } else if !core.is_empty() && is_common(core) {
+        let suffix = lowercase.chars().count() - core.chars().count();
+        bits = f64::min(bits, 10.0 + suffix as f64 * 10f64.log2());
+    }
+
+    let score = match bits {
+        b if b < 28.0 => 0,
+        b if b < 36.0 => 1,
+        b if b < 60.0 => 2,
+        b if b < 80.0 => 3,
+        _ => 4,
+    };
+
+    Strength { score, bits }
+}
+
+/// 是否为常见密码（含 leet 变体）
+fn is_common(lowercase: &str) -> bool {
+    COMMON_PASSWORDS.contains(&lowercase) || COMMON_PASSWORDS.contains(&normalize(lowercase).as_str())
+}
+
+/// 字符集大小
+fn pool_size(chars: &[char]) -> u32 {
+    let mut pool = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) { pool += 26; }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) { pool += 26; }
+    if chars.iter().any(|c| c.is_ascii_digit()) { pool += 10; }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') { pool += 33; }
+    if chars.iter().any(|c| !c.is_ascii()) { pool += 100; }
+    pool.max(1)
+}
+
+/// 小写并还原常见的 leet 替换，用于字典比较
+fn normalize(password: &str) -> String {
+    password.to_lowercase().chars().map(|c| match c {
+        '0' => 'o',
+        '1' | '!' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        c => c,
+    }).collect()
+}
+
+/// 不满足策略时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PolicyMode {
+    /// 打印警告后继续
+    Warn,
+    /// 拒绝加密
+    Refuse,
+}
+
+impl PolicyMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "warn" => Ok(PolicyMode::Warn),
+            "refuse" => Ok(PolicyMode::Refuse),
+            _ => Err(format!("bad password policy: {}", value)),
+        }
+    }
+}
+
+/// 加密时的密码策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPolicy {
+    pub min_score: u8,
+    pub min_length: usize,
+    /// 禁止出现在密码中的词（小写）
+    pub forbidden_words: Vec<String>,
+    pub mode: PolicyMode,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_score: 2,
+            min_length: 8,
+            forbidden_words: Vec::new(),
+            mode: PolicyMode::Warn,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// 检查密码，返回所有不满足的条目
+    pub fn check(&self, password: &str) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push(format!("shorter than {} characters", self.min_length));
+        }
+
+        let strength = estimate(password);
+        if strength.score < self.min_score {
+            violations.push(format!("strength {}/4 is below the minimum {}/4", strength.score, self.min_score));
+        }
+
+        let lowercase = password.to_lowercase();
+        let normalized = normalize(password);
+        for word in &self.forbidden_words {
+            if lowercase.contains(word.as_str()) || normalized.contains(word.as_str()) {
+                violations.push(format!("contains forbidden word \"{}\"", word));
+            }
+        }
+
+        violations
+    }
+
+    /// 读取禁用词文件：每行一个词，忽略空行和 `#` 注释
+    pub fn load_forbidden_words(path: &str) -> Result<Vec<String>, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read forbidden words {}: {}", path, e))?;
+
+        Ok(content.lines()
+            .map(|line| line.trim().to_lowercase())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        assert_eq!(estimate("a").score, 0);
+        assert_eq!(estimate("password").score, 0);
+        assert_eq!(estimate("P@ssw0rd").score, 0);
+        assert_eq!(estimate("abcdefghijkl").score, 0);
+        assert!(estimate("Password123!").score <= 1);
+        assert!(estimate("Tr0ub4dor&3").score >= 3);
+        assert_eq!(estimate("correct horse battery staple").score, 4);
+        assert_eq!(estimate("").bits, 0.0);
+    }
+
+    #[test]
+    fn test_policy_check() {
+        let policy = PasswordPolicy {
+            forbidden_words: vec!["acme".to_string()],
+            ..PasswordPolicy::default()
+        };
+
+        assert!(policy.check("correct horse battery staple").is_empty());
+        assert_eq!(policy.check("a").len(), 2);
+
+        let violations = policy.check("Correct horse ACM3 staple");
+        assert_eq!(violations, vec!["contains forbidden word \"acme\"".to_string()]);
+    }
+
+    #[test]
+    fn test_load_forbidden_words() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "# company names\nAcme\n\n  widget  \n").unwrap();
+
+        let words = PasswordPolicy::load_forbidden_words(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(words, vec!["acme".to_string(), "widget".to_string()]);
+    }
+}
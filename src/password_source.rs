@@ -0,0 +1,185 @@
+use std::io::Read;
+use crate::secret::SecretString;
+#[cfg(unix)]
+use crate::secret::SecretBytes;
+
+/// 非交互式密码来源
+///
+/// 除 `Literal` 外均在使用时读取，并统一去掉末尾的一个换行（`\n` 或 `\r\n`），
+/// 因此 `echo pw > file` 与 `printf pw > file` 得到相同的密码。
+#[derive(Debug)]
+pub enum PasswordSource {
+    /// `-p PASSWORD`，会留在 shell 历史与 `/proc/<pid>/cmdline` 中
+    Literal(SecretString),
+    /// `--password-env NAME`
+    Env(String),
+    /// `--password-file PATH`
+    File(String),
+    /// `--password-fd N`
+    Fd(i32),
+    /// `--password-command "cmd"`，取命令的标准输出
+    Command(String),
+}
+
+impl PasswordSource {
+    /// 读取密码
+    pub fn read(self) -> Result<SecretString, String> {
+        let password = match self {
+            PasswordSource::Literal(password) => return non_empty(password),
+            PasswordSource::Env(name) => std::env::var(&name)
+                .map_err(|_| format!("environment variable {} is not set", name))?,
+            PasswordSource::File(path) => std::fs::read_to_string(&path)
+                .map_err(|e| format!("cannot read password file {}: {}", path, e))?,
+            PasswordSource::Fd(fd) => read_fd(fd)?,
+            PasswordSource::Command(command) => run_command(&command)?,
+        };
+
+        non_empty(SecretString::from(strip_newline(password)))
+    }
+
+    /// 可以写进配置、之后由其他进程重新读取的命令行选项；`-p` 与 `--password-fd` 没有
+    pub fn to_option(&self) -> Option<(&'static str, String)> {
+        match self {
+            PasswordSource::Env(name) => Some(("--password-env", name.clone())),
+            PasswordSource::File(path) => Some(("--password-file", path.clone())),
+            PasswordSource::Command(command) => Some(("--password-command", command.clone())),
+            PasswordSource::Literal(_) | PasswordSource::Fd(_) => None,
+        }
+    }
+}
+
+/// 去掉末尾的一个换行
+fn strip_newline(mut password: String) -> String {
+    if password.ends_with('\n') {
+        password.pop();
+        if password.ends_with('\r') {
+            password.pop();
+        }
+    }
+    password
+}
+
+fn non_empty(password: SecretString) -> Result<SecretString, String> {
+    if password.expose().is_empty() {
+        return Err("empty password".to_string());
+    }
+    Ok(password)
+}
+
+/// 从描述符读取的密码上限，缓冲区按此预先分配，读取时不会扩容留下副本
+#[cfg(unix)]
+const FD_PASSWORD_LIMIT: usize = 64 * 1024;
+
+#[cfg(unix)]
+fn read_fd(fd: i32) -> Result<String, String> {
+    use std::mem::ManuallyDrop;
+    use std::os::fd::FromRawFd;
+
+    // 标准输出与标准错误不可能提供密码，读取失败前还会被误当作自己的描述符关闭
+    if fd < 0 || fd == 1 || fd == 2 {
+        return Err(format!("bad file descriptor: {}", fd));
+    }
+
+    // SAFETY: 描述符由调用方通过命令行交给本进程；ManuallyDrop 保证只借用、不关闭
+    let mut file = ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    let mut buffer = SecretBytes::zeroed(FD_PASSWORD_LIMIT);
+    let mut length = 0;
+    loop {
+        if length == FD_PASSWORD_LIMIT {
+            return Err(format!("password from fd {} is too long", fd));
+        }
+        match file.read(&mut buffer.expose_mut()[length..]) {
+            Ok(0) => break,
+            Ok(count) => length += count,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("cannot read password from fd {}: {}", fd, e)),
+        }
+    }
+
+    std::str::from_utf8(&buffer.expose()[..length])
+        .map(str::to_string)
+        .map_err(|_| format!("password from fd {} is not UTF-8", fd))
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32) -> Result<String, String> {
+    Err("--password-fd is only supported on unix".to_string())
+}
+
+/// 通过 shell 运行命令，标准错误直接输出给用户
+fn run_command(command: &str) -> Result<String, String> {
+    #[cfg(unix)]
+    let mut child = std::process::Command::new("sh");
+    #[cfg(unix)]
+    child.arg("-c").arg(command);
+    #[cfg(not(unix))]
+    let mut child = std::process::Command::new("cmd");
+    #[cfg(not(unix))]
+    child.arg("/C").arg(command);
+
+    let output = child
+        .stdin(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .map_err(|e| format!("cannot run password command: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!("password command failed: {}", output.status));
+    }
+
+    String::from_utf8(output.stdout).map_err(|_| "password command output is not UTF-8".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_newline() {
+        assert_eq!(strip_newline("pw".to_string()), "pw");
+        assert_eq!(strip_newline("pw\n".to_string()), "pw");
+        assert_eq!(strip_newline("pw\r\n".to_string()), "pw");
+        // 只去掉一个换行，其余空白属于密码
+        assert_eq!(strip_newline("pw \n\n".to_string()), "pw \n");
+    }
+
+    #[test]
+    fn test_read_sources() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "from-file\n").unwrap();
+        let source = PasswordSource::File(file.path().to_str().unwrap().to_string());
+        assert_eq!(source.read().unwrap().expose(), "from-file");
+
+        // SAFETY: 变量名仅本测试使用
+        unsafe { std::env::set_var("DEC_TEST_PASSWORD_SOURCE", "from-env\n"); }
+        let source = PasswordSource::Env("DEC_TEST_PASSWORD_SOURCE".to_string());
+        assert_eq!(source.read().unwrap().expose(), "from-env");
+
+        assert!(PasswordSource::Env("DEC_TEST_PASSWORD_UNSET".to_string()).read().is_err());
+
+        std::fs::write(file.path(), "\n").unwrap();
+        let source = PasswordSource::File(file.path().to_str().unwrap().to_string());
+        assert_eq!(source.read().unwrap_err(), "empty password");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_command_and_fd() {
+        let source = PasswordSource::Command("echo from-command".to_string());
+        assert_eq!(source.read().unwrap().expose(), "from-command");
+
+        assert!(PasswordSource::Command("exit 3".to_string()).read().is_err());
+
+        use std::os::fd::{FromRawFd, IntoRawFd};
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "from-fd\r\n").unwrap();
+        let fd = std::fs::File::open(file.path()).unwrap().into_raw_fd();
+        assert_eq!(PasswordSource::Fd(fd).read().unwrap().expose(), "from-fd");
+        // 描述符只是借用，读取后仍由调用方持有
+        // SAFETY: fd 仍然有效，由这里收回并关闭
+        drop(unsafe { std::fs::File::from_raw_fd(fd) });
+
+        assert!(PasswordSource::Fd(1).read().is_err());
+        assert!(PasswordSource::Fd(2).read().is_err());
+    }
+}
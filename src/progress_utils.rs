@@ -10,8 +10,8 @@ static LAST_PROGRESS: AtomicI32 = AtomicI32::new(-1);
 
 /// 更新并显示带时间的进度
 pub fn update_progress(total_read: u64, file_size: u64) {
-    // 更新进度
-    let mut progress = (total_read * 100 / file_size) as i32;
+    // 更新进度（空文件直接视为完成）
+    let mut progress = (total_read * 100).checked_div(file_size).map_or(100, |p| p as i32);
 
     // 获得更好体验...yes!
     if progress > 98 {
@@ -48,7 +48,7 @@ pub fn update_progress(total_read: u64, file_size: u64) {
     }
 
     // 限制进度在0-100之间
-    progress = progress.min(100).max(0);
+    progress = progress.clamp(0, 100);
     LAST_PROGRESS.store(progress, Ordering::Relaxed);
     
     // 计算进度条长度
@@ -89,6 +89,11 @@ pub fn update_progress(total_read: u64, file_size: u64) {
     }
 }
 
+/// 重置进度记录，开始处理新文件前调用
+pub fn reset_progress() {
+    LAST_PROGRESS.store(-1, Ordering::Relaxed);
+}
+
 /// 格式化持续时间显示
 pub fn format_duration(duration: std::time::Duration) -> String {
     let total_secs = duration.as_secs();
@@ -0,0 +1,102 @@
+use std::io::{Cursor, Read, Write};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use crate::armor::ArmorReader;
+use crate::crypto_utils::*;
+use crate::decryptor;
+use crate::encryptor::{EncryptOptions, PayloadWriter};
+use crate::secret::SecretBytes;
+
+/// 以默认参数加密短字符串，返回单行令牌，可直接写进配置文件或环境变量
+///
+/// 密钥派生或生成随机数失败时返回错误，因此返回 `Result<String, _>` 而不是 `String`。
+pub fn seal(plaintext: &[u8], password: &str) -> Result<String, Box<dyn std::error::Error>> {
+    seal_with_options(plaintext, password, &EncryptOptions::default())
+}
+
+/// 按加密选项（KDF、压缩、填充）加密短字符串，返回 `dec:` + URL 安全的 base64
+///
+/// 令牌即完整的加密文件，不含元数据块；`armor` 选项被忽略。
+pub fn seal_with_options(plaintext: &[u8], password: &str, options: &EncryptOptions) -> Result<String, Box<dyn std::error::Error>> {
+    let mut writer = PayloadWriter::new(Vec::new(), password, options, None)?;
+    writer.write_all(plaintext)?;
+    let encrypted = writer.finish()?;
+
+    Ok(format!("{}{}", TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(encrypted)))
+}
+
+/// 解密令牌（也接受 `--armor` 输出的 ASCII 封装），HMAC 验证通过后返回明文
+pub fn open(token: &str, password: &str) -> Result<SecretBytes, Box<dyn std::error::Error>> {
+    let token = token.trim();
+
+    let encrypted = if let Some(encoded) = token.strip_prefix(TOKEN_PREFIX) {
+        URL_SAFE_NO_PAD.decode(encoded).map_err(|_| "令牌格式错误")?
+    } else if token.starts_with(ARMOR_BEGIN) {
+        let mut decoded = Vec::new();
+        ArmorReader::new(Cursor::new(token.as_bytes()))?.read_to_end(&mut decoded)?;
+        decoded
+    } else {
+        return Err(format!("不是有效的令牌，应以 {} 开头", TOKEN_PREFIX).into());
+    };
+
+    decryptor::decrypt_bytes(encrypted, password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_derivation::Kdf;
+
+    fn light_options() -> EncryptOptions {
+        EncryptOptions { kdf: Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS }, ..EncryptOptions::default() }
+    }
+
+    #[test]
+    fn test_seal_open() {
+        let token = seal_with_options(b"ghp_0123456789abcdef", "pw", &light_options()).unwrap();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || "-_:".contains(c)));
+
+        assert_eq!(open(&token, "pw").unwrap().expose(), b"ghp_0123456789abcdef");
+        assert_eq!(open(&format!("  {}\n", token), "pw").unwrap().expose(), b"ghp_0123456789abcdef");
+        assert!(open(&token, "wrong").is_err());
+
+        // 篡改令牌
+        let mut tampered = token.into_bytes();
+        let last = tampered.len() - 10;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        assert!(open(std::str::from_utf8(&tampered).unwrap(), "pw").is_err());
+
+        assert!(open("not a token", "pw").is_err());
+    }
+
+    #[test]
+    fn test_seal_default_options() {
+        let token = seal(b"hunter2", "pw").unwrap();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(open(&token, "pw").unwrap().expose(), b"hunter2");
+    }
+
+    #[test]
+    fn test_open_compressed() {
+        // 压缩后的密文远短于明文，解密时缓冲区须扩容
+        let options = EncryptOptions { compression: Some(crate::compression::Compression::new(3)), ..light_options() };
+        let plaintext = vec![b'x'; 300_000];
+        let token = seal_with_options(&plaintext, "pw", &options).unwrap();
+        assert!(token.len() < plaintext.len() / 10);
+        assert_eq!(open(&token, "pw").unwrap().expose(), plaintext.as_slice());
+    }
+
+    #[test]
+    fn test_open_armored() {
+        let mut writer = crate::armor::ArmorWriter::new(Vec::new()).unwrap();
+        writer.write_all(&{
+            let mut payload = PayloadWriter::new(Vec::new(), "pw", &light_options(), None).unwrap();
+            payload.write_all(b"").unwrap();
+            payload.finish().unwrap()
+        }).unwrap();
+        let armored = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(open(&armored, "pw").unwrap().expose(), b"");
+    }
+}
@@ -0,0 +1,117 @@
+use zeroize::Zeroize;
+
+/// 持有密钥或明文的字节缓冲区：drop 时清零，不可 Clone，Debug 不输出内容
+///
+/// 启用 `mlock` 特性时，在 Linux 上锁定密钥所在内存页，避免被换出到交换分区。
+pub struct SecretBytes {
+    bytes: Vec<u8>,
+    locked: bool,
+}
+
+impl SecretBytes {
+    /// 创建全零缓冲区（用于明文缓冲）
+    pub fn zeroed(len: usize) -> Self {
+        Self { bytes: vec![0u8; len], locked: false }
+    }
+
+    /// 创建全零的密钥缓冲区，尽可能锁定内存
+    pub fn key(len: usize) -> Self {
+        let mut secret = Self::zeroed(len);
+        secret.locked = memory_lock(&secret.bytes);
+        secret
+    }
+
+    pub fn expose(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn expose_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self { bytes, locked: false }
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        // 只清零内容、不改变长度，以便按原区域解锁
+        self.bytes.as_mut_slice().zeroize();
+        if self.locked {
+            memory_unlock(&self.bytes);
+        }
+    }
+}
+
+impl std::fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.bytes.len())
+    }
+}
+
+/// 持有密码的字符串：drop 时清零，不可 Clone，Debug 不输出内容
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+/// 锁定内存页，失败（如超出 `RLIMIT_MEMLOCK`）时静默回退
+#[cfg(all(target_os = "linux", feature = "mlock"))]
+fn memory_lock(bytes: &[u8]) -> bool {
+    // SAFETY: 指针与长度来自有效的切片，mlock 不会读写其内容
+    !bytes.is_empty() && unsafe { libc::mlock(bytes.as_ptr().cast(), bytes.len()) } == 0
+}
+
+#[cfg(not(all(target_os = "linux", feature = "mlock")))]
+fn memory_lock(_bytes: &[u8]) -> bool {
+    false
+}
+
+#[cfg(all(target_os = "linux", feature = "mlock"))]
+fn memory_unlock(bytes: &[u8]) {
+    // SAFETY: 与 memory_lock 锁定的区域相同
+    unsafe { libc::munlock(bytes.as_ptr().cast(), bytes.len()); }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "mlock")))]
+fn memory_unlock(_bytes: &[u8]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacted() {
+        let mut key = SecretBytes::key(4);
+        key.expose_mut().copy_from_slice(b"\x13\x37\xbe\xef");
+        assert_eq!(format!("{:?}", key), "SecretBytes([REDACTED; 4])");
+
+        let password = SecretString::from("hunter2".to_string());
+        assert_eq!(password.expose(), "hunter2");
+        assert!(!format!("{:?}", password).contains("hunter2"));
+    }
+}
@@ -0,0 +1,184 @@
+use std::fs::{File, Metadata};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::crypto_utils::*;
+use crate::decryptor;
+use crate::volume::{self, VolumeReader};
+
+/// 加密成功后删除明文源文件
+///
+/// 依次确认：输出已落盘（fsync 文件与目录；分卷输出逐卷确认）、重新读取输出并通过 HMAC 校验、
+/// 源文件在加密期间没有被改动（长度与修改时间同 `before` 一致），全部通过后才删除。
+/// `shred` 为 true 时删除前先以随机数据覆盖一遍并落盘；在 SSD（磨损均衡）、写时复制
+/// 或带快照的文件系统（btrfs、ZFS、APFS 等）上，覆盖写到新位置，旧数据仍可能留在介质上。
+pub fn remove_source(input_file_path: &str, output_file_path: &str, password: &str, shred: bool, before: &Metadata) -> Result<(), Box<dyn std::error::Error>> {
+    let input_path = Path::new(input_file_path);
+    let output_path = Path::new(output_file_path);
+
+    // 分卷输出由任意一卷找到整组，每一卷都要确认
+    let outputs: Vec<PathBuf> = if volume::is_volume(output_path, &mut File::open(output_path)?)? {
+        VolumeReader::open(output_path)?.paths().map(PathBuf::from).collect()
+    } else {
+        vec![output_path.to_path_buf()]
+    };
+
+    let input = input_path.canonicalize()?;
+    for output in &outputs {
+        if input == output.canonicalize()? {
+            return Err("输入与输出是同一个文件".into());
+        }
+        // 确认输出已落盘
+        File::open(output)?.sync_all()?;
+    }
+    #[cfg(unix)]
+    File::open(parent_dir(output_path))?.sync_all()?;
+
+    // 从磁盘重新读取输出并校验 HMAC
+    decryptor::verify_with_password(output_file_path, password)
+        .map_err(|e| format!("输出文件校验失败，保留源文件: {}", e))?;
+
+    // 加密期间仍在写入的文件（如日志）不能删除
+    let now = input_path.symlink_metadata()?;
+    if !now.is_file() || now.len() != before.len() || now.modified()? != before.modified()? {
+        return Err("源文件在加密期间被改动，保留源文件".into());
+    }
+
+    if shred {
+        overwrite(input_path, now.len())?;
+    }
+    std::fs::remove_file(input_path)?;
+
+    #[cfg(unix)]
+    File::open(parent_dir(input_path))?.sync_all()?;
+
+    Ok(())
+}
+
+/// 源文件的其他硬链接数：删除这个名字并不会删除内容，覆盖则对所有链接生效
+#[cfg(unix)]
+pub fn other_links(metadata: &Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::nlink(metadata).saturating_sub(1)
+}
+
+#[cfg(not(unix))]
+pub fn other_links(_metadata: &Metadata) -> u64 {
+    0
+}
+
+/// 以随机数据覆盖文件的全部内容并落盘（不改变长度）
+fn overwrite(path: &Path, length: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::options().write(true).open(path)?;
+    let rng = SystemRandom::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+
+    let mut remaining = length;
+    while remaining > 0 {
+        let chunk = std::cmp::min(remaining, BUFFER_SIZE as u64) as usize;
+        rng.fill(&mut buffer[..chunk]).map_err(|_| "无法生成随机数据")?;
+        file.write_all(&buffer[..chunk])?;
+        remaining -= chunk as u64;
+    }
+
+    file.sync_all()?;
+    Ok(())
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryptor::EncryptOptions;
+    use crate::key_derivation::Kdf;
+
+    fn encrypt(dir: &Path, content: &[u8]) -> (String, String, Metadata) {
+        let input = dir.join("plain.txt");
+        std::fs::write(&input, content).unwrap();
+        let before = input.metadata().unwrap();
+
+        let output = dir.join("plain.txt.decx");
+        let options = EncryptOptions { kdf: Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS }, ..EncryptOptions::default() };
+        crate::encryptor::encrypt_with_mode(input.to_str().unwrap(), output.to_str().unwrap(), "pw", &options).unwrap();
+        (input.to_str().unwrap().to_string(), output.to_str().unwrap().to_string(), before)
+    }
+
+    #[test]
+    fn test_remove_source() {
+        for shred in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let (input, output, before) = encrypt(dir.path(), b"remove me");
+
+            remove_source(&input, &output, "pw", shred, &before).unwrap();
+            assert!(!Path::new(&input).exists());
+            assert!(Path::new(&output).exists());
+        }
+    }
+
+    #[test]
+    fn test_remove_source_split() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("plain.txt");
+        let content: Vec<u8> = (0..20_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        std::fs::write(&input, &content).unwrap();
+        let before = input.metadata().unwrap();
+
+        let base = dir.path().join("plain.txt.decx").to_str().unwrap().to_string();
+        let options = EncryptOptions {
+            kdf: Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS },
+            split: Some(VOLUME_MIN_SIZE),
+            ..EncryptOptions::default()
+        };
+        crate::encryptor::encrypt_with_mode(input.to_str().unwrap(), &base, "pw", &options).unwrap();
+
+        // 缺少一卷时整组无法确认，保留源文件
+        let last = volume::volume_path(&base, 5);
+        let saved = std::fs::read(&last).unwrap();
+        std::fs::remove_file(&last).unwrap();
+        assert!(remove_source(input.to_str().unwrap(), &volume::volume_path(&base, 1), "pw", false, &before).is_err());
+        assert!(input.exists());
+
+        std::fs::write(&last, saved).unwrap();
+        remove_source(input.to_str().unwrap(), &volume::volume_path(&base, 1), "pw", false, &before).unwrap();
+        assert!(!input.exists());
+    }
+
+    #[test]
+    fn test_remove_source_keeps_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let (input, output, before) = encrypt(dir.path(), b"keep me");
+
+        // 密码不符
+        assert!(remove_source(&input, &output, "wrong", true, &before).is_err());
+
+        // 输出损坏
+        let mut bytes = std::fs::read(&output).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(&output, &bytes).unwrap();
+        assert!(remove_source(&input, &output, "pw", true, &before).is_err());
+
+        // 加密之后源文件又被写入
+        let (input, output, before) = encrypt(dir.path(), b"keep me");
+        std::fs::OpenOptions::new().append(true).open(&input).unwrap().write_all(b" more").unwrap();
+        assert!(remove_source(&input, &output, "pw", true, &before).is_err());
+        assert_eq!(std::fs::read(&input).unwrap(), b"keep me more");
+
+        assert!(remove_source(&output, &output, "pw", false, &before).is_err());
+    }
+
+    #[test]
+    fn test_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.txt");
+        let content = vec![b'a'; BUFFER_SIZE + 100];
+        std::fs::write(&path, &content).unwrap();
+
+        overwrite(&path, content.len() as u64).unwrap();
+        let overwritten = std::fs::read(&path).unwrap();
+        assert_eq!(overwritten.len(), content.len());
+        assert_ne!(overwritten, content);
+    }
+}
@@ -0,0 +1,617 @@
+use std::path::Path;
+use aes::Aes256;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ctr::Ctr128BE;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use serde_json::{Map, Number, Value};
+use crate::crypto_utils::*;
+use crate::hmac_validator::HmacValidator;
+use crate::key_derivation::{self, Kdf, PasswordNormalization};
+use crate::secret::{SecretBytes, SecretString};
+
+type Aes256Ctr = Ctr128BE<Aes256>;
+
+/// 支持逐值加密的配置文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+    /// `KEY=VALUE` 每行一项
+    Dotenv,
+}
+
+impl ConfigFormat {
+    /// 按扩展名识别格式；`.env`、`.env.production`、`prod.env` 都视为 dotenv
+    pub fn from_path(path: &str) -> Option<Self> {
+        let name = Path::new(path).file_name()?.to_str()?;
+        if name == ".env" || name.starts_with(".env.") || name.ends_with(".env") {
+            return Some(ConfigFormat::Dotenv);
+        }
+
+        match Path::new(name).extension()?.to_str()? {
+            "json" => Some(ConfigFormat::Json),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "toml" => Some(ConfigFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// 解析为保留键顺序的树；顶层必须是映射
+    fn parse(&self, text: &str) -> Result<Map<String, Value>, Box<dyn std::error::Error>> {
+        let value: Value = match self {
+            ConfigFormat::Json => serde_json::from_str(text)?,
+            ConfigFormat::Yaml => serde_yaml::from_str(text)?,
+            ConfigFormat::Toml => {
+                let value = toml::from_str(text)?;
+                reject_toml_datetime(&value)?;
+                value
+            }
+            ConfigFormat::Dotenv => Value::Object(parse_dotenv(text)?),
+        };
+
+        match value {
+            Value::Object(map) => Ok(map),
+            _ => Err("配置文件的顶层必须是映射".into()),
+        }
+    }
+
+    /// 输出为原格式的文本
+    fn render(&self, map: Map<String, Value>) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(&Value::Object(map))? + "\n",
+            ConfigFormat::Yaml => serde_yaml::to_string(&Value::Object(map))?,
+            ConfigFormat::Toml => toml::to_string(&Value::Object(map))?,
+            ConfigFormat::Dotenv => render_dotenv(&map)?,
+        })
+    }
+}
+
+/// 顶层 `dec` 段：解密所需的参数与整篇文档的 MAC
+struct DocumentMetadata {
+    version: u64,
+    kdf: Kdf,
+    salt: Vec<u8>,
+    key_check: Vec<u8>,
+    /// 加密时间（Unix 秒）
+    modified: u64,
+    mac: Vec<u8>,
+}
+
+impl DocumentMetadata {
+    fn to_map(&self) -> Map<String, Value> {
+        let mut map = Map::new();
+        map.insert("version".to_string(), Value::from(self.version));
+        map.insert("kdf".to_string(), Value::from(self.kdf.to_string()));
+        map.insert("salt".to_string(), Value::from(STANDARD.encode(&self.salt)));
+        map.insert("key_check".to_string(), Value::from(STANDARD.encode(&self.key_check)));
+        map.insert("modified".to_string(), Value::from(self.modified));
+        map.insert("mac".to_string(), Value::from(STANDARD.encode(&self.mac)));
+        map
+    }
+
+    /// 解析 `dec` 段；dotenv 中各字段为字符串
+    fn from_map(map: &Map<String, Value>) -> Result<Self, Box<dyn std::error::Error>> {
+        let field = |name: &str| -> Result<String, Box<dyn std::error::Error>> {
+            match map.get(name) {
+                Some(Value::String(s)) => Ok(s.clone()),
+                Some(Value::Number(n)) => Ok(n.to_string()),
+                _ => Err(format!("加密元数据缺少字段: {}", name).into()),
+            }
+        };
+        let decode = |name: &str| -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            STANDARD.decode(field(name)?).map_err(|_| format!("加密元数据字段损坏: {}", name).into())
+        };
+
+        let version: u64 = field("version")?.parse().map_err(|_| "加密元数据字段损坏: version")?;
+        if version != STRUCTURED_VERSION {
+            return Err(format!("不支持的结构化加密版本: {}", version).into());
+        }
+
+        Ok(Self {
+            version,
+            kdf: Kdf::parse(&field("kdf")?)?,
+            salt: decode("salt")?,
+            key_check: decode("key_check")?,
+            modified: field("modified")?.parse().map_err(|_| "加密元数据字段损坏: modified")?,
+            mac: decode("mac")?,
+        })
+    }
+}
+
+/// 值在文档中的位置，参与每个值的认证，防止密文被挪到其他键下
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// 位置的无歧义编码
+fn path_bytes(path: &[Segment]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for segment in path {
+        let (kind, bytes) = match segment {
+            Segment::Key(key) => (b'k', key.as_bytes().to_vec()),
+            Segment::Index(index) => (b'i', index.to_string().into_bytes()),
+        };
+        out.push(kind);
+        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        out.extend_from_slice(&bytes);
+    }
+    out
+}
+
+/// 位置的可读形式，如 `db.password`、`servers[0]`
+fn path_display(path: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if out.is_empty() => out.push_str(key),
+            Segment::Key(key) => { out.push('.'); out.push_str(key); }
+            Segment::Index(index) => out.push_str(&format!("[{}]", index)),
+        }
+    }
+    out
+}
+
+/// 按文档顺序访问每个叶子值（映射与数组以外的值）
+fn walk<F>(value: &mut Value, path: &mut Vec<Segment>, visit: &mut F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(&[Segment], &mut Value) -> Result<(), Box<dyn std::error::Error>>,
+{
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                path.push(Segment::Key(key.clone()));
+                walk(child, path, visit)?;
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter_mut().enumerate() {
+                path.push(Segment::Index(index));
+                walk(child, path, visit)?;
+                path.pop();
+            }
+        }
+        leaf => visit(path, leaf)?,
+    }
+    Ok(())
+}
+
+/// 文档的密钥
+struct DocumentKeys {
+    encryption_key: SecretBytes,
+    hmac_key: SecretBytes,
+}
+
+impl DocumentKeys {
+    /// 密码按 NFC 规范化后派生主密钥，再用 HKDF 派生加密与 HMAC 密钥
+    fn derive(password: &str, kdf: &Kdf, salt: &[u8]) -> Result<(Self, SecretBytes), Box<dyn std::error::Error>> {
+        let password = PasswordNormalization::Nfc.apply(password);
+        let master_key = key_derivation::derive_master_key(kdf, password.expose().as_bytes(), salt)?;
+        let (encryption_key, hmac_key) = key_derivation::derive_encryption_and_hmac_keys(&master_key)?;
+        Ok((Self { encryption_key, hmac_key }, master_key))
+    }
+
+    /// 单个值的认证标签：位置 + 类型 + IV + 密文
+    fn value_tag(&self, path: &[u8], kind: &str, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut hmac = HmacValidator::new(self.hmac_key.expose())?;
+        hmac.update(b"dec-value\0");
+        for part in [path, kind.as_bytes(), iv, ciphertext] {
+            hmac.update(&(part.len() as u32).to_le_bytes());
+            hmac.update(part);
+        }
+        Ok(hmac.finalize())
+    }
+
+    /// 整篇文档的 MAC：元数据参数 + 按顺序的全部叶子（位置与加密后的值）
+    fn document_mac(&self, metadata: &DocumentMetadata, leaves: &[(Vec<u8>, String)]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut hmac = HmacValidator::new(self.hmac_key.expose())?;
+        hmac.update(b"dec-document\0");
+        hmac.update(&metadata.version.to_le_bytes());
+        hmac.update(&metadata.modified.to_le_bytes());
+        for part in [metadata.kdf.to_string().as_bytes(), &metadata.salt, &metadata.key_check] {
+            hmac.update(&(part.len() as u32).to_le_bytes());
+            hmac.update(part);
+        }
+
+        hmac.update(&(leaves.len() as u64).to_le_bytes());
+        for (path, value) in leaves {
+            for part in [path.as_slice(), value.as_bytes()] {
+                hmac.update(&(part.len() as u32).to_le_bytes());
+                hmac.update(part);
+            }
+        }
+        Ok(hmac.finalize())
+    }
+
+    /// 加密一个叶子值，返回 `ENC[...]` 字符串；null 保持不变
+    fn encrypt_value(&self, path: &[u8], value: &Value) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let (kind, data) = match value {
+            Value::String(s) => ("str", s.clone().into_bytes()),
+            Value::Number(n) if n.is_f64() => ("float", n.to_string().into_bytes()),
+            Value::Number(n) => ("int", n.to_string().into_bytes()),
+            Value::Bool(b) => ("bool", b.to_string().into_bytes()),
+            _ => return Ok(None),
+        };
+        let mut data = zeroize::Zeroizing::new(data);
+
+        let iv = generate_iv();
+        Aes256Ctr::new(self.encryption_key.expose().into(), iv.as_slice().into()).apply_keystream(&mut data);
+        let tag = self.value_tag(path, kind, &iv, &data)?;
+
+        Ok(Some(format!("{}data:{},iv:{},tag:{},type:{}]",
+            STRUCTURED_VALUE_PREFIX, STANDARD.encode(&*data), STANDARD.encode(&iv), STANDARD.encode(&tag), kind)))
+    }
+
+    /// 验证并解密 `ENC[...]` 字符串，恢复原类型
+    fn decrypt_value(&self, path: &[u8], encrypted: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        let fields = encrypted.strip_prefix(STRUCTURED_VALUE_PREFIX)
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or("加密值格式错误")?;
+
+        let (mut data, mut iv, mut tag, mut kind) = (None, None, None, None);
+        for field in fields.split(',') {
+            match field.split_once(':') {
+                Some(("data", v)) => data = Some(STANDARD.decode(v)?),
+                Some(("iv", v)) => iv = Some(STANDARD.decode(v)?),
+                Some(("tag", v)) => tag = Some(STANDARD.decode(v)?),
+                Some(("type", v)) => kind = Some(v),
+                _ => return Err("加密值格式错误".into()),
+            }
+        }
+        let (Some(data), Some(iv), Some(tag), Some(kind)) = (data, iv, tag, kind) else {
+            return Err("加密值格式错误".into());
+        };
+        if iv.len() != IV_LENGTH {
+            return Err("加密值格式错误".into());
+        }
+
+        if !tags_equal(&self.value_tag(path, kind, &iv, &data)?, &tag) {
+            return Err("认证失败，值可能已被篡改或移动".into());
+        }
+
+        let mut plaintext = zeroize::Zeroizing::new(data);
+        Aes256Ctr::new(self.encryption_key.expose().into(), iv.as_slice().into()).apply_keystream(&mut plaintext);
+        let text = std::str::from_utf8(&plaintext).map_err(|_| "解密后的值不是 UTF-8")?;
+
+        Ok(match kind {
+            "str" => Value::String(text.to_string()),
+            "int" => Value::Number(text.parse::<i64>().map(Number::from)
+                .or_else(|_| text.parse::<u64>().map(Number::from))
+                .map_err(|_| "解密后的整数无效")?),
+            "float" => Value::Number(text.parse::<f64>().ok().and_then(Number::from_f64).ok_or("解密后的浮点数无效")?),
+            "bool" => Value::Bool(text.parse().map_err(|_| "解密后的布尔值无效")?),
+            _ => return Err(format!("不支持的值类型: {}", kind).into()),
+        })
+    }
+}
+
+/// 加密配置文件的全部叶子值，键与结构保持可读，顶层追加 `dec` 段
+pub fn encrypt_document(text: &str, format: ConfigFormat, password: &str, kdf: Kdf) -> Result<String, Box<dyn std::error::Error>> {
+    let map = format.parse(text)?;
+    if map.contains_key(STRUCTURED_METADATA_KEY) {
+        return Err(format!("顶层已有 `{}` 段，文件可能已经加密", STRUCTURED_METADATA_KEY).into());
+    }
+
+    let salt = generate_salt();
+    let (keys, master_key) = DocumentKeys::derive(password, &kdf, &salt)?;
+
+    // 逐值加密，同时记录参与整篇 MAC 的叶子
+    let mut leaves = Vec::new();
+    let mut document = Value::Object(map);
+    walk(&mut document, &mut Vec::new(), &mut |path, value| {
+        let path = path_bytes(path);
+        if let Some(encrypted) = keys.encrypt_value(&path, value)? {
+            *value = Value::String(encrypted);
+        }
+        leaves.push((path, leaf_repr(value)));
+        Ok(())
+    })?;
+
+    let mut metadata = DocumentMetadata {
+        version: STRUCTURED_VERSION,
+        kdf,
+        salt,
+        key_check: key_derivation::derive_key_check(&master_key)?,
+        modified: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        mac: Vec::new(),
+    };
+    metadata.mac = keys.document_mac(&metadata, &leaves)?;
+
+    let Value::Object(mut map) = document else { unreachable!() };
+    map.insert(STRUCTURED_METADATA_KEY.to_string(), Value::Object(metadata.to_map()));
+    format.render(map)
+}
+
+/// 验证整篇 MAC 与每个值，返回解密后的树（不含 `dec` 段）
+pub fn decrypt_values(text: &str, format: ConfigFormat, password: &str) -> Result<Map<String, Value>, Box<dyn std::error::Error>> {
+    let mut map = format.parse(text)?;
+    let metadata = match map.remove(STRUCTURED_METADATA_KEY) {
+        Some(Value::Object(metadata)) => DocumentMetadata::from_map(&metadata)?,
+        _ => return Err(format!("缺少 `{}` 段，文件未加密", STRUCTURED_METADATA_KEY).into()),
+    };
+
+    // 先校验口令，再在解密任何值之前验证整篇 MAC
+    let (keys, master_key) = DocumentKeys::derive(password, &metadata.kdf, &metadata.salt)?;
+    key_derivation::verify_key_check(&master_key, &metadata.key_check)?;
+
+    let mut document = Value::Object(map);
+    let mut leaves = Vec::new();
+    walk(&mut document, &mut Vec::new(), &mut |path, value| {
+        if !value.is_null() && !value.as_str().is_some_and(|s| s.starts_with(STRUCTURED_VALUE_PREFIX)) {
+            return Err(format!("未加密的值: {}", path_display(path)).into());
+        }
+        leaves.push((path_bytes(path), leaf_repr(value)));
+        Ok(())
+    })?;
+
+    if !tags_equal(&keys.document_mac(&metadata, &leaves)?, &metadata.mac) {
+        return Err("文档 MAC 验证失败，键、值或结构可能已被篡改".into());
+    }
+
+    walk(&mut document, &mut Vec::new(), &mut |path, value| {
+        if let Some(encrypted) = value.as_str() {
+            *value = keys.decrypt_value(&path_bytes(path), encrypted)
+                .map_err(|e| format!("{}: {}", path_display(path), e))?;
+        }
+        Ok(())
+    })?;
+
+    let Value::Object(map) = document else { unreachable!() };
+    Ok(map)
+}
+
+/// 解密配置文件，输出原格式的明文
+pub fn decrypt_document(text: &str, format: ConfigFormat, password: &str) -> Result<SecretString, Box<dyn std::error::Error>> {
+    let map = decrypt_values(text, format, password)?;
+    Ok(SecretString::from(format.render(map)?))
+}
+
+/// 解密后作为环境变量：顶层键为变量名，字符串原样，其他标量转为文本，嵌套值转为 JSON
+pub fn decrypt_env(text: &str, format: ConfigFormat, password: &str) -> Result<Vec<(String, SecretString)>, Box<dyn std::error::Error>> {
+    let map = decrypt_values(text, format, password)?;
+    Ok(map.into_iter().map(|(key, value)| {
+        let value = match value {
+            Value::String(s) => s,
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        (key, SecretString::from(value))
+    }).collect())
+}
+
+/// 常量时间比较认证标签
+fn tags_equal(expected: &[u8], stored: &[u8]) -> bool {
+    expected.len() == stored.len() && expected.iter().zip(stored).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// 叶子在整篇 MAC 中的表示
+fn leaf_repr(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// toml 的日期时间在通用树中是私有的映射，无法还原类型，直接拒绝
+fn reject_toml_datetime(value: &Value) -> Result<(), Box<dyn std::error::Error>> {
+    match value {
+        Value::Object(map) if map.contains_key("$__toml_private_datetime") => Err("不支持 TOML 日期时间值".into()),
+        Value::Object(map) => map.values().try_for_each(reject_toml_datetime),
+        Value::Array(items) => items.iter().try_for_each(reject_toml_datetime),
+        _ => Ok(()),
+    }
+}
+
+/// 解析 dotenv：`KEY=VALUE`，支持 `export` 前缀、单双引号与 `#` 注释（注释不保留）
+///
+/// `dec` 段在 dotenv 中展开为 `dec_version=...` 等键，因此明文中不能有 `dec_` 开头的变量。
+fn parse_dotenv(text: &str) -> Result<Map<String, Value>, Box<dyn std::error::Error>> {
+    let mut map = Map::new();
+    let mut metadata = Map::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=').ok_or_else(|| format!("dotenv 第 {} 行缺少 `=`", number + 1))?;
+        let (key, value) = (key.trim(), value.trim());
+        if key.is_empty() {
+            return Err(format!("dotenv 第 {} 行缺少键名", number + 1).into());
+        }
+
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            let mut out = String::new();
+            let mut chars = quoted.chars();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => out.push('\n'),
+                        Some('r') => out.push('\r'),
+                        Some('t') => out.push('\t'),
+                        Some(c) => out.push(c),
+                        None => return Err(format!("dotenv 第 {} 行引号未闭合", number + 1).into()),
+                    },
+                    Some(c) => out.push(c),
+                    None => return Err(format!("dotenv 第 {} 行引号未闭合", number + 1).into()),
+                }
+            }
+            out
+        } else if let Some(quoted) = value.strip_prefix('\'') {
+            quoted.split_once('\'').ok_or_else(|| format!("dotenv 第 {} 行引号未闭合", number + 1))?.0.to_string()
+        } else {
+            // 未加引号时，空白后的 `#` 开始注释
+            value.split(" #").next().unwrap_or("").trim_end().to_string()
+        };
+
+        match key.strip_prefix(STRUCTURED_DOTENV_PREFIX) {
+            Some(field) => { metadata.insert(field.to_string(), Value::String(value)); }
+            None => { map.insert(key.to_string(), Value::String(value)); }
+        }
+    }
+
+    if !metadata.is_empty() {
+        map.insert(STRUCTURED_METADATA_KEY.to_string(), Value::Object(metadata));
+    }
+    Ok(map)
+}
+
+/// 输出 dotenv；含空白、引号、`#` 或换行的值加双引号并转义
+fn render_dotenv(map: &Map<String, Value>) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = String::new();
+    let mut push = |key: &str, value: &Value| -> Result<(), Box<dyn std::error::Error>> {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(_) | Value::Bool(_) => value.to_string(),
+            _ => return Err(format!("dotenv 不支持嵌套值: {}", key).into()),
+        };
+
+        out.push_str(key);
+        out.push('=');
+        if value.chars().any(|c| c.is_whitespace() || "\"'#\\".contains(c)) {
+            out.push('"');
+            for c in value.chars() {
+                match c {
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    '\t' => out.push_str("\\t"),
+                    '"' | '\\' => { out.push('\\'); out.push(c); }
+                    _ => out.push(c),
+                }
+            }
+            out.push('"');
+        } else {
+            out.push_str(&value);
+        }
+        out.push('\n');
+        Ok(())
+    };
+
+    for (key, value) in map {
+        match (key.as_str(), value) {
+            (STRUCTURED_METADATA_KEY, Value::Object(metadata)) => {
+                for (field, value) in metadata {
+                    push(&format!("{}{}", STRUCTURED_DOTENV_PREFIX, field), value)?;
+                }
+            }
+            _ => push(key, value)?,
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KDF: Kdf = Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS };
+
+    #[test]
+    fn test_roundtrip_all_formats() {
+        let documents = [
+            (ConfigFormat::Json, "{\n  \"db\": {\n    \"user\": \"app\",\n    \"password\": \"hunter2\",\n    \"port\": 5432\n  },\n  \"ratio\": 0.5,\n  \"debug\": false,\n  \"hosts\": [\n    \"a\",\n    \"b\"\n  ],\n  \"empty\": null\n}\n"),
+            (ConfigFormat::Yaml, "db:\n  user: app\n  password: hunter2\n  port: 5432\nratio: 0.5\ndebug: false\nhosts:\n- a\n- b\n"),
+            (ConfigFormat::Toml, "ratio = 0.5\ndebug = false\nhosts = [\"a\", \"b\"]\n\n[db]\nuser = \"app\"\npassword = \"hunter2\"\nport = 5432\n"),
+            (ConfigFormat::Dotenv, "DB_USER=app\nDB_PASSWORD=\"hunter 2\"\nPORT=5432\n"),
+        ];
+
+        for (format, text) in documents {
+            let encrypted = encrypt_document(text, format, "pw", KDF).unwrap();
+
+            // 键保持可读，值不可见
+            assert!(!encrypted.contains("hunter"), "{:?}: {}", format, encrypted);
+            assert!(encrypted.contains("password") || encrypted.contains("PASSWORD"));
+            assert!(encrypted.contains(STRUCTURED_VALUE_PREFIX));
+
+            let decrypted = decrypt_document(&encrypted, format, "pw").unwrap();
+            assert_eq!(format.parse(decrypted.expose()).unwrap(), format.parse(text).unwrap(), "{:?}", format);
+
+            assert_eq!(decrypt_document(&encrypted, format, "wrong").unwrap_err().to_string(), "密码错误");
+            assert!(encrypt_document(&encrypted, format, "pw", KDF).is_err());
+        }
+    }
+
+    #[test]
+    fn test_tamper_detection() {
+        let text = "{\"a\": \"one\", \"b\": \"two\", \"c\": [1, 2]}";
+        let encrypted = encrypt_document(text, ConfigFormat::Json, "pw", KDF).unwrap();
+        let document: Value = serde_json::from_str(&encrypted).unwrap();
+        let decrypt = |document: &Value| decrypt_values(&document.to_string(), ConfigFormat::Json, "pw");
+        assert!(decrypt(&document).is_ok());
+
+        // 交换两个值
+        let mut swapped = document.clone();
+        let a = swapped["a"].take();
+        swapped["a"] = swapped["b"].take();
+        swapped["b"] = a;
+        assert!(decrypt(&swapped).is_err());
+
+        // 删除、新增与重排
+        let mut removed = document.clone();
+        removed.as_object_mut().unwrap().remove("b");
+        assert!(decrypt(&removed).is_err());
+
+        let mut added = document.clone();
+        added["d"] = document["a"].clone();
+        assert!(decrypt(&added).is_err());
+
+        let mut reordered = document.clone();
+        reordered["c"].as_array_mut().unwrap().reverse();
+        assert!(decrypt(&reordered).is_err());
+
+        // 明文值混入
+        let mut plain = document.clone();
+        plain["a"] = Value::from("one");
+        assert_eq!(decrypt(&plain).unwrap_err().to_string(), "未加密的值: a");
+
+        // 元数据被改动
+        let mut modified = document.clone();
+        modified["dec"]["modified"] = Value::from(0);
+        assert!(decrypt(&modified).is_err());
+    }
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(ConfigFormat::from_path("config/app.json"), Some(ConfigFormat::Json));
+        assert_eq!(ConfigFormat::from_path("secrets.enc.yaml"), Some(ConfigFormat::Yaml));
+        assert_eq!(ConfigFormat::from_path("a.yml"), Some(ConfigFormat::Yaml));
+        assert_eq!(ConfigFormat::from_path("Cargo.toml"), Some(ConfigFormat::Toml));
+        assert_eq!(ConfigFormat::from_path(".env"), Some(ConfigFormat::Dotenv));
+        assert_eq!(ConfigFormat::from_path(".env.production"), Some(ConfigFormat::Dotenv));
+        assert_eq!(ConfigFormat::from_path("prod.env"), Some(ConfigFormat::Dotenv));
+        assert_eq!(ConfigFormat::from_path("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_dotenv() {
+        let map = parse_dotenv("# comment\nexport A=1\nB = 'single # quoted'\nC=\"line\\nbreak\"\nD=value # trailing\nE=\n").unwrap();
+        assert_eq!(map["A"], "1");
+        assert_eq!(map["B"], "single # quoted");
+        assert_eq!(map["C"], "line\nbreak");
+        assert_eq!(map["D"], "value");
+        assert_eq!(map["E"], "");
+        assert_eq!(parse_dotenv(&render_dotenv(&map).unwrap()).unwrap(), map);
+
+        assert!(parse_dotenv("NOVALUE\n").is_err());
+        assert!(parse_dotenv("A=\"open\n").is_err());
+
+        // 环境变量保持声明顺序
+        let encrypted = encrypt_document("Z=1\nA=two\n", ConfigFormat::Dotenv, "pw", KDF).unwrap();
+        assert!(encrypted.lines().any(|l| l.starts_with("dec_mac=")));
+        let env = decrypt_env(&encrypted, ConfigFormat::Dotenv, "pw").unwrap();
+        let env: Vec<(&str, &str)> = env.iter().map(|(k, v)| (k.as_str(), v.expose())).collect();
+        assert_eq!(env, [("Z", "1"), ("A", "two")]);
+    }
+
+    #[test]
+    fn test_toml_datetime_rejected() {
+        assert!(encrypt_document("when = 1979-05-27T07:32:00Z\n", ConfigFormat::Toml, "pw", KDF).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::armor::{self, ArmorWriter};
+use crate::parity::{self, ParityWriter};
+use crate::volume;
+use crate::crypto_utils::*;
+use crate::decryptor;
+use crate::encryptor::{EncryptOptions, PayloadWriter};
+use crate::header::Header;
+use crate::key_derivation::PasswordNormalization;
+
+/// 升级结果
+#[derive(Debug, PartialEq)]
+pub enum UpgradeOutcome {
+    /// 已从指定版本重新加密为当前版本
+    Upgraded { from_version: u8 },
+    /// 已是当前版本且参数一致，未改动
+    UpToDate,
+}
+
+/// 判断文件是否已是当前格式与目标参数
+fn is_up_to_date(header: &Header, options: &EncryptOptions) -> bool {
+    header.version == VERSION_SIGN
+        && header.kdf == options.kdf
+        && header.key_check.is_some()
+        && header.normalization == PasswordNormalization::Nfc
+        && header.compression == options.compression
+        && header.padding == options.padding
+        && header.digest == options.digest
+}
+
+/// 将旧格式文件就地重新加密为当前格式
+///
+/// 在同目录的临时文件中流式解密并重新加密，新文件通过校验后才原子替换原文件；
+/// 任何一步失败都不会改动原文件。`new_password` 为 `None` 时沿用原密码。
+/// `options` 中未指定的压缩、填充与明文摘要沿用原文件的设置。
+pub fn upgrade_file(
+    input_file_path: &str,
+    password: &str,
+    new_password: Option<&str>,
+    options: &EncryptOptions,
+    force: bool,
+) -> Result<UpgradeOutcome, Box<dyn std::error::Error>> {
+    let input_path = Path::new(input_file_path);
+
+    if !input_path.exists() || !input_path.is_file() {
+        return Err(format!("输入文件不存在: {}", input_file_path).into());
+    }
+    if volume::is_volume(input_path, &mut File::open(input_path)?)? {
+        return Err("分卷文件不能原地改写".into());
+    }
+
+    // 读取原文件头
+    let (mut reader, _, armored) = armor::open_quietly(input_path)?;
+    let (header, _) = Header::read(&mut reader)?;
+    let options = &EncryptOptions {
+        compression: options.compression.or(header.compression),
+        padding: options.padding.or(header.padding),
+        digest: options.digest.or(header.digest),
+        ..options.clone()
+    };
+    if !force && new_password.is_none() && is_up_to_date(&header, options) {
+        return Ok(UpgradeOutcome::UpToDate);
+    }
+
+    let new_password = new_password.unwrap_or(password);
+
+    // 在同一目录创建临时文件，保证最终的重命名是原子的
+    let dir = input_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+
+    // 解密原文件，同时以当前格式重新加密写入临时文件；原文件 HMAC 验证失败时放弃
+    // 原文件的元数据块、ASCII 封装与纠错封装原样保留
+    {
+        let writer = BufWriter::with_capacity(BUFFER_SIZE, temp_file.as_file_mut());
+        if armored {
+            reencrypt(input_file_path, password, new_password, options, ArmorWriter::new(writer)?)?.finish()?.flush()?;
+        } else if let Some(parity) = parity::detect(input_path)? {
+            reencrypt(input_file_path, password, new_password, options, ParityWriter::new(writer, parity)?)?.finish()?.flush()?;
+        } else {
+            reencrypt(input_file_path, password, new_password, options, writer)?.flush()?;
+        }
+    }
+    temp_file.as_file().sync_all()?;
+
+    // 新文件通过校验后才替换原文件
+    let temp_path = temp_file.path().to_str().ok_or("临时文件路径无效")?.to_string();
+    decryptor::verify_with_password(&temp_path, new_password)
+        .map_err(|e| format!("新文件校验失败，保留原文件: {}", e))?;
+
+    // 保留原文件权限，然后原子替换
+    std::fs::set_permissions(temp_file.path(), input_path.metadata()?.permissions())?;
+    temp_file.persist(input_path).map_err(|e| e.error)?;
+
+    // 同步目录，确保重命名落盘
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+
+    Ok(UpgradeOutcome::Upgraded { from_version: header.version })
+}
+
+/// 解密原文件并以新的密码与参数重新加密写入 `writer`，返回内部写入器
+fn reencrypt<W: Write>(
+    input_file_path: &str,
+    password: &str,
+    new_password: &str,
+    options: &EncryptOptions,
+    writer: W,
+) -> Result<W, Box<dyn std::error::Error>> {
+    let writer = decryptor::decrypt_to_writer(input_file_path, password, |metadata| {
+        PayloadWriter::new(writer, new_password, options, metadata)
+    })?;
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_derivation::Kdf;
+
+    fn light_options() -> EncryptOptions {
+        EncryptOptions { kdf: Kdf::Pbkdf2Sha256 { iterations: PBKDF2_MIN_ITERATIONS }, ..EncryptOptions::default() }
+    }
+
+    #[test]
+    fn test_upgrade_changes_kdf_and_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("plain.txt");
+        let encrypted_path = dir.path().join("plain.txt.decx");
+        let decrypted_path = dir.path().join("out.txt");
+        std::fs::write(&plain_path, b"upgrade me").unwrap();
+
+        let encrypted = encrypted_path.to_str().unwrap();
+        crate::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "old", &light_options()).unwrap();
+
+        // 参数一致时无需升级
+        assert_eq!(upgrade_file(encrypted, "old", None, &light_options(), false).unwrap(), UpgradeOutcome::UpToDate);
+
+        // 更换 KDF 参数与密码
+        let options = EncryptOptions { kdf: Kdf::Scrypt { log_n: 10, r: 8, p: 1 }, ..EncryptOptions::default() };
+        let outcome = upgrade_file(encrypted, "old", Some("new"), &options, false).unwrap();
+        assert_eq!(outcome, UpgradeOutcome::Upgraded { from_version: VERSION_SIGN });
+
+        let (header, _) = Header::read(&mut File::open(&encrypted_path).unwrap()).unwrap();
+        assert_eq!(header.kdf, options.kdf);
+
+        crate::decryptor::decrypt_with_mode(encrypted, decrypted_path.to_str().unwrap(), "new").unwrap();
+        assert_eq!(std::fs::read(&decrypted_path).unwrap(), b"upgrade me");
+    }
+
+    #[test]
+    fn test_upgrade_keeps_compression_padding_and_digest() {
+        use crate::compression::Compression;
+        use crate::digest::DigestAlgorithm;
+        use crate::padding::Padding;
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("plain.txt");
+        let encrypted_path = dir.path().join("plain.txt.decx");
+        std::fs::write(&plain_path, b"hide my length".repeat(100)).unwrap();
+
+        let encrypted = encrypted_path.to_str().unwrap();
+        let options = EncryptOptions {
+            compression: Some(Compression::new(3)),
+            padding: Some(Padding::Bucket(4096)),
+            digest: Some(DigestAlgorithm::Blake3),
+            ..light_options()
+        };
+        crate::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "pw", &options).unwrap();
+
+        // 只给出 KDF 时，其余设置沿用原文件，不算过时
+        assert_eq!(upgrade_file(encrypted, "pw", None, &light_options(), false).unwrap(), UpgradeOutcome::UpToDate);
+
+        let scrypt = EncryptOptions { kdf: Kdf::Scrypt { log_n: 10, r: 8, p: 1 }, ..EncryptOptions::default() };
+        upgrade_file(encrypted, "pw", None, &scrypt, false).unwrap();
+        let (header, _) = Header::read(&mut File::open(&encrypted_path).unwrap()).unwrap();
+        assert_eq!(header.kdf, scrypt.kdf);
+        assert_eq!(header.compression, options.compression);
+        assert_eq!(header.padding, options.padding);
+        assert_eq!(header.digest, options.digest);
+
+        // 明确指定时改用新的设置
+        let repadded = EncryptOptions { padding: Some(Padding::Bucket(8192)), ..scrypt };
+        upgrade_file(encrypted, "pw", None, &repadded, false).unwrap();
+        let (header, _) = Header::read(&mut File::open(&encrypted_path).unwrap()).unwrap();
+        assert_eq!(header.padding, repadded.padding);
+        assert_eq!(header.digest, options.digest);
+        assert_eq!(crate::info::inspect(encrypted).unwrap().ciphertext_length % 8192, 0);
+    }
+
+    #[test]
+    fn test_upgrade_keeps_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("notes.txt");
+        let encrypted_path = dir.path().join("notes.txt.decx");
+        std::fs::write(&plain_path, b"named").unwrap();
+
+        let encrypted = encrypted_path.to_str().unwrap();
+        let options = EncryptOptions { metadata: true, ..light_options() };
+        crate::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "pw", &options).unwrap();
+        std::fs::remove_file(&plain_path).unwrap();
+
+        upgrade_file(encrypted, "pw", Some("new"), &light_options(), false).unwrap();
+        let (header, _) = Header::read(&mut File::open(&encrypted_path).unwrap()).unwrap();
+        assert!(header.metadata);
+
+        let restored = decryptor::decrypt_restoring_name(encrypted, "new").unwrap();
+        assert_eq!(restored, plain_path);
+        assert_eq!(std::fs::read(&plain_path).unwrap(), b"named");
+    }
+
+    #[test]
+    fn test_upgrade_wrong_password_keeps_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("plain.txt");
+        let encrypted_path = dir.path().join("plain.txt.decx");
+        std::fs::write(&plain_path, b"keep me").unwrap();
+
+        let encrypted = encrypted_path.to_str().unwrap();
+        crate::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "right", &light_options()).unwrap();
+        let before = std::fs::read(&encrypted_path).unwrap();
+
+        assert!(upgrade_file(encrypted, "wrong", None, &light_options(), true).is_err());
+        assert_eq!(std::fs::read(&encrypted_path).unwrap(), before);
+
+        // 不留下临时文件
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
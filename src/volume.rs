@@ -0,0 +1,430 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::crypto_utils::*;
+
+/// 第 `index` 卷的文件名：`base.001`、`base.002`……
+pub fn volume_path(base: &str, index: u32) -> String {
+    format!("{}.{:03}", base, index)
+}
+
+/// 从分卷文件名中拆出公共部分与卷号，如 `file.decx.002` → (`file.decx`, 2)
+pub fn split_volume_name(path: &str) -> Option<(&str, u32)> {
+    let (base, number) = path.rsplit_once('.')?;
+    let valid = number.len() >= 3 && number.bytes().all(|b| b.is_ascii_digit()) && !base.is_empty();
+    valid.then(|| number.parse().ok().map(|index| (base, index))).flatten()
+}
+
+/// 文件是否为分卷：以卷头魔数开头，或名为 `*.decx.NNN`（第一卷的魔数损坏时仍按分卷读取以便报告）
+pub fn is_volume(path: &Path, file: &mut File) -> io::Result<bool> {
+    let mut magic = [0u8; 4];
+    let found = file.read_exact(&mut magic).is_ok() && &magic == VOLUME_MAGIC;
+    file.rewind()?;
+
+    let named = path.to_str().and_then(split_volume_name).is_some_and(|(base, _)| base.ends_with(".decx"));
+    Ok(found || named)
+}
+
+fn volume_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// 卷头：把各卷连成一组，并记录本卷内容的 BLAKE3 以便指出损坏的卷
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VolumeHeader {
+    last: bool,
+    set_id: [u8; VOLUME_SET_ID_LENGTH],
+    index: u32,
+    length: u64,
+    hash: [u8; VOLUME_HASH_LENGTH],
+}
+
+impl VolumeHeader {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = VOLUME_MAGIC.to_vec();
+        bytes.push(VOLUME_VERSION);
+        bytes.push(if self.last { VOLUME_FLAG_LAST } else { 0 });
+        bytes.extend_from_slice(&self.set_id);
+        bytes.extend_from_slice(&self.index.to_le_bytes());
+        bytes.extend_from_slice(&self.length.to_le_bytes());
+        bytes.extend_from_slice(&self.hash);
+        let sum = blake3::hash(&bytes);
+        bytes.extend_from_slice(&sum.as_bytes()[..VOLUME_CHECKSUM_LENGTH]);
+        bytes
+    }
+
+    fn decode(bytes: &[u8; VOLUME_HEADER_LENGTH]) -> Option<Self> {
+        let (fields, sum) = bytes.split_at(VOLUME_HEADER_LENGTH - VOLUME_CHECKSUM_LENGTH);
+        if &blake3::hash(fields).as_bytes()[..VOLUME_CHECKSUM_LENGTH] != sum
+            || &fields[..4] != VOLUME_MAGIC || fields[4] != VOLUME_VERSION {
+            return None;
+        }
+        Some(Self {
+            last: fields[5] & VOLUME_FLAG_LAST != 0,
+            set_id: fields[6..22].try_into().unwrap(),
+            index: u32::from_le_bytes(fields[22..26].try_into().unwrap()),
+            length: u64::from_le_bytes(fields[26..34].try_into().unwrap()),
+            hash: fields[34..66].try_into().unwrap(),
+        })
+    }
+}
+
+/// 分卷写入器：写满一卷后开始下一卷；每卷写完后回到开头写入卷头并落盘
+pub struct VolumeWriter {
+    base: String,
+    volume_size: u64,
+    set_id: [u8; VOLUME_SET_ID_LENGTH],
+    index: u32,
+    file: File,
+    hasher: blake3::Hasher,
+    written: u64,
+}
+
+impl VolumeWriter {
+    /// 创建第一卷；`volume_size` 为每卷文件的大小（含卷头）
+    pub fn create(base: &str, volume_size: u64) -> io::Result<Self> {
+        if volume_size < VOLUME_MIN_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("分卷不能小于 {} 字节", VOLUME_MIN_SIZE)));
+        }
+        let mut set_id = [0u8; VOLUME_SET_ID_LENGTH];
+        SystemRandom::new().fill(&mut set_id).map_err(|_| io::Error::other("无法生成随机数据"))?;
+
+        Ok(Self {
+            base: base.to_string(),
+            volume_size,
+            set_id,
+            index: 1,
+            file: Self::open_volume(base, 1)?,
+            hasher: blake3::Hasher::new(),
+            written: 0,
+        })
+    }
+
+    /// 创建卷文件并预留卷头
+    fn open_volume(base: &str, index: u32) -> io::Result<File> {
+        let mut file = File::create(volume_path(base, index))?;
+        file.write_all(&[0u8; VOLUME_HEADER_LENGTH])?;
+        Ok(file)
+    }
+
+    /// 写入当前卷的卷头并落盘
+    fn close_volume(&mut self, last: bool) -> io::Result<()> {
+        let header = VolumeHeader {
+            last,
+            set_id: self.set_id,
+            index: self.index,
+            length: self.written,
+            hash: self.hasher.finalize().into(),
+        };
+        self.file.rewind()?;
+        self.file.write_all(&header.encode())?;
+        self.file.sync_all()
+    }
+
+    /// 结束最后一卷，返回卷数
+    pub fn finish(mut self) -> io::Result<u32> {
+        self.close_volume(true)?;
+        Ok(self.index)
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let capacity = self.volume_size - VOLUME_HEADER_LENGTH as u64;
+
+        // 还有数据要写时才开始下一卷，最后一卷总是以 finish 结束
+        if self.written == capacity && !buf.is_empty() {
+            self.close_volume(false)?;
+            self.index = self.index.checked_add(1).ok_or_else(|| io::Error::other("分卷过多"))?;
+            self.file = Self::open_volume(&self.base, self.index)?;
+            self.hasher = blake3::Hasher::new();
+            self.written = 0;
+        }
+
+        let count = std::cmp::min(buf.len() as u64, capacity - self.written) as usize;
+        self.file.write_all(&buf[..count])?;
+        self.hasher.update(&buf[..count]);
+        self.written += count as u64;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// 组内的一卷
+struct Volume {
+    path: String,
+    file: File,
+    /// 本卷内容在整个流中的起点
+    offset: u64,
+    length: u64,
+    hash: [u8; VOLUME_HASH_LENGTH],
+    hasher: blake3::Hasher,
+    /// 已连续计入摘要的长度，读到卷尾时比对
+    hashed: u64,
+}
+
+impl Volume {
+    /// 把从 `start` 起读到的数据计入摘要；整卷读完且与卷头中的摘要不符时返回 true
+    fn track(&mut self, start: u64, data: &[u8]) -> bool {
+        let end = start + data.len() as u64;
+        if start <= self.hashed && self.hashed < end {
+            self.hasher.update(&data[(self.hashed - start) as usize..]);
+            self.hashed = end;
+            return self.hashed == self.length && self.hasher.finalize().as_bytes() != &self.hash;
+        }
+        false
+    }
+}
+
+/// 分卷读取器：按卷号依次打开整组分卷，作为一个连续的流读取与定位
+///
+/// 打开时检查缺失、顺序错乱、混入其他组与截断的卷；顺序读完一卷时比对该卷内容的摘要。
+pub struct VolumeReader {
+    volumes: Vec<Volume>,
+    length: u64,
+    position: u64,
+    /// 卷内容损坏时只报告、不中止（由内层的纠错封装修复）
+    tolerant: bool,
+    report: bool,
+}
+
+impl VolumeReader {
+    /// 由任意一卷的文件名找到整组分卷
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let name = path.to_str().unwrap_or_default();
+        let (base, _) = split_volume_name(name)
+            .ok_or_else(|| volume_error(format!("无法从文件名确定卷号: {}", name)))?;
+
+        let mut volumes = Vec::new();
+        let mut set_id = None;
+        let mut offset = 0;
+        let mut index = 1;
+        loop {
+            let path = volume_path(base, index);
+            let mut file = File::open(&path).map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => volume_error(format!("缺少第 {} 卷: {}", index, path)),
+                _ => e,
+            })?;
+
+            let mut raw = [0u8; VOLUME_HEADER_LENGTH];
+            let header = file.read_exact(&mut raw).ok().and_then(|_| VolumeHeader::decode(&raw))
+                .ok_or_else(|| volume_error(format!("第 {} 卷的卷头损坏: {}", index, path)))?;
+
+            if *set_id.get_or_insert(header.set_id) != header.set_id {
+                return Err(volume_error(format!("{} 属于另一组分卷", path)));
+            }
+            if header.index != index {
+                return Err(volume_error(format!("分卷顺序错乱: {} 是第 {} 卷", path, header.index)));
+            }
+            if file.metadata()?.len() != VOLUME_HEADER_LENGTH as u64 + header.length {
+                return Err(volume_error(format!("第 {} 卷长度不符，可能被截断: {}", index, path)));
+            }
+
+            volumes.push(Volume {
+                path, file, offset,
+                length: header.length,
+                hash: header.hash,
+                hasher: blake3::Hasher::new(),
+                hashed: 0,
+            });
+            offset += header.length;
+
+            if header.last {
+                break;
+            }
+            index = index.checked_add(1).ok_or_else(|| volume_error("分卷过多".to_string()))?;
+        }
+
+        Ok(Self { volumes, length: offset, position: 0, tolerant: false, report: false })
+    }
+
+    /// 内层带纠错封装时，损坏的卷交给纠错数据修复：`report` 为 true 时在标准错误报告，继续读取
+    pub fn tolerate_damage(&mut self, report: bool) {
+        self.tolerant = true;
+        self.report = report;
+    }
+
+    /// 卷数
+    pub fn count(&self) -> usize {
+        self.volumes.len()
+    }
+
+    /// 各卷的文件名，按卷号排列
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.volumes.iter().map(|volume| volume.path.as_str())
+    }
+
+    /// 写出这组分卷时的每卷大小：除最后一卷外各卷都是满的，只有一卷时取其大小
+    pub fn volume_size(&self) -> u64 {
+        std::cmp::max(VOLUME_HEADER_LENGTH as u64 + self.volumes[0].length, VOLUME_MIN_SIZE)
+    }
+
+    /// 读完全部分卷，比对每一卷内容的摘要（不需要密码）
+    pub fn check(mut self) -> io::Result<()> {
+        self.rewind()?;
+        io::copy(&mut self, &mut io::sink())?;
+        Ok(())
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.length {
+            return Ok(0);
+        }
+
+        let index = self.volumes.partition_point(|v| v.offset + v.length <= self.position);
+        let volume = &mut self.volumes[index];
+        let start = self.position - volume.offset;
+        let count = std::cmp::min(buf.len() as u64, volume.length - start) as usize;
+
+        volume.file.seek(SeekFrom::Start(VOLUME_HEADER_LENGTH as u64 + start))?;
+        let count = volume.file.read(&mut buf[..count])?;
+        if count == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("第 {} 卷被截断: {}", index + 1, volume.path)));
+        }
+        if volume.track(start, &buf[..count]) {
+            if !self.tolerant {
+                return Err(volume_error(format!("第 {} 卷已损坏: {}", index + 1, volume.path)));
+            }
+            if self.report {
+                eprintln!("DEC!: volume {} is damaged, repairing from parity: {}", index + 1, volume.path);
+            }
+        }
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.length.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek"))?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_set(dir: &Path, data: &[u8], volume_size: u64) -> String {
+        let base = dir.join("data.decx").to_str().unwrap().to_string();
+        let mut writer = VolumeWriter::create(&base, volume_size).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+        base
+    }
+
+    fn read_set(path: &str) -> io::Result<Vec<u8>> {
+        let mut reader = VolumeReader::open(Path::new(path))?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn sample(length: usize) -> Vec<u8> {
+        (0..length).map(|i| (i * 13 + i / 255) as u8).collect()
+    }
+
+    #[test]
+    fn test_volume_name() {
+        assert_eq!(split_volume_name("a/file.decx.001"), Some(("a/file.decx", 1)));
+        assert_eq!(split_volume_name("file.decx.1234"), Some(("file.decx", 1234)));
+        assert_eq!(split_volume_name("file.decx.01"), None);
+        assert_eq!(split_volume_name("file.decx"), None);
+        assert_eq!(volume_path("file.decx", 12), "file.decx.012");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let capacity = VOLUME_MIN_SIZE as usize - VOLUME_HEADER_LENGTH;
+        for (length, count) in [(0, 1), (100, 1), (capacity, 1), (capacity + 1, 2), (3 * capacity + 7, 4)] {
+            let dir = tempfile::tempdir().unwrap();
+            let data = sample(length);
+            let base = write_set(dir.path(), &data, VOLUME_MIN_SIZE);
+
+            // 从任意一卷打开都读出整组
+            let reader = VolumeReader::open(Path::new(&volume_path(&base, count))).unwrap();
+            assert_eq!(reader.count(), count as usize);
+            assert_eq!(read_set(&volume_path(&base, 1)).unwrap(), data);
+            assert!(!Path::new(&volume_path(&base, count + 1)).exists());
+            for index in 1..count {
+                assert_eq!(std::fs::metadata(volume_path(&base, index)).unwrap().len(), VOLUME_MIN_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_seek() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = sample(20_000);
+        let base = write_set(dir.path(), &data, VOLUME_MIN_SIZE);
+        let mut reader = VolumeReader::open(Path::new(&volume_path(&base, 1))).unwrap();
+
+        for offset in [0usize, 4000, 4021, 4022, 12_345, 19_990] {
+            reader.seek(SeekFrom::Start(offset as u64)).unwrap();
+            let mut buf = [0u8; 10];
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, &data[offset..offset + 10]);
+        }
+        assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), data.len() as u64);
+    }
+
+    #[test]
+    fn test_damaged_sets() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = sample(15_000);
+        let base = write_set(dir.path(), &data, VOLUME_MIN_SIZE);
+        let first = volume_path(&base, 1);
+        let error = |path: &str| read_set(path).unwrap_err().to_string();
+
+        // 内容损坏：指出是哪一卷
+        let third = volume_path(&base, 3);
+        let original = std::fs::read(&third).unwrap();
+        let mut damaged = original.clone();
+        damaged[VOLUME_HEADER_LENGTH + 10] ^= 1;
+        std::fs::write(&third, &damaged).unwrap();
+        assert!(error(&first).contains(&format!("第 3 卷已损坏: {}", third)));
+        std::fs::write(&third, &original).unwrap();
+        VolumeReader::open(Path::new(&first)).unwrap().check().unwrap();
+
+        // 卷头损坏
+        let mut damaged = original.clone();
+        damaged[30] ^= 1;
+        std::fs::write(&third, &damaged).unwrap();
+        assert!(error(&first).contains("第 3 卷的卷头损坏"));
+        std::fs::write(&third, &original).unwrap();
+
+        // 顺序错乱
+        let second = volume_path(&base, 2);
+        std::fs::rename(&second, dir.path().join("tmp")).unwrap();
+        std::fs::rename(&third, &second).unwrap();
+        std::fs::rename(dir.path().join("tmp"), &third).unwrap();
+        assert!(error(&first).contains("分卷顺序错乱"));
+        std::fs::rename(&second, dir.path().join("tmp")).unwrap();
+        std::fs::rename(&third, &second).unwrap();
+        std::fs::rename(dir.path().join("tmp"), &third).unwrap();
+        assert_eq!(read_set(&first).unwrap(), data);
+
+        // 缺少一卷、截断
+        std::fs::remove_file(&third).unwrap();
+        assert!(error(&first).contains("缺少第 3 卷"));
+        std::fs::write(&third, &original[..original.len() - 1]).unwrap();
+        assert!(error(&first).contains("第 3 卷长度不符"));
+
+        // 混入另一组的同号卷
+        let other = tempfile::tempdir().unwrap();
+        let other_base = write_set(other.path(), &data, VOLUME_MIN_SIZE);
+        std::fs::copy(volume_path(&other_base, 3), &third).unwrap();
+        assert!(error(&first).contains("属于另一组分卷"));
+    }
+}
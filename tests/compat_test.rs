@@ -0,0 +1,88 @@
+// tests/compat_test.rs
+// 每个格式版本都在 `tests/fixtures` 中保留至少一个样本文件，保证旧文件始终可以解密
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use tempfile::NamedTempFile;
+
+    const FIXTURE_PASSWORD: &str = "dec-fixture";
+
+    fn fixtures_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures")
+    }
+
+    fn fixtures() -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+            .expect("Failed to read fixtures dir")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "decx"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_every_format_has_fixture() {
+        let versions: HashSet<u8> = fixtures().iter()
+            .map(|path| dec::info::inspect(path.to_str().unwrap()).unwrap().header.version)
+            .collect();
+
+        for format in dec::format::FORMATS.iter() {
+            assert!(versions.contains(&format.version), "missing fixture for format v{}", format.version);
+        }
+    }
+
+    #[test]
+    fn test_decrypt_fixtures() {
+        let expected = std::fs::read(fixtures_dir().join("plain.txt")).unwrap();
+
+        for fixture in fixtures() {
+            let fixture_path = fixture.to_str().unwrap();
+            let decrypted_file = NamedTempFile::new().unwrap();
+            let decrypted_path = decrypted_file.path().to_str().unwrap();
+
+            dec::decryptor::check_version(fixture_path)
+                .unwrap_or_else(|e| panic!("{}: {}", fixture_path, e));
+            dec::decryptor::decrypt_with_mode(fixture_path, decrypted_path, FIXTURE_PASSWORD)
+                .unwrap_or_else(|e| panic!("{}: {}", fixture_path, e));
+            assert_eq!(std::fs::read(decrypted_path).unwrap(), expected, "{}", fixture_path);
+
+            dec::decryptor::verify_with_password(fixture_path, FIXTURE_PASSWORD)
+                .unwrap_or_else(|e| panic!("{}: {}", fixture_path, e));
+        }
+    }
+
+    #[test]
+    fn test_upgrade_fixtures() {
+        use dec::encryptor::EncryptOptions;
+        use dec::key_derivation::Kdf;
+        use dec::upgrader::{upgrade_file, UpgradeOutcome};
+
+        let expected = std::fs::read(fixtures_dir().join("plain.txt")).unwrap();
+        let options = EncryptOptions { kdf: Kdf::Pbkdf2Sha256 { iterations: 1000 }, ..EncryptOptions::default() };
+
+        for fixture in fixtures() {
+            // 在副本上升级，保留原样本
+            let dir = tempfile::tempdir().unwrap();
+            let upgraded = dir.path().join(fixture.file_name().unwrap());
+            std::fs::copy(&fixture, &upgraded).unwrap();
+            let upgraded_path = upgraded.to_str().unwrap();
+
+            let from_version = dec::info::inspect(upgraded_path).unwrap().header.version;
+            let outcome = upgrade_file(upgraded_path, FIXTURE_PASSWORD, None, &options, false)
+                .unwrap_or_else(|e| panic!("{}: {}", fixture.display(), e));
+            assert_eq!(outcome, UpgradeOutcome::Upgraded { from_version });
+
+            let header = dec::info::inspect(upgraded_path).unwrap().header;
+            assert_eq!(header.version, dec::crypto_utils::VERSION_SIGN);
+            assert_eq!(header.kdf, options.kdf);
+            assert!(header.key_check.is_some());
+
+            let decrypted_file = NamedTempFile::new().unwrap();
+            let decrypted_path = decrypted_file.path().to_str().unwrap();
+            dec::decryptor::decrypt_with_mode(upgraded_path, decrypted_path, FIXTURE_PASSWORD).unwrap();
+            assert_eq!(std::fs::read(decrypted_path).unwrap(), expected, "{}", fixture.display());
+        }
+    }
+}
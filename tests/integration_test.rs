@@ -27,7 +27,8 @@ mod tests {
         let encrypt_result = dec::encryptor::encrypt_with_mode(
             &input_path,
             &encrypted_path,
-            &password
+            &password,
+            &dec::encryptor::EncryptOptions::default()
         );
 
         assert!(encrypt_result.is_ok(), "Encryption failed: {:?}", encrypt_result.err());
@@ -62,4 +63,758 @@ mod tests {
         println!("Encryption speed: {:.2} MB/s", 500.0 / encrypt_duration.as_secs_f64());
         println!("Decryption speed: {:.2} MB/s", 500.0 / decrypt_duration.as_secs_f64());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_alternative_kdf_roundtrip() {
+        use dec::key_derivation::Kdf;
+
+        let test_data: Vec<u8> = (0..300 * 1024).map(|i| (i % 251) as u8).collect();
+        let password = "Password123!";
+
+        let mut input_file = NamedTempFile::new().unwrap();
+        input_file.write_all(&test_data).unwrap();
+        let input_path = input_file.path().to_str().unwrap();
+
+        for kdf in [Kdf::Scrypt { log_n: 12, r: 8, p: 1 }, Kdf::Pbkdf2Sha256 { iterations: 1000 }] {
+            let encrypted_file = NamedTempFile::new().unwrap();
+            let encrypted_path = encrypted_file.path().to_str().unwrap();
+            let decrypted_file = NamedTempFile::new().unwrap();
+            let decrypted_path = decrypted_file.path().to_str().unwrap();
+
+            let options = dec::encryptor::EncryptOptions { kdf, ..Default::default() };
+            dec::encryptor::encrypt_with_mode(input_path, encrypted_path, password, &options).unwrap();
+
+            // KDF 参数记录在文件头中，解密时无需指定
+            let (header, _) = dec::header::Header::read(&mut std::fs::File::open(encrypted_path).unwrap()).unwrap();
+            assert_eq!(header.kdf, kdf);
+
+            dec::decryptor::decrypt_with_mode(encrypted_path, decrypted_path, password).unwrap();
+            assert_eq!(std::fs::read(decrypted_path).unwrap(), test_data);
+
+            // 错误密码应当失败
+            assert!(dec::decryptor::decrypt_with_mode(encrypted_path, decrypted_path, "wrong").is_err());
+        }
+    }
+
+    #[test]
+    fn test_wrong_password_rejected_before_output() {
+        use dec::key_derivation::Kdf;
+
+        let mut input_file = NamedTempFile::new().unwrap();
+        input_file.write_all(b"key check test data").unwrap();
+        let input_path = input_file.path().to_str().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let encrypted_path = dir.path().join("data.decx");
+        let decrypted_path = dir.path().join("data");
+
+        let options = dec::encryptor::EncryptOptions { kdf: Kdf::Pbkdf2Sha256 { iterations: 1000 }, ..Default::default() };
+        dec::encryptor::encrypt_with_mode(input_path, encrypted_path.to_str().unwrap(), "right", &options).unwrap();
+
+        // 错误密码在口令校验阶段即失败，不会创建输出文件
+        let result = dec::decryptor::decrypt_with_mode(
+            encrypted_path.to_str().unwrap(),
+            decrypted_path.to_str().unwrap(),
+            "wrong"
+        );
+        assert_eq!(result.unwrap_err().to_string(), "密码错误");
+        assert!(!decrypted_path.exists());
+    }
+
+    #[test]
+    fn test_verify_without_decrypting() {
+        use dec::key_derivation::Kdf;
+
+        let mut input_file = NamedTempFile::new().unwrap();
+        input_file.write_all(&vec![42u8; 100 * 1024]).unwrap();
+        let input_path = input_file.path().to_str().unwrap();
+
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path().to_str().unwrap();
+
+        let options = dec::encryptor::EncryptOptions { kdf: Kdf::Pbkdf2Sha256 { iterations: 1000 }, ..Default::default() };
+        dec::encryptor::encrypt_with_mode(input_path, encrypted_path, "pw", &options).unwrap();
+
+        assert!(dec::decryptor::verify_with_password(encrypted_path, "pw").is_ok());
+        assert!(dec::decryptor::verify_with_password(encrypted_path, "bad").is_err());
+
+        // 翻转一个密文位后校验失败
+        let mut bytes = std::fs::read(encrypted_path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        std::fs::write(encrypted_path, &bytes).unwrap();
+        assert!(dec::decryptor::verify_with_password(encrypted_path, "pw").is_err());
+    }
+
+    #[test]
+    fn test_password_unicode_normalization() {
+        let options = dec::encryptor::EncryptOptions { kdf: dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 }, ..Default::default() };
+
+        let mut input_file = NamedTempFile::new().unwrap();
+        input_file.write_all(b"normalized").unwrap();
+        let input_path = input_file.path().to_str().unwrap();
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path().to_str().unwrap();
+
+        // 以 NFD 形式加密（如 macOS 输入法），以 NFC 形式解密
+        dec::encryptor::encrypt_with_mode(input_path, encrypted_path, "cafe\u{301}-密码", &options).unwrap();
+        assert!(dec::decryptor::verify_with_password(encrypted_path, "caf\u{e9}-密码").is_ok());
+    }
+
+    #[test]
+    fn test_compressed_roundtrip() {
+        use dec::compression::Compression;
+
+        // 可压缩的日志样式数据，跨越多个压缩块
+        let test_data: Vec<u8> = (0..200_000)
+            .flat_map(|i| format!("2026-01-01T00:00:{:02}Z INFO request {} ok\n", i % 60, i % 1000).into_bytes())
+            .collect();
+
+        let mut input_file = NamedTempFile::new().unwrap();
+        input_file.write_all(&test_data).unwrap();
+        let input_path = input_file.path().to_str().unwrap();
+        let encrypted_file = NamedTempFile::new().unwrap();
+        let encrypted_path = encrypted_file.path().to_str().unwrap();
+        let decrypted_file = NamedTempFile::new().unwrap();
+        let decrypted_path = decrypted_file.path().to_str().unwrap();
+
+        let options = dec::encryptor::EncryptOptions {
+            kdf: dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 },
+            compression: Some(Compression::new(3)),
+            ..Default::default()
+        };
+        dec::encryptor::encrypt_with_mode(input_path, encrypted_path, "pw", &options).unwrap();
+        assert!(std::fs::metadata(encrypted_path).unwrap().len() < test_data.len() as u64 / 5);
+
+        let info = dec::info::inspect(encrypted_path).unwrap();
+        assert_eq!(info.header.compression, options.compression);
+        assert_eq!(info.plaintext_length, None);
+
+        dec::decryptor::decrypt_with_mode(encrypted_path, decrypted_path, "pw").unwrap();
+        assert_eq!(std::fs::read(decrypted_path).unwrap(), test_data);
+        assert!(dec::decryptor::verify_with_password(encrypted_path, "pw").is_ok());
+    }
+
+    #[test]
+    fn test_padded_roundtrip() {
+        use dec::compression::Compression;
+        use dec::padding::Padding;
+
+        let dir = tempfile::tempdir().unwrap();
+        let kdf = dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 };
+
+        for (length, padding, compression) in [
+            (0usize, Padding::Padme, None),
+            (1000, Padding::Bucket(4096), None),
+            (5000, Padding::Bucket(4096), None),
+            (300_000, Padding::Padme, Some(Compression::new(3))),
+            (123, Padding::Fixed(64 * 1024), None),
+        ] {
+            let test_data: Vec<u8> = (0..length).map(|i| (i % 97) as u8).collect();
+            let input_path = dir.path().join("plain");
+            let encrypted_path = dir.path().join("plain.decx");
+            let decrypted_path = dir.path().join("plain.out");
+            std::fs::write(&input_path, &test_data).unwrap();
+
+            let options = dec::encryptor::EncryptOptions { kdf, compression, padding: Some(padding), ..Default::default() };
+            dec::encryptor::encrypt_with_mode(input_path.to_str().unwrap(), encrypted_path.to_str().unwrap(), "pw", &options).unwrap();
+
+            // 填充后的主体长度符合方案
+            let info = dec::info::inspect(encrypted_path.to_str().unwrap()).unwrap();
+            assert_eq!(info.header.padding, Some(padding));
+            assert_eq!(info.plaintext_length, None);
+            match padding {
+                Padding::Bucket(size) => assert_eq!(info.ciphertext_length % size, 0),
+                Padding::Fixed(size) => assert_eq!(info.ciphertext_length, size),
+                Padding::Padme => assert!(info.ciphertext_length >= length as u64 + 8 || compression.is_some()),
+            }
+
+            dec::decryptor::decrypt_with_mode(encrypted_path.to_str().unwrap(), decrypted_path.to_str().unwrap(), "pw").unwrap();
+            assert_eq!(std::fs::read(&decrypted_path).unwrap(), test_data, "{} bytes, {}", length, padding);
+        }
+    }
+
+    #[test]
+    fn test_metadata_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let input_path = dir.path().join("report.txt");
+        std::fs::write(&input_path, b"quarterly numbers").unwrap();
+
+        let mtime = std::time::UNIX_EPOCH + std::time::Duration::new(1_600_000_000, 500);
+        std::fs::File::options().write(true).open(&input_path).unwrap().set_modified(mtime).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&input_path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        // 外部文件名随机，原文件名只保存在加密的元数据中
+        let encrypted_path = dir.path().join(dec::metadata::random_file_name());
+        let options = dec::encryptor::EncryptOptions {
+            kdf: dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 },
+            metadata: true,
+            ..Default::default()
+        };
+        dec::encryptor::encrypt_with_mode(input_path.to_str().unwrap(), encrypted_path.to_str().unwrap(), "pw", &options).unwrap();
+        assert!(dec::info::inspect(encrypted_path.to_str().unwrap()).unwrap().header.metadata);
+
+        // 指定输出路径时恢复权限与修改时间
+        let output_path = dir.path().join("elsewhere");
+        let metadata = dec::decryptor::decrypt_with_mode(encrypted_path.to_str().unwrap(), output_path.to_str().unwrap(), "pw").unwrap().unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("report.txt"));
+        assert_eq!(std::fs::metadata(&output_path).unwrap().modified().unwrap(), mtime);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&output_path).unwrap().permissions().mode() & 0o777, 0o640);
+        }
+
+        // 恢复原文件名时不覆盖已有文件
+        assert!(dec::decryptor::decrypt_restoring_name(encrypted_path.to_str().unwrap(), "pw").is_err());
+        std::fs::remove_file(&input_path).unwrap();
+        let restored = dec::decryptor::decrypt_restoring_name(encrypted_path.to_str().unwrap(), "pw").unwrap();
+        assert_eq!(restored, input_path);
+        assert_eq!(std::fs::read(&input_path).unwrap(), b"quarterly numbers");
+
+        // 失败时不留下临时文件
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_armored_roundtrip() {
+        use dec::padding::Padding;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input_path = dir.path().join("token.txt");
+        let armored_path = dir.path().join("token.txt.decx.asc");
+        let decrypted_path = dir.path().join("token.out");
+        let test_data: Vec<u8> = (0..10_000u32).map(|i| (i % 253) as u8).collect();
+        std::fs::write(&input_path, &test_data).unwrap();
+
+        let options = dec::encryptor::EncryptOptions {
+            kdf: dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 },
+            padding: Some(Padding::Bucket(4096)),
+            metadata: true,
+            armor: true,
+            ..Default::default()
+        };
+        let armored = armored_path.to_str().unwrap();
+        dec::encryptor::encrypt_with_mode(input_path.to_str().unwrap(), armored, "pw", &options).unwrap();
+
+        // 纯文本，长度按解码后的二进制计算
+        let text = std::fs::read_to_string(&armored_path).unwrap();
+        assert!(text.starts_with("-----BEGIN DEC ENCRYPTED FILE-----\n"));
+        assert!(text.ends_with("-----END DEC ENCRYPTED FILE-----\n"));
+        let info = dec::info::inspect(armored).unwrap();
+        assert!(info.armored);
+        assert_eq!(info.ciphertext_length % 4096, 0);
+
+        // 解密与校验自动识别封装格式
+        dec::decryptor::verify_with_password(armored, "pw").unwrap();
+        dec::decryptor::decrypt_with_mode(armored, decrypted_path.to_str().unwrap(), "pw").unwrap();
+        assert_eq!(std::fs::read(&decrypted_path).unwrap(), test_data);
+
+        // 升级后仍为封装格式
+        let upgraded = dec::upgrader::upgrade_file(armored, "pw", Some("new"), &options, false).unwrap();
+        assert!(matches!(upgraded, dec::upgrader::UpgradeOutcome::Upgraded { .. }));
+        assert!(dec::info::inspect(armored).unwrap().armored);
+
+        // 改动一个 base64 字符后解密失败
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let replacement = if lines[5].starts_with('A') { "B" } else { "A" };
+        lines[5].replace_range(0..1, replacement);
+        std::fs::write(&armored_path, lines.join("\n") + "\n").unwrap();
+        assert!(dec::decryptor::decrypt_with_mode(armored, decrypted_path.to_str().unwrap(), "pw").is_err());
+
+        // 填充的文件先定位到末尾读取填充长度，跳过的内容仍计入封装的校验和
+        let test_data: Vec<u8> = (0..300_000u32).map(|i| (i % 253) as u8).collect();
+        std::fs::write(&input_path, &test_data).unwrap();
+        dec::encryptor::encrypt_with_mode(input_path.to_str().unwrap(), armored, "pw", &options).unwrap();
+        let mut lines: Vec<String> = std::fs::read_to_string(&armored_path).unwrap().lines().map(str::to_string).collect();
+        let middle = lines.len() / 2;
+        let replacement = if lines[middle].starts_with('A') { "B" } else { "A" };
+        lines[middle].replace_range(0..1, replacement);
+        std::fs::write(&armored_path, lines.join("\n") + "\n").unwrap();
+        let error = dec::decryptor::decrypt_with_mode(armored, decrypted_path.to_str().unwrap(), "pw").unwrap_err();
+        assert!(error.to_string().contains("校验和不匹配"), "{}", error);
+        assert!(dec::decryptor::decrypt_range_to_writer(armored, "pw", Some(0..100), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_sealed_token_roundtrip() {
+        use dec::padding::Padding;
+
+        let options = dec::encryptor::EncryptOptions {
+            kdf: dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 },
+            compression: Some(dec::compression::Compression::new(3)),
+            padding: Some(Padding::Bucket(256)),
+            ..Default::default()
+        };
+
+        // 令牌为单行，可直接放进 .env 或 YAML
+        let token = dec::seal::seal_with_options(b"postgres://user:hunter2@db/prod", "pw", &options).unwrap();
+        assert!(token.starts_with("dec:"));
+        assert!(!token.contains(['\n', ' ', '=', '+', '/']));
+        assert_eq!(dec::seal::open(&token, "pw").unwrap().expose(), b"postgres://user:hunter2@db/prod");
+        assert!(dec::seal::open(&token, "other").is_err());
+
+        // 默认参数的库接口
+        let token = dec::seal::seal(b"hunter2", "pw").unwrap();
+        assert!(token.starts_with("dec:"));
+        assert_eq!(dec::seal::open(&token, "pw").unwrap().expose(), b"hunter2");
+        assert!(dec::seal::open(&token, "other").is_err());
+
+        // 也能打开 --armor 加密的整个文件
+        let dir = tempfile::tempdir().unwrap();
+        let input_path = dir.path().join("secret.txt");
+        let armored_path = dir.path().join("secret.txt.decx.asc");
+        std::fs::write(&input_path, b"armored secret").unwrap();
+        let options = dec::encryptor::EncryptOptions { armor: true, metadata: true, ..options };
+        dec::encryptor::encrypt_with_mode(input_path.to_str().unwrap(), armored_path.to_str().unwrap(), "pw", &options).unwrap();
+        let text = std::fs::read_to_string(&armored_path).unwrap();
+        assert_eq!(dec::seal::open(&text, "pw").unwrap().expose(), b"armored secret");
+    }
+
+    #[test]
+    fn test_structured_config_values() {
+        use dec::structured::{self, ConfigFormat};
+
+        let kdf = dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 };
+        let text = "service:\n  name: billing\n  token: s3cr3t\n  replicas: 3\n  tls: true\nallowed:\n- 10.0.0.1\n- 10.0.0.2\n";
+
+        // 键与结构不变，只有值被替换
+        let encrypted = structured::encrypt_document(text, ConfigFormat::Yaml, "pw", kdf).unwrap();
+        let keys: Vec<&str> = encrypted.lines().filter_map(|l| l.split_once(':').map(|(k, _)| k)).collect();
+        assert_eq!(&keys[..5], ["service", "  name", "  token", "  replicas", "  tls"]);
+        assert!(!encrypted.contains("s3cr3t") && !encrypted.contains("billing"));
+
+        let decrypted = structured::decrypt_document(&encrypted, ConfigFormat::Yaml, "pw").unwrap();
+        assert_eq!(decrypted.expose(), text);
+
+        // 顶层值作为环境变量，嵌套值为 JSON
+        let env = structured::decrypt_env(&encrypted, ConfigFormat::Yaml, "pw").unwrap();
+        assert_eq!(env[1].0, "allowed");
+        assert_eq!(env[1].1.expose(), r#"["10.0.0.1","10.0.0.2"]"#);
+
+        // 改动任何一个密文都会导致整篇验证失败
+        let tampered = encrypted.replacen("type:int", "type:str", 1);
+        assert!(structured::decrypt_document(&tampered, ConfigFormat::Yaml, "pw").is_err());
+    }
+
+    #[test]
+    fn test_git_filter_repository() {
+        use std::process::Command;
+
+        let git = |dir: &std::path::Path, args: &[&str]| {
+            let output = Command::new("git").args(args).current_dir(dir).output().expect("git");
+            assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+            output.stdout
+        };
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, clone) = (dir.path().join("repo"), dir.path().join("clone"));
+        let password_file = dir.path().join("repo.pass");
+        std::fs::write(&password_file, "repo password\n").unwrap();
+        let password_file = password_file.to_str().unwrap();
+        let exe = std::path::Path::new(env!("CARGO_BIN_EXE_dec"));
+        let kdf = dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 };
+
+        std::fs::create_dir_all(repo.join("secrets")).unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["config", "user.email", "dev@example.com"]);
+        git(&repo, &["config", "user.name", "dev"]);
+        dec::git_filter::init(&repo, exe, &["secrets/**".to_string()], ("--password-file", password_file), kdf).unwrap();
+
+        std::fs::write(repo.join("secrets/db.txt"), "password=hunter2\n").unwrap();
+        std::fs::write(repo.join("README"), "public\n").unwrap();
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-qm", "init"]);
+
+        // 仓库中是密文，其他文件不受影响
+        let blob = git(&repo, &["cat-file", "-p", "HEAD:secrets/db.txt"]);
+        assert!(blob.starts_with(b"DEC!"));
+        assert!(!blob.windows(7).any(|w| w == b"hunter2"));
+        assert_eq!(git(&repo, &["cat-file", "-p", "HEAD:README"]), b"public\n");
+
+        // 重新写入相同内容后不显示为已修改
+        std::fs::write(repo.join("secrets/db.txt"), "password=hunter2\n").unwrap();
+        git(&repo, &["update-index", "--really-refresh"]);
+        assert!(git(&repo, &["status", "--porcelain"]).is_empty());
+
+        // 克隆后配置同一密码，检出即为明文
+        git(dir.path(), &["clone", "-q", repo.to_str().unwrap(), clone.to_str().unwrap()]);
+        dec::git_filter::init(&clone, exe, &["secrets/**".to_string()], ("--password-file", password_file), kdf).unwrap();
+        std::fs::remove_file(clone.join("secrets/db.txt")).unwrap();
+        git(&clone, &["checkout", "--", "secrets/db.txt"]);
+        assert_eq!(std::fs::read_to_string(clone.join("secrets/db.txt")).unwrap(), "password=hunter2\n");
+        assert!(git(&clone, &["status", "--porcelain"]).is_empty());
+    }
+
+    #[test]
+    fn test_edit_command() {
+        use std::process::Command;
+
+        let dir = tempfile::tempdir().unwrap();
+        let temp_base = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("todo.txt");
+        let encrypted_path = dir.path().join("todo.txt.decx.asc");
+        std::fs::write(&plain_path, "buy milk\n").unwrap();
+
+        let options = dec::encryptor::EncryptOptions {
+            kdf: dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 },
+            armor: true,
+            ..Default::default()
+        };
+        let encrypted = encrypted_path.to_str().unwrap();
+        dec::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "pw", &options).unwrap();
+
+        // 编辑器追加一行后保存，仍为 ASCII 封装
+        let status = Command::new(env!("CARGO_BIN_EXE_dec"))
+            .args(["edit", encrypted, "-p", "pw"])
+            .env("VISUAL", "echo 'buy eggs' >>")
+            .env("XDG_RUNTIME_DIR", temp_base.path())
+            .status().unwrap();
+        assert!(status.success());
+        assert!(std::fs::read_to_string(&encrypted_path).unwrap().starts_with("-----BEGIN DEC ENCRYPTED FILE-----"));
+
+        let decrypted = dec::decryptor::decrypt_to_writer(encrypted, "pw", |_| Ok(Vec::new())).unwrap();
+        assert_eq!(decrypted, b"buy milk\nbuy eggs\n");
+
+        // 编辑器失败时退出码非零，原文件不变
+        let before = std::fs::read(&encrypted_path).unwrap();
+        let status = Command::new(env!("CARGO_BIN_EXE_dec"))
+            .args(["edit", encrypted, "-p", "pw"])
+            .env("VISUAL", "false")
+            .env("XDG_RUNTIME_DIR", temp_base.path())
+            .status().unwrap();
+        assert!(!status.success());
+        assert_eq!(std::fs::read(&encrypted_path).unwrap(), before);
+        assert_eq!(std::fs::read_dir(temp_base.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_decrypt_command_rejects_tampered_file() {
+        use std::process::{Command, Stdio};
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("report.txt");
+        let encrypted_path = dir.path().join("report.txt.decx");
+        let output_path = dir.path().join("out.txt");
+        let plaintext: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&plain_path, &plaintext).unwrap();
+
+        let options = dec::encryptor::EncryptOptions {
+            kdf: dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 },
+            metadata: true,
+            digest: Some(dec::digest::DigestAlgorithm::Sha256),
+            ..Default::default()
+        };
+        let encrypted = encrypted_path.to_str().unwrap();
+        dec::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "pw", &options).unwrap();
+        std::fs::remove_file(&plain_path).unwrap();
+
+        // 篡改靠近末尾的一个字节，大部分明文已写出后才校验失败
+        let mut data = std::fs::read(&encrypted_path).unwrap();
+        let len = data.len();
+        data[len - 100] ^= 1;
+        std::fs::write(&encrypted_path, &data).unwrap();
+
+        let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_dec"))
+            .args(args).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+            .status().unwrap();
+
+        // 失败时退出码非零，且不留下未通过校验的明文
+        assert!(!run(&["-d", encrypted, "-o", output_path.to_str().unwrap(), "-p", "pw", "-q"]).success());
+        assert!(!output_path.exists());
+
+        assert!(!run(&["-d", encrypted, "--restore-name", "-p", "pw", "-q"]).success());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // 密码错误时尚未写出，已有的输出文件保持原样
+        std::fs::write(&output_path, b"keep").unwrap();
+        assert!(!run(&["-d", encrypted, "-o", output_path.to_str().unwrap(), "-p", "wrong", "-q"]).success());
+        assert_eq!(std::fs::read(&output_path).unwrap(), b"keep");
+    }
+
+    #[test]
+    fn test_decrypt_range() {
+        use dec::compression::Compression;
+        use dec::encryptor::EncryptOptions;
+        use dec::padding::Padding;
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("app.log");
+        let plaintext: Vec<u8> = (0..50_000u32).flat_map(|i| format!("line {}\n", i).into_bytes()).collect();
+        std::fs::write(&plain_path, &plaintext).unwrap();
+
+        let kdf = dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 };
+        let variants = [
+            EncryptOptions { kdf, metadata: false, ..Default::default() },
+            EncryptOptions { kdf, metadata: true, padding: Some(Padding::Padme), armor: true, ..Default::default() },
+            EncryptOptions { kdf, compression: Some(Compression { level: 3, block_size: 4096 }), ..Default::default() },
+            EncryptOptions { kdf, compression: Some(Compression { level: 3, block_size: 4096 }), padding: Some(Padding::Bucket(1 << 20)), metadata: true, ..Default::default() },
+        ];
+        let length = plaintext.len() as u64;
+        let ranges = [0..0, 0..10, 4090..4100, 4096..8192, 12_345..300_000, length - 5..length, length - 5..u64::MAX, length..u64::MAX, length + 10..length + 20];
+
+        for (i, options) in variants.iter().enumerate() {
+            let encrypted_path = dir.path().join(format!("app.{}.decx", i));
+            let encrypted = encrypted_path.to_str().unwrap();
+            dec::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "pw", options).unwrap();
+
+            let full = dec::decryptor::decrypt_range_to_writer(encrypted, "pw", None, Vec::new()).unwrap();
+            assert_eq!(full, plaintext, "variant {}", i);
+
+            for range in ranges.iter().cloned() {
+                let expected = &plaintext[(range.start.min(length) as usize)..(range.end.min(length) as usize)];
+                let slice = dec::decryptor::decrypt_range_to_writer(encrypted, "pw", Some(range.clone()), Vec::new()).unwrap();
+                assert_eq!(slice, expected, "variant {} range {:?}", i, range);
+            }
+
+            assert!(dec::decryptor::decrypt_range_to_writer(encrypted, "wrong", Some(0..10), Vec::new()).is_err());
+        }
+
+        // 任何位置被改动都不输出明文
+        let encrypted_path = dir.path().join("app.0.decx");
+        let mut bytes = std::fs::read(&encrypted_path).unwrap();
+        let last = bytes.len() - 40;
+        bytes[last] ^= 1;
+        std::fs::write(&encrypted_path, &bytes).unwrap();
+        let mut output = Vec::new();
+        assert!(dec::decryptor::decrypt_range_to_writer(encrypted_path.to_str().unwrap(), "pw", Some(0..10), &mut output).is_err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_encrypt_with_verification() {
+        use dec::compression::Compression;
+        use dec::encryptor::{EncryptOptions, Verification};
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("data.bin");
+        let plaintext: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&plain_path, &plaintext).unwrap();
+
+        let kdf = dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 };
+        for (i, verify) in [Verification::Mac, Verification::Content].into_iter().enumerate() {
+            for armor in [false, true] {
+                let options = EncryptOptions { kdf, compression: Some(Compression::new(3)), armor, verify: Some(verify), ..Default::default() };
+                let encrypted_path = dir.path().join(format!("data.{}.{}.decx", i, armor));
+                let encrypted = encrypted_path.to_str().unwrap();
+                dec::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "pw", &options).unwrap();
+
+                let decrypted = dec::decryptor::decrypt_to_writer(encrypted, "pw", |_| Ok(Vec::new())).unwrap();
+                assert_eq!(decrypted, plaintext);
+            }
+        }
+    }
+
+    #[test]
+    fn test_plaintext_digest() {
+        use std::io::Write;
+        use dec::digest::DigestAlgorithm;
+        use dec::encryptor::{EncryptOptions, EncryptingWriter};
+        use dec::header::Header;
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("photo.raw");
+        let plaintext: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 256) as u8).collect();
+        std::fs::write(&plain_path, &plaintext).unwrap();
+
+        let kdf = dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 };
+        for (i, algorithm) in [DigestAlgorithm::Sha256, DigestAlgorithm::Blake3].into_iter().enumerate() {
+            let options = EncryptOptions { kdf, digest: Some(algorithm), padding: Some(dec::padding::Padding::Padme), ..Default::default() };
+            let encrypted_path = dir.path().join(format!("photo.{}.decx", i));
+            let encrypted = encrypted_path.to_str().unwrap();
+            dec::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "pw", &options).unwrap();
+
+            let decrypted_path = dir.path().join(format!("photo.{}.out", i));
+            dec::decryptor::decrypt_with_mode(encrypted, decrypted_path.to_str().unwrap(), "pw").unwrap();
+            assert_eq!(std::fs::read(&decrypted_path).unwrap(), plaintext);
+
+            // 摘要不算作内容
+            let tail = dec::decryptor::decrypt_range_to_writer(encrypted, "pw", Some(199_990..u64::MAX), Vec::new()).unwrap();
+            assert_eq!(tail, &plaintext[199_990..]);
+
+            // 验证后可读出摘要
+            let mut hasher = algorithm.hasher();
+            hasher.update(&plaintext);
+            let digest = dec::decryptor::read_digest(encrypted, "pw").unwrap().unwrap();
+            assert_eq!(digest, hasher.finalize());
+
+            let mut info = dec::info::inspect(encrypted).unwrap();
+            assert_eq!(info.to_json()["digest"]["value"], serde_json::Value::Null);
+            info.authenticate("pw").unwrap();
+            assert_eq!(info.to_json()["digest"]["value"], dec::digest::to_hex(&digest));
+            assert!(info.authenticate("wrong").is_err());
+        }
+
+        // HMAC 正确、摘要不符（如加密端的错误）时解密失败
+        let header = Header { digest: Some(DigestAlgorithm::Sha256), ..Header::new(kdf) };
+        let mut writer = EncryptingWriter::new(Vec::new(), "pw", header).unwrap();
+        writer.write_all(b"content").unwrap();
+        writer.write_all(&[0u8; 32]).unwrap();
+        let encrypted = writer.finish().unwrap();
+        let error = dec::decryptor::decrypt_bytes(encrypted, "pw").unwrap_err();
+        assert!(error.to_string().contains("sha256"), "{}", error);
+    }
+
+    #[test]
+    fn test_parity_repair() {
+        use dec::encryptor::EncryptOptions;
+        use dec::parity::{self, Parity, RepairOutcome};
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("archive.tar");
+        let plaintext: Vec<u8> = (0..1_000_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        std::fs::write(&plain_path, &plaintext).unwrap();
+
+        let kdf = dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 };
+        let options = EncryptOptions {
+            kdf,
+            parity: Some(Parity::with_percent(10).unwrap()),
+            digest: Some(dec::digest::DigestAlgorithm::Sha256),
+            ..Default::default()
+        };
+        let encrypted_path = dir.path().join("archive.tar.decx");
+        let encrypted = encrypted_path.to_str().unwrap();
+        dec::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), encrypted, "pw", &options).unwrap();
+        let clean = std::fs::read(&encrypted_path).unwrap();
+        assert_eq!(parity::detect(&encrypted_path).unwrap(), options.parity);
+        assert!(dec::info::inspect(encrypted).unwrap().to_string().contains("reed-solomon 64+7"));
+
+        // 位翻转与整块清零：文件头所在的第一段、中间与最后一段
+        let mut damaged = clean.clone();
+        for offset in [30, 100_000, 500_000, 500_001, damaged.len() - 200] {
+            damaged[offset] ^= 0x10;
+        }
+        damaged[700_000..704_096].fill(0);
+        std::fs::write(&encrypted_path, &damaged).unwrap();
+
+        // 读取时先修复，再校验 HMAC 与明文摘要
+        dec::decryptor::verify_with_password(encrypted, "pw").unwrap();
+        let decrypted_path = dir.path().join("archive.out");
+        dec::decryptor::decrypt_with_mode(encrypted, decrypted_path.to_str().unwrap(), "pw").unwrap();
+        assert_eq!(std::fs::read(&decrypted_path).unwrap(), plaintext);
+        let range = dec::decryptor::decrypt_range_to_writer(encrypted, "pw", Some(654_321..654_400), Vec::new()).unwrap();
+        assert_eq!(range, &plaintext[654_321..654_400]);
+
+        // 修复后与原样写出的文件逐字节相同
+        match parity::repair_file(encrypted, None).unwrap() {
+            RepairOutcome::Repaired { shards, .. } => assert!(shards >= 5),
+            RepairOutcome::Intact => panic!("damage not found"),
+        }
+        assert_eq!(std::fs::read(&encrypted_path).unwrap(), clean);
+        assert_eq!(parity::repair_file(encrypted, None).unwrap(), RepairOutcome::Intact);
+
+        // 升级保留纠错封装
+        dec::upgrader::upgrade_file(encrypted, "pw", None, &EncryptOptions { kdf, ..Default::default() }, true).unwrap();
+        assert_eq!(parity::detect(&encrypted_path).unwrap(), options.parity);
+        dec::decryptor::verify_with_password(encrypted, "pw").unwrap();
+
+        // 一段中损坏的块超过校验块数时无法修复，原文件不变
+        let mut damaged = std::fs::read(&encrypted_path).unwrap();
+        damaged[20_000..60_000].fill(0xff);
+        std::fs::write(&encrypted_path, &damaged).unwrap();
+        assert!(dec::decryptor::verify_with_password(encrypted, "pw").is_err());
+        assert!(parity::repair_file(encrypted, None).is_err());
+        assert_eq!(std::fs::read(&encrypted_path).unwrap(), damaged);
+    }
+
+    #[test]
+    fn test_split_volumes() {
+        use dec::encryptor::{EncryptOptions, Verification};
+        use dec::volume::volume_path;
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("backup.tar");
+        // 不可压缩的数据，压缩后仍跨越多卷
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let plaintext: Vec<u8> = (0..300_000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }).collect();
+        std::fs::write(&plain_path, &plaintext).unwrap();
+
+        let kdf = dec::key_derivation::Kdf::Pbkdf2Sha256 { iterations: 1000 };
+        let base = dir.path().join("backup.tar.decx").to_str().unwrap().to_string();
+        let first = volume_path(&base, 1);
+
+        for options in [
+            EncryptOptions { kdf, split: Some(64 * 1024), verify: Some(Verification::Content), ..Default::default() },
+            EncryptOptions { kdf, split: Some(64 * 1024), compression: Some(dec::compression::Compression::new(3)), ..Default::default() },
+            EncryptOptions { kdf, split: Some(100 * 1024), parity: Some(dec::parity::Parity::with_percent(10).unwrap()), ..Default::default() },
+        ] {
+            dec::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), &base, "pw", &options).unwrap();
+            assert!(!std::path::Path::new(&base).exists());
+            assert!(std::fs::metadata(&first).unwrap().len() <= options.split.unwrap());
+
+            let decrypted_path = dir.path().join("backup.out");
+            dec::decryptor::decrypt_with_mode(&first, decrypted_path.to_str().unwrap(), "pw").unwrap();
+            assert_eq!(std::fs::read(&decrypted_path).unwrap(), plaintext);
+            let range = dec::decryptor::decrypt_range_to_writer(&volume_path(&base, 2), "pw", Some(123_456..123_500), Vec::new()).unwrap();
+            assert_eq!(range, &plaintext[123_456..123_500]);
+            assert!(dec::info::inspect(&first).unwrap().volumes.unwrap() >= 3);
+        }
+
+        // 带纠错数据时，损坏的卷在读取时修复
+        let second = volume_path(&base, 2);
+        let third = volume_path(&base, 3);
+        let mut damaged = std::fs::read(&second).unwrap();
+        damaged[5000] ^= 0x80;
+        std::fs::write(&second, &damaged).unwrap();
+        let decrypted_path = dir.path().join("backup.out");
+        dec::decryptor::decrypt_with_mode(&first, decrypted_path.to_str().unwrap(), "pw").unwrap();
+        assert_eq!(std::fs::read(&decrypted_path).unwrap(), plaintext);
+
+        // dec repair 按原来的卷大小重新写出整组：先写副本，再原地修复
+        use dec::parity::{repair_file, RepairOutcome};
+        let sizes: Vec<u64> = (1..=3).map(|i| std::fs::metadata(volume_path(&base, i)).unwrap().len()).collect();
+        let copy_base = dir.path().join("copy.decx").to_str().unwrap().to_string();
+        let outcome = repair_file(&second, Some(&copy_base)).unwrap();
+        assert!(matches!(outcome, RepairOutcome::Repaired { segments: 1, shards: 1, envelope: false }), "{:?}", outcome);
+        assert_eq!(std::fs::read(&second).unwrap(), damaged);
+        dec::volume::VolumeReader::open(std::path::Path::new(&volume_path(&copy_base, 1))).unwrap().check().unwrap();
+
+        repair_file(&first, None).unwrap();
+        for (index, size) in (1..=3).zip(&sizes) {
+            assert_eq!(std::fs::metadata(volume_path(&base, index)).unwrap().len(), *size);
+        }
+        dec::volume::VolumeReader::open(std::path::Path::new(&first)).unwrap().check().unwrap();
+        assert_eq!(repair_file(&third, None).unwrap(), RepairOutcome::Intact);
+        dec::decryptor::decrypt_with_mode(&first, decrypted_path.to_str().unwrap(), "pw").unwrap();
+        assert_eq!(std::fs::read(&decrypted_path).unwrap(), plaintext);
+
+        // 没有纠错数据时，损坏、缺失与改名调换都指出是哪一卷（压缩的内容可能先在解压时出错）
+        let options = EncryptOptions { kdf, split: Some(64 * 1024), compression: Some(dec::compression::Compression::new(3)), ..Default::default() };
+        dec::encryptor::encrypt_with_mode(plain_path.to_str().unwrap(), &base, "pw", &options).unwrap();
+        assert!(repair_file(&first, None).unwrap_err().to_string().contains("没有纠错数据"));
+        let original = std::fs::read(&second).unwrap();
+        let mut damaged = original.clone();
+        damaged[5000] ^= 0x80;
+        std::fs::write(&second, &damaged).unwrap();
+        let error = dec::decryptor::decrypt_with_mode(&first, decrypted_path.to_str().unwrap(), "pw").unwrap_err();
+        assert!(error.to_string().contains("第 2 卷已损坏"), "{}", error);
+        assert!(dec::decryptor::verify_with_password(&first, "pw").unwrap_err().to_string().contains("第 2 卷已损坏"));
+
+        std::fs::rename(&second, dir.path().join("moved")).unwrap();
+        let error = dec::decryptor::verify_with_password(&first, "pw").unwrap_err();
+        assert!(error.to_string().contains("缺少第 2 卷"), "{}", error);
+
+        std::fs::write(&second, &original).unwrap();
+        std::fs::rename(&third, dir.path().join("moved")).unwrap();
+        std::fs::rename(&second, &third).unwrap();
+        std::fs::rename(dir.path().join("moved"), &second).unwrap();
+        let error = dec::decryptor::verify_with_password(&first, "pw").unwrap_err();
+        assert!(error.to_string().contains("分卷顺序错乱"), "{}", error);
+
+        // 分卷不能原地改写
+        let options = EncryptOptions { kdf, ..Default::default() };
+        assert!(dec::upgrader::upgrade_file(&first, "pw", None, &options, true).is_err());
+    }
+}